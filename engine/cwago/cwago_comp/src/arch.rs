@@ -0,0 +1,592 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/src/arch.rs
+// (C) 2023 CwagoCommunity.
+//
+//! アーキタイプを提供します。
+//! 
+//! アーキタイプはデータの種類の組み合わせ毎に1つのチャンクを持ちます。
+//! データを追加、または、削除したエンティティは組み合わせが一致するアーキタイプに移ります。
+//! 組み合わせは型情報の索引のビット集合で表し、同じ集合のアーキタイプは1つです。
+//! 移動先はアーキタイプ毎に記録するため、同じ変更では組み合わせを引き直しません。
+// =========================
+
+use std::{
+    any::TypeId, 
+    mem::replace
+};
+
+use cwago_utility::{
+    bitset::BitSet, 
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result, 
+        ResultExt
+    }, 
+    hash::FxHashMap, 
+    msg
+};
+
+use crate::{
+    chunk::Chunk, 
+    data::Data, 
+    ent::{
+        Entities, 
+        Id, 
+        Location
+    }, 
+    messages::ENTITY_NOT_FOUND, 
+    ty::Info
+};
+
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    thread_local! {
+        // ドロップされた値の合計です。
+        static DROPPED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.arch.pos", no_serialize)]
+    struct Pos(i32);
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.arch.vel", no_serialize)]
+    struct Vel(i32);
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.arch.life", no_serialize)]
+    struct Life(u32);
+    impl Drop for Life {
+        fn drop(&mut self) {
+            DROPPED.with(|d| d.set(d.get() + self.0));
+        }
+    }
+
+    fn dropped() -> u32 {
+        DROPPED.with(|d| d.replace(0))
+    }
+
+    #[test]
+    fn test_get_or_insert() {
+        let mut archs = Archetypes::new();
+        assert_eq!(archs.count(), 1);
+        assert!(archs.get(EMPTY).unwrap().infos().is_empty());
+
+        let a = archs.get_or_insert(&[Pos::info(), Vel::info()]);
+        let b = archs.get_or_insert(&[Vel::info(), Pos::info()]);
+        assert_eq!(a, b, "順序が異なる同じ組み合わせは同じアーキタイプです。");
+        assert_eq!(archs.count(), 2);
+        assert_eq!(archs.find(&[Vel::info(), Pos::info()]), Some(a));
+        assert_eq!(archs.find(&[Vel::info()]), None);
+        let mask: BitSet = [Pos::info().index(), Vel::info().index()].into_iter().collect();
+        assert_eq!(archs.get(a).unwrap().mask(), &mask);
+        assert!(archs.get(a).unwrap().contains(TypeId::of::<Pos>()));
+        assert!(!archs.get(a).unwrap().contains(TypeId::of::<Life>()));
+
+        let e = archs.try_get_or_insert(&[Pos::info(), Pos::info()]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+        assert_eq!(archs.count(), 2);
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut archs = Archetypes::new();
+        let pos = archs.added(EMPTY, Pos::info());
+        let both = archs.added(pos, Vel::info());
+        assert_eq!(archs.find(&[Pos::info(), Vel::info()]), Some(both));
+        assert_eq!(archs.added(both, Pos::info()), both, "既に持つデータの追加は移動しません。");
+
+        // 辿った辺は両方向に記録されます。
+        assert_eq!(archs.archs[pos].add.get(&TypeId::of::<Vel>()), Some(&both));
+        assert_eq!(archs.archs[both].remove.get(&TypeId::of::<Vel>()), Some(&pos));
+        assert_eq!(archs.removed(both, TypeId::of::<Vel>()), Some(pos));
+        assert_eq!(archs.removed(pos, TypeId::of::<Vel>()), None);
+
+        // 別の経路で辿っても同じアーキタイプです。
+        let vel = archs.removed(both, TypeId::of::<Pos>()).unwrap();
+        assert_eq!(archs.added(vel, Pos::info()), both);
+        assert_eq!(archs.removed(vel, TypeId::of::<Vel>()), Some(EMPTY));
+        assert_eq!(archs.count(), 4);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        dropped();
+        let mut entities = Entities::new();
+        let mut archs = Archetypes::new();
+        let a = archs.spawn(&mut entities);
+        let b = archs.spawn(&mut entities);
+        let c = archs.spawn(&mut entities);
+        for (i, id) in [a, b, c].into_iter().enumerate() {
+            assert_eq!(archs.insert(&mut entities, id, Pos(i as i32)), None);
+            assert_eq!(archs.insert(&mut entities, id, Life(1)), None);
+        }
+        assert_eq!(archs.insert(&mut entities, a, Pos(10)), Some(Pos(0)), "既に持つデータは置き換えます。");
+        assert_eq!(archs.get(EMPTY).unwrap().len(), 0);
+
+        // 先頭のエンティティを移すと、最後のエンティティが空いた行に移ります。
+        assert_eq!(archs.insert(&mut entities, a, Vel(5)), None);
+        let loc = entities.location(c).unwrap();
+        assert_eq!(archs.get(loc.chunk).unwrap().chunk().ids()[loc.row], c);
+        assert_eq!(archs.get_data::<Pos>(&entities, c), Some(&Pos(2)));
+        assert_eq!(archs.get_data::<Pos>(&entities, a), Some(&Pos(10)));
+        assert_eq!(archs.get_data::<Vel>(&entities, a), Some(&Vel(5)));
+        assert_eq!(archs.get_data::<Vel>(&entities, b), None);
+        archs.get_data_mut::<Vel>(&entities, a).unwrap().0 += 1;
+
+        assert_eq!(archs.remove::<Pos>(&mut entities, a), Some(Pos(10)));
+        assert_eq!(archs.remove::<Pos>(&mut entities, a), None);
+        assert_eq!(archs.get_data::<Vel>(&entities, a), Some(&Vel(6)));
+        assert_eq!(dropped(), 0, "移動した値をドロップしてはいけません。");
+
+        assert!(archs.despawn(&mut entities, b));
+        assert!(!archs.despawn(&mut entities, b));
+        assert_eq!(dropped(), 1);
+        assert_eq!(archs.get_data::<Pos>(&entities, c), Some(&Pos(2)));
+        assert_eq!(archs.try_insert(&mut entities, b, Pos(0)).unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(archs.remove::<Pos>(&mut entities, b), None);
+
+        for id in [a, c] {
+            let loc = entities.location(id).unwrap();
+            assert_eq!(archs.get(loc.chunk).unwrap().chunk().ids()[loc.row], id);
+        }
+        drop(archs);
+        assert_eq!(dropped(), 2);
+    }
+}
+
+/// データの種類を持たないアーキタイプの添え字です。
+pub const EMPTY: usize = 0;
+
+/// データの種類の組み合わせと、そのエンティティを格納するチャンクです。
+pub struct Arch {
+    infos: Box<[&'static Info]>,      // 型の識別子順のデータの型情報です。
+    mask: BitSet,                     // データの型情報の索引の集合です。
+    chunk: Chunk,                     // エンティティとデータです。
+    add: FxHashMap<TypeId, usize>,    // データを追加した場合の移動先です。
+    remove: FxHashMap<TypeId, usize>, // データを削除した場合の移動先です。
+}
+impl Arch {
+    /// データの型情報を取得します。型の識別子順です。
+    pub fn infos(&self) -> &[&'static Info] {
+        &self.infos
+    }
+
+    /// データの型情報の索引の集合を取得します。
+    pub fn mask(&self) -> &BitSet {
+        &self.mask
+    }
+
+    /// データの種類を持つかを取得します。
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.infos.binary_search_by_key(&id, |info| info.type_id()).is_ok()
+    }
+
+    /// チャンクを取得します。
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// エンティティの数を取得します。
+    pub fn len(&self) -> usize {
+        self.chunk.len()
+    }
+
+    /// エンティティが無いかを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.chunk.is_empty()
+    }
+}
+
+/// アーキタイプの集まりです。
+/// 
+/// エンティティのデータの位置の`chunk`はアーキタイプの添え字です。
+/// アーキタイプは削除しないため、添え字は変わりません。
+/// 
+pub struct Archetypes {
+    archs: Vec<Arch>,                   // 作成順のアーキタイプです。
+    by_mask: FxHashMap<BitSet, usize>,  // 型情報の索引の集合からアーキタイプを引きます。
+}
+impl Archetypes {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// データの種類を持たないアーキタイプのみのインスタンスです。
+    /// 
+    pub fn new() -> Archetypes {
+        let mut archs = Archetypes { archs: Vec::new(), by_mask: FxHashMap::default() };
+        archs.get_or_insert(&[]);
+        archs
+    }
+
+    /// アーキタイプの数を取得します。
+    /// 
+    /// アーキタイプは増えるのみのため、数の変化で新しいアーキタイプを検知できます。
+    /// 
+    pub fn count(&self) -> usize {
+        self.archs.len()
+    }
+
+    /// アーキタイプを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `index` - アーキタイプの添え字です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// アーキタイプ、範囲外の場合はNoneです。
+    /// 
+    pub fn get(&self, index: usize) -> Option<&Arch> {
+        self.archs.get(index)
+    }
+
+    /// アーキタイプを作成順に走査します。
+    pub fn iter(&self) -> impl Iterator<Item = &Arch> + '_ {
+        self.archs.iter()
+    }
+
+    /// データの種類の組み合わせからアーキタイプを探します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - データの型情報です。順序は問いません。
+    /// 
+    /// # 戻り値
+    /// 
+    /// アーキタイプの添え字、無い場合はNoneです。
+    /// 
+    pub fn find(&self, infos: &[&'static Info]) -> Option<usize> {
+        let mask: BitSet = infos.iter().map(|info| info.index()).collect();
+        self.by_mask.get(&mask).copied()
+    }
+
+    /// データの種類の組み合わせのアーキタイプを取得し、無い場合は作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - データの型情報です。順序は問いません。
+    /// 
+    /// # 戻り値
+    /// 
+    /// アーキタイプの添え字です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 同じ型が重複している場合に異常終了します。
+    /// 
+    pub fn get_or_insert(&mut self, infos: &[&'static Info]) -> usize {
+        self.try_get_or_insert(infos).or_abort()
+    }
+
+    /// データの種類の組み合わせのアーキタイプを取得し、無い場合は作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - データの型情報です。順序は問いません。
+    /// 
+    /// # 戻り値
+    /// 
+    /// アーキタイプの添え字、同じ型が重複している場合はエラーです。
+    /// 
+    pub fn try_get_or_insert(&mut self, infos: &[&'static Info]) -> Result<usize> {
+        let mask: BitSet = infos.iter().map(|info| info.index()).collect();
+        // 重複がある場合は引かずに、チャンクの作成でエラーにします。
+        if mask.len() == infos.len() {
+            if let Some(&index) = self.by_mask.get(&mask) {
+                return Ok(index);
+            }
+        }
+        let mut infos = infos.to_vec();
+        infos.sort_unstable_by_key(|info| info.type_id());
+        let chunk = Chunk::try_new(&infos)?;
+        let index = self.archs.len();
+        self.archs.push(Arch {
+            infos: infos.into_boxed_slice(),
+            mask: mask.clone(),
+            chunk,
+            add: FxHashMap::default(),
+            remove: FxHashMap::default(),
+        });
+        self.by_mask.insert(mask, index);
+        cwago_utility::profile_counter!("archetypes", self.archs.len());
+        Ok(index)
+    }
+
+    /// データを追加した場合の移動先を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `index` - 移動元のアーキタイプの添え字です。
+    /// * `info` - 追加するデータの型情報です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 移動先のアーキタイプの添え字です。既に持つ場合は`index`です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 添え字が範囲外の場合に異常終了します。
+    /// 
+    pub fn added(&mut self, index: usize, info: &'static Info) -> usize {
+        let id = info.type_id();
+        if let Some(&target) = self.archs[index].add.get(&id) {
+            return target;
+        }
+        if self.archs[index].mask.contains(info.index()) {
+            return index;
+        }
+        let mut infos = self.archs[index].infos.to_vec();
+        infos.push(info);
+        // 重複しないことは確認済みです。
+        let target = self.get_or_insert(&infos);
+        self.link(index, target, id);
+        target
+    }
+
+    /// データを削除した場合の移動先を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `index` - 移動元のアーキタイプの添え字です。
+    /// * `id` - 削除するデータの型の識別子です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 移動先のアーキタイプの添え字、データを持たない場合はNoneです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 添え字が範囲外の場合に異常終了します。
+    /// 
+    pub fn removed(&mut self, index: usize, id: TypeId) -> Option<usize> {
+        if let Some(&target) = self.archs[index].remove.get(&id) {
+            return Some(target);
+        }
+        if !self.archs[index].contains(id) {
+            return None;
+        }
+        let infos: Vec<&'static Info> = self.archs[index].infos.iter()
+            .copied()
+            .filter(|info| info.type_id() != id)
+            .collect();
+        let target = self.get_or_insert(&infos);
+        self.link(target, index, id);
+        Some(target)
+    }
+
+    // データを追加する辺と、逆向きの削除する辺を記録します。
+    fn link(&mut self, without: usize, with: usize, id: TypeId) {
+        self.archs[without].add.insert(id, with);
+        self.archs[with].remove.insert(id, without);
+    }
+
+    /// データを持たないエンティティを作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// エンティティIdです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// エンティティIdを割り当てられない場合に異常終了します。
+    /// 
+    pub fn spawn(&mut self, entities: &mut Entities) -> Id {
+        self.try_spawn(entities).or_abort()
+    }
+
+    /// データを持たないエンティティを作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// エンティティId、割り当てられない場合はエラーです。
+    /// 
+    pub fn try_spawn(&mut self, entities: &mut Entities) -> Result<Id> {
+        let chunk = &mut self.archs[EMPTY].chunk;
+        let id = entities.try_spawn(Location { chunk: EMPTY, row: chunk.len() })?;
+        chunk.push_default(id);
+        Ok(id)
+    }
+
+    /// エンティティとデータを削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - 削除するエンティティIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した場合はtrue、無効なIdの場合はfalseです。
+    /// 
+    pub fn despawn(&mut self, entities: &mut Entities, id: Id) -> bool {
+        let Some(loc) = entities.despawn(id) else {
+            return false;
+        };
+        self.archs[loc.chunk].chunk.swap_remove(loc.row, entities);
+        true
+    }
+
+    /// エンティティのデータを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - エンティティIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// データ、無効なId、または、データを持たない場合はNoneです。
+    /// 
+    pub fn get_data<D: Data>(&self, entities: &Entities, id: Id) -> Option<&D> {
+        let loc = entities.location(id)?;
+        self.archs[loc.chunk].chunk.get(loc.row)
+    }
+
+    /// エンティティのデータを可変で取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - エンティティIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// データ、無効なId、または、データを持たない場合はNoneです。
+    /// 
+    pub fn get_data_mut<D: Data>(&mut self, entities: &Entities, id: Id) -> Option<&mut D> {
+        let loc = entities.location(id)?;
+        self.archs[loc.chunk].chunk.get_mut(loc.row)
+    }
+
+    /// エンティティにデータを追加します。
+    /// 
+    /// 既に持つ場合は値を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - エンティティIdです。
+    /// * `value` - 追加するデータです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 置き換えた以前の値、新たに追加した場合はNoneです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 無効なIdの場合に異常終了します。
+    /// 
+    pub fn insert<D: Data>(&mut self, entities: &mut Entities, id: Id, value: D) -> Option<D> {
+        self.try_insert(entities, id, value).or_abort()
+    }
+
+    /// エンティティにデータを追加します。
+    /// 
+    /// 既に持つ場合は値を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - エンティティIdです。
+    /// * `value` - 追加するデータです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 置き換えた以前の値、新たに追加した場合はNone、無効なIdの場合はエラーです。
+    /// 
+    pub fn try_insert<D: Data>(&mut self, entities: &mut Entities, id: Id, value: D) -> Result<Option<D>> {
+        let Some(loc) = entities.location(id) else {
+            return Err(CwagoError::new(ErrorKind::NotFound, msg!(ENTITY_NOT_FOUND, id = id)));
+        };
+        if let Some(old) = self.archs[loc.chunk].chunk.get_mut::<D>(loc.row) {
+            return Ok(Some(replace(old, value)));
+        }
+        let target = self.added(loc.chunk, D::info());
+        let mut value = Some(value);
+        // Safety: 移動先だけにある列は追加するデータの列のみで、移動元だけにある列はありません。
+        unsafe {
+            self.move_entity(entities, id, loc, target, |_, ptr| {
+                (ptr as *mut D).write(value.take().unwrap());
+            }, |_, _| ());
+        }
+        Ok(None)
+    }
+
+    /// エンティティからデータを削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entities` - エンティティの割り当てです。
+    /// * `id` - エンティティIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した値、無効なId、または、データを持たない場合はNoneです。
+    /// 
+    pub fn remove<D: Data>(&mut self, entities: &mut Entities, id: Id) -> Option<D> {
+        let loc = entities.location(id)?;
+        let target = self.removed(loc.chunk, TypeId::of::<D>())?;
+        let mut value = None;
+        // Safety: 移動元だけにある列は削除するデータの列のみで、移動先だけにある列はありません。
+        unsafe {
+            self.move_entity(entities, id, loc, target, |info, _| {
+                unreachable!("no column of '{}' should be initialized", info.name())
+            }, |_, ptr| {
+                value = Some((ptr as *mut D).read());
+            });
+        }
+        value
+    }
+
+    // エンティティを別のアーキタイプに移します。
+    // Safety: Chunk::move_rowと同じです。
+    unsafe fn move_entity(
+        &mut self,
+        entities: &mut Entities,
+        id: Id,
+        loc: Location,
+        target: usize,
+        init: impl FnMut(&'static Info, *mut u8),
+        take: impl FnMut(&'static Info, *mut u8)
+    ) {
+        let (src, dst) = pair_mut(&mut self.archs, loc.chunk, target);
+        let row = src.chunk.move_row(loc.row, &mut dst.chunk, entities, init, take);
+        entities.set_location(id, Location { chunk: target, row });
+    }
+}
+impl Default for Archetypes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 異なる2つの要素を可変で取得します。
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b);
+    if a < b {
+        let (l, r) = items.split_at_mut(b);
+        (&mut l[a], &mut r[0])
+    } else {
+        let (l, r) = items.split_at_mut(a);
+        (&mut r[0], &mut l[b])
+    }
+}
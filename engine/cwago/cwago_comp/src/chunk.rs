@@ -6,23 +6,768 @@
 // (C) 2023 CwagoCommunity.
 //
 //! チャンクを提供します。
+//! 
+//! チャンクは同じ種類のデータを持つエンティティを、データの種類毎の列に並べて格納します。
+//! 行を削除すると最後の行が削除した行に移るため、移ったエンティティの位置を更新します。
 // =========================
 
-use std::any::TypeId;
+use std::{
+    alloc::{
+        alloc, 
+        dealloc, 
+        handle_alloc_error, 
+        realloc, 
+        Layout
+    }, 
+    any::TypeId, 
+    ptr::{
+        self, 
+        NonNull
+    }, 
+    slice
+};
 
-use cwago_utility::hash::FxHashMap;
+use cwago_utility::{
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result, 
+        ResultExt
+    }, 
+    hash::FxHashMap, 
+    msg
+};
 
 use crate::{
-    ent::Id, 
-    ty::Info
+    ent::{
+        Entities, 
+        Id, 
+        Location
+    }, 
+    messages::CHUNK_DUPLICATED_COLUMN, 
+    ty::{
+        drop_buf, 
+        initialize_buf, 
+        move_buf, 
+        Info
+    }
 };
 
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use std::{
+        rc::Rc, 
+        cell::Cell
+    };
+
+    use super::*;
+    use crate::data::Data;
+
+    thread_local! {
+        // ドロップされた値の合計です。
+        static DROPPED: Cell<u64> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.chunk.tracked", no_serialize)]
+    struct Tracked(u64, Option<Rc<()>>);
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DROPPED.with(|d| d.set(d.get() + self.0));
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.chunk.name", no_serialize)]
+    struct Label(String);
+
+    fn dropped() -> u64 {
+        DROPPED.with(|d| d.replace(0))
+    }
+
+    // 値を指定して行を追加します。
+    fn push(chunk: &mut Chunk, entities: &mut Entities, value: u64, label: &str) -> Id {
+        let id = entities.spawn(Location { chunk: 0, row: chunk.len() });
+        let mut tracked = Some(Tracked(value, None));
+        let mut label = Some(Label(label.to_string()));
+        unsafe {
+            chunk.push_with(id, |info, ptr| {
+                if info.type_id() == TypeId::of::<Tracked>() {
+                    (ptr as *mut Tracked).write(tracked.take().unwrap());
+                } else {
+                    (ptr as *mut Label).write(label.take().unwrap());
+                }
+            });
+        }
+        id
+    }
+
+    fn chunk() -> Chunk {
+        Chunk::new(&[Tracked::info(), Label::info()])
+    }
+
+    #[test]
+    fn test_push_get() {
+        dropped();
+        let mut entities = Entities::new();
+        let mut chunk = chunk();
+        assert!(chunk.is_empty());
+        let a = push(&mut chunk, &mut entities, 1, "a");
+        let b = push(&mut chunk, &mut entities, 2, "b");
+        assert_eq!(chunk.len(), 2);
+        assert_eq!(chunk.ids(), [a, b]);
+        assert!(chunk.contains(TypeId::of::<Label>()));
+        assert!(!chunk.contains(TypeId::of::<u32>()));
+        assert_eq!(chunk.column::<Tracked>().unwrap().iter().map(|t| t.0).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(chunk.get::<Label>(1).unwrap().0, "b");
+        assert!(chunk.get::<Label>(2).is_none());
+        assert!(chunk.column::<u32>().is_none());
+
+        chunk.get_mut::<Label>(0).unwrap().0.push('!');
+        chunk.column_mut::<Tracked>().unwrap()[1].0 = 20;
+        let label = chunk.get_ptr(TypeId::of::<Label>(), 0).unwrap() as *const Label;
+        assert_eq!(unsafe { &*label }.0, "a!");
+        assert!(chunk.get_ptr(TypeId::of::<Label>(), 2).is_none());
+
+        let row = chunk.push_default(entities.spawn(Location::default()));
+        assert_eq!(chunk.get::<Tracked>(row), Some(&Tracked::default()));
+        drop(chunk);
+        assert_eq!(dropped(), 21, "すべての値がドロップされる必要があります。");
+    }
+
+    #[test]
+    fn test_swap_remove() {
+        dropped();
+        let mut entities = Entities::new();
+        let mut chunk = chunk();
+        let ids: Vec<Id> = (1..=4).map(|i| push(&mut chunk, &mut entities, i, &i.to_string())).collect();
+
+        // 途中の行を削除すると、最後の行が移ります。
+        assert_eq!(chunk.swap_remove(1, &mut entities), ids[1]);
+        assert_eq!(dropped(), 2);
+        assert_eq!(chunk.ids(), [ids[0], ids[3], ids[2]]);
+        assert_eq!(entities.location(ids[3]), Some(Location { chunk: 0, row: 1 }));
+        assert_eq!(chunk.get::<Label>(1).unwrap().0, "4");
+        assert_eq!(chunk.get::<Tracked>(1).unwrap().0, 4);
+
+        // 最後の行は移動しません。
+        assert_eq!(chunk.swap_remove(2, &mut entities), ids[2]);
+        assert_eq!(dropped(), 3);
+        assert_eq!(entities.location(ids[0]), Some(Location { chunk: 0, row: 0 }));
+
+        assert_eq!(chunk.swap_remove(0, &mut entities), ids[0]);
+        assert_eq!(chunk.swap_remove(0, &mut entities), ids[3]);
+        assert!(chunk.is_empty());
+        assert_eq!(dropped(), 5);
+        drop(chunk);
+        assert_eq!(dropped(), 0, "削除済みの値を再びドロップしてはいけません。");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_swap_remove_empty() {
+        let mut entities = Entities::new();
+        let mut chunk = chunk();
+        chunk.swap_remove(0, &mut entities);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_swap_remove_out_of_range() {
+        let mut entities = Entities::new();
+        let mut chunk = chunk();
+        push(&mut chunk, &mut entities, 1, "a");
+        chunk.swap_remove(1, &mut entities);
+    }
+
+    #[test]
+    fn test_swap_remove_with() {
+        dropped();
+        let mut entities = Entities::new();
+        let mut chunk = chunk();
+        push(&mut chunk, &mut entities, 1, "a");
+        push(&mut chunk, &mut entities, 2, "b");
+
+        // 値を取り出した列はドロップされません。
+        let mut taken = None;
+        unsafe {
+            chunk.swap_remove_with(0, &mut entities, |info, ptr| {
+                if info.type_id() == TypeId::of::<Label>() {
+                    taken = Some((ptr as *mut Label).read());
+                } else {
+                    drop_buf(info, ptr);
+                }
+            });
+        }
+        assert_eq!(taken, Some(Label("a".to_string())));
+        assert_eq!(dropped(), 1);
+        assert_eq!(chunk.get::<Label>(0).unwrap().0, "b");
+    }
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.chunk.simd", no_serialize)]
+    #[repr(align(64))]
+    struct Simd([f32; 4]);
+
+    #[derive(Default, Data)]
+    #[data(name = "test.chunk.marker", tag, no_serialize)]
+    struct Marker;
+
+    // ドロップを数える型サイズ0の型です。
+    #[derive(Default, Data)]
+    #[data(name = "test.chunk.unit", no_serialize)]
+    struct Unit;
+    impl Drop for Unit {
+        fn drop(&mut self) {
+            DROPPED.with(|d| d.set(d.get() + 100));
+        }
+    }
+
+    #[test]
+    fn test_align() {
+        dropped();
+        assert_eq!(Simd::info().align(), 64);
+        assert_eq!(Unit::info().size(), 0);
+        let mut entities = Entities::new();
+        let mut chunk = Chunk::new(&[Simd::info(), Marker::info(), Unit::info()]);
+        let ids: Vec<Id> = (0..100)
+            .map(|i| {
+                let id = entities.spawn(Location { chunk: 0, row: i });
+                chunk.push_default(id);
+                chunk.get_mut::<Simd>(i).unwrap().0 = [i as f32; 4];
+                id
+            })
+            .collect();
+
+        // 再確保した後も、すべての行が型のアラインメントに従います。
+        for row in 0..chunk.len() {
+            for (id, align) in [(TypeId::of::<Simd>(), 64), (TypeId::of::<Marker>(), 1), (TypeId::of::<Unit>(), 1)] {
+                let ptr = chunk.get_ptr(id, row).unwrap();
+                assert_eq!(ptr as usize % align, 0, "{}行目の値がアラインメントに従っていません。", row);
+            }
+        }
+        assert_eq!(chunk.column::<Marker>().unwrap().len(), 100);
+        assert_eq!(chunk.column::<Unit>().unwrap().len(), 100);
+
+        assert_eq!(chunk.swap_remove(10, &mut entities), ids[10]);
+        assert_eq!(dropped(), 100, "型サイズ0の値もドロップされる必要があります。");
+        assert_eq!(chunk.get::<Simd>(10), Some(&Simd([99.0; 4])));
+        assert_eq!(entities.location(ids[99]), Some(Location { chunk: 0, row: 10 }));
+
+        chunk.shrink_to_fit();
+        assert_eq!(chunk.capacity(), 99);
+        assert_eq!(chunk.get::<Simd>(98), Some(&Simd([98.0; 4])));
+        assert_eq!(chunk.get_ptr(TypeId::of::<Simd>(), 0).unwrap() as usize % 64, 0);
+        drop(chunk);
+        assert_eq!(dropped(), 9900);
+    }
+
+    #[test]
+    fn test_zero_sized_only() {
+        dropped();
+        let mut entities = Entities::new();
+        let mut chunk = Chunk::with_capacity(&[Marker::info(), Unit::info()], 4);
+        for _ in 0..10 {
+            chunk.push_default(entities.spawn(Location::default()));
+        }
+        assert_eq!(chunk.len(), 10);
+        chunk.swap_remove(0, &mut entities);
+        chunk.shrink_to_fit();
+        drop(chunk);
+        assert_eq!(dropped(), 1000);
+
+        // 列が無いチャンクもエンティティを格納できます。
+        let mut chunk = Chunk::new(&[]);
+        let id = entities.spawn(Location::default());
+        chunk.push_default(id);
+        assert_eq!(chunk.swap_remove(0, &mut entities), id);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut chunk = Chunk::with_capacity(&[Label::info()], 8);
+        assert!(chunk.capacity() >= 8);
+        let mut entities = Entities::new();
+        for _ in 0..8 {
+            chunk.push_default(entities.spawn(Location::default()));
+        }
+        chunk.reserve(100);
+        assert!(chunk.capacity() >= 108);
+        for row in 0..8 {
+            assert_eq!(chunk.get::<Label>(row), Some(&Label::default()));
+        }
+        chunk.shrink_to_fit();
+        assert!(chunk.capacity() >= 8);
+
+        let e = Chunk::try_new(&[Label::info(), Label::info()]).err().expect("重複した列はエラーになる必要があります。");
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    }
+}
+
 /// エンティティとデータの集まりです。
+/// 
+/// 列を生ポインタで保持するため`Send`と`Sync`は実装されず、作成したスレッドから移動できません。
+/// `Data`トレイトは`Send`と`Sync`を要求しないため、`Rc`などを含むデータも格納でき、
+/// これらの型を他のスレッドへ渡すことになる実装は健全ではありません。
+/// スレッド間で共有する場合は、`Data`に`Send + Sync`を要求した上で実装します。
 pub struct Chunk {
-    ids: Vec<Id>,
-    datas: FxHashMap<TypeId, Datas>,
+    ids: Vec<Id>,                     // 各行のエンティティです。
+    cap: usize,                       // 各列に確保した行数です。
+    datas: FxHashMap<TypeId, Datas>,  // データの種類毎の列です。
 }
+
+// データの列です。
 struct Datas {
-    info: &'static Info,
-    buf: Vec<u8>,
-}
\ No newline at end of file
+    info: &'static Info, // データの型情報です。
+    ptr: NonNull<u8>,    // 型のアラインメントで確保した先頭です。先頭から行数分の値は初期化済みです。
+}
+impl Datas {
+    // 行数を確保して作成します。
+    fn new(info: &'static Info, capacity: usize) -> Datas {
+        // 型サイズが0、または、未確保の場合はアラインメントの位置を指すだけです。
+        let ptr = NonNull::new(ptr::without_provenance_mut(info.align())).unwrap();
+        let mut data = Datas { info, ptr };
+        data.resize(0, capacity);
+        data
+    }
+
+    // 行数分のメモリレイアウトを取得します。
+    fn layout(&self, capacity: usize) -> Layout {
+        self.info.size()
+            .checked_mul(capacity)
+            .and_then(|size| Layout::from_size_align(size, self.info.align()).ok())
+            .expect("chunk capacity overflow")
+    }
+
+    // 確保した行数を変更します。範囲内の値はそのまま残ります。
+    fn resize(&mut self, old: usize, new: usize) {
+        let (old, new) = (self.layout(old), self.layout(new));
+        if old.size() == new.size() {
+            return;
+        }
+        // Safety: 確保済みの場合はoldのレイアウトで確保しています。
+        let ptr = unsafe {
+            if old.size() == 0 {
+                alloc(new)
+            } else if new.size() == 0 {
+                dealloc(self.ptr.as_ptr(), old);
+                ptr::without_provenance_mut(new.align())
+            } else {
+                realloc(self.ptr.as_ptr(), old, new.size())
+            }
+        };
+        self.ptr = NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(new));
+    }
+
+    // 行の位置のポインタを取得します。
+    fn ptr(&self, row: usize) -> *mut u8 {
+        // Safety: 呼び出し側は確保済みの範囲の行を指定します。
+        unsafe { self.ptr.as_ptr().add(row * self.info.size()) }
+    }
+}
+
+impl Chunk {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - 列のデータの型情報です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 同じ型の列が重複している場合に異常終了します。
+    /// 
+    pub fn new(infos: &[&'static Info]) -> Chunk {
+        Self::try_new(infos).or_abort()
+    }
+
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - 列のデータの型情報です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、同じ型の列が重複している場合はエラーです。
+    /// 
+    pub fn try_new(infos: &[&'static Info]) -> Result<Chunk> {
+        Self::try_with_capacity(infos, 0)
+    }
+
+    /// 行数を確保して作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - 列のデータの型情報です。
+    /// * `capacity` - 確保する行数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 同じ型の列が重複している場合に異常終了します。
+    /// 
+    pub fn with_capacity(infos: &[&'static Info], capacity: usize) -> Chunk {
+        Self::try_with_capacity(infos, capacity).or_abort()
+    }
+
+    /// 行数を確保して作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `infos` - 列のデータの型情報です。
+    /// * `capacity` - 確保する行数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、同じ型の列が重複している場合はエラーです。
+    /// 
+    pub fn try_with_capacity(infos: &[&'static Info], capacity: usize) -> Result<Chunk> {
+        let mut datas = FxHashMap::default();
+        for info in infos {
+            let data = Datas::new(info, capacity);
+            if datas.insert(info.type_id(), data).is_some() {
+                return Err(CwagoError::new(
+                    ErrorKind::InvalidArgument,
+                    msg!(CHUNK_DUPLICATED_COLUMN, name = info.name())
+                ));
+            }
+        }
+        Ok(Chunk { ids: Vec::with_capacity(capacity), cap: capacity, datas })
+    }
+
+    /// 行数を取得します。
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// 行が無いかを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// 再確保せずに格納できる行数を取得します。
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// 少なくとも`additional`行を追加できるように確保します。
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.ids.len().checked_add(additional).expect("chunk capacity overflow");
+        if required > self.cap {
+            // 追加の度に再確保しないように、少なくとも倍に増やします。
+            self.resize(required.max(self.cap * 2));
+            self.ids.reserve(additional);
+        }
+    }
+
+    /// 余分に確保した領域を解放します。
+    pub fn shrink_to_fit(&mut self) {
+        self.resize(self.ids.len());
+        self.ids.shrink_to_fit();
+    }
+
+    // 各列に確保した行数を変更します。
+    fn resize(&mut self, capacity: usize) {
+        for data in self.datas.values_mut() {
+            data.resize(self.cap, capacity);
+        }
+        self.cap = capacity;
+    }
+
+    /// 各行のエンティティを取得します。
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    /// 列のデータの型情報を走査します。順序は問いません。
+    pub fn infos(&self) -> impl Iterator<Item = &'static Info> + '_ {
+        self.datas.values().map(|data| data.info)
+    }
+
+    /// 列を持つかを取得します。
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.datas.contains_key(&id)
+    }
+
+    /// 行を追加し、各列を初期化します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 行のエンティティです。
+    /// * `init` - 列毎に、型情報と未初期化の位置を受け取って値を書き込みます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 追加した行です。
+    /// 
+    /// # Safety
+    /// 
+    /// `init`はすべての列の位置に、型情報の型の値を書き込む必要があります。
+    /// 
+    pub unsafe fn push_with(&mut self, id: Id, mut init: impl FnMut(&'static Info, *mut u8)) -> usize {
+        let row = self.ids.len();
+        self.reserve(1);
+        for data in self.datas.values_mut() {
+            init(data.info, data.ptr(row));
+        }
+        // すべての列を初期化してから行数を更新します。途中で異常終了した場合は値がリークします。
+        self.ids.push(id);
+        row
+    }
+
+    /// 行を追加し、各列を既定値で初期化します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 行のエンティティです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 追加した行です。
+    /// 
+    pub fn push_default(&mut self, id: Id) -> usize {
+        // Safety: 型情報の初期化関数はその型の値を書き込みます。
+        unsafe { self.push_with(id, |info, ptr| initialize_buf(info, ptr)) }
+    }
+
+    /// 行を削除し、最後の行を削除した行に移します。
+    /// 
+    /// 移ったエンティティの位置は`entities`で更新します。
+    /// 削除したエンティティは`entities`から解放しません。
+    /// 
+    /// # 引数
+    /// 
+    /// * `row` - 削除する行です。
+    /// * `entities` - 位置を更新するエンティティの割り当てです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した行のエンティティです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 行が範囲外の場合に異常終了します。
+    /// 
+    pub fn swap_remove(&mut self, row: usize, entities: &mut Entities) -> Id {
+        // Safety: 型情報の解体関数で各値をドロップします。
+        unsafe { self.swap_remove_with(row, entities, |info, ptr| drop_buf(info, ptr)) }
+    }
+
+    /// 行の値を取り出して削除し、最後の行を削除した行に移します。
+    /// 
+    /// 移ったエンティティの位置は`entities`で更新します。
+    /// 削除したエンティティは`entities`から解放しません。
+    /// 
+    /// # 引数
+    /// 
+    /// * `row` - 削除する行です。
+    /// * `entities` - 位置を更新するエンティティの割り当てです。
+    /// * `take` - 列毎に、型情報と値の位置を受け取り、値をムーブ、または、ドロップします。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した行のエンティティです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 行が範囲外の場合に異常終了します。
+    /// 
+    /// # Safety
+    /// 
+    /// `take`はすべての列の値の所有権を引き取る必要があります。呼び出し後の位置は上書きされます。
+    /// 
+    pub unsafe fn swap_remove_with(
+        &mut self,
+        row: usize,
+        entities: &mut Entities,
+        mut take: impl FnMut(&'static Info, *mut u8)
+    ) -> Id {
+        // 空のチャンクでは行数から1を引けないため、先に範囲を確認します。
+        assert!(row < self.ids.len(), "row {} is out of range for a chunk of {} rows", row, self.ids.len());
+        let last = self.ids.len() - 1;
+        for data in self.datas.values_mut() {
+            take(data.info, data.ptr(row));
+            if row != last {
+                move_buf(data.info, data.ptr(last), data.ptr(row));
+            }
+        }
+        let removed = self.ids.swap_remove(row);
+        if let Some(moved) = self.ids.get(row) {
+            if let Some(loc) = entities.location(*moved) {
+                entities.set_location(*moved, Location { row, ..loc });
+            }
+        }
+        removed
+    }
+
+    /// 行を別のチャンクに移し、最後の行を移した行に移します。
+    /// 
+    /// 両方のチャンクにある列の値はムーブし、移動先だけにある列は`init`で初期化し、
+    /// 移動元だけにある列は`take`に渡します。
+    /// 残った行の位置は`entities`で更新しますが、移したエンティティの位置は呼び出し側が更新します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `row` - 移す行です。
+    /// * `dst` - 移動先のチャンクです。
+    /// * `entities` - 位置を更新するエンティティの割り当てです。
+    /// * `init` - 移動先だけにある列毎に、型情報と未初期化の位置を受け取って値を書き込みます。
+    /// * `take` - 移動元だけにある列毎に、型情報と値の位置を受け取り、値をムーブ、または、ドロップします。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 移動先の行です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 行が範囲外の場合に異常終了します。
+    /// 
+    /// # Safety
+    /// 
+    /// `init`は渡されたすべての位置に型情報の型の値を書き込み、
+    /// `take`は渡されたすべての値の所有権を引き取る必要があります。
+    /// 
+    pub unsafe fn move_row(
+        &mut self, 
+        row: usize, 
+        dst: &mut Chunk, 
+        entities: &mut Entities, 
+        mut init: impl FnMut(&'static Info, *mut u8), 
+        mut take: impl FnMut(&'static Info, *mut u8)
+    ) -> usize {
+        let id = self.ids[row];
+        let src = &self.datas;
+        let moved = dst.push_with(id, |info, to| match src.get(&info.type_id()) {
+            Some(data) => move_buf(info, data.ptr(row), to), 
+            None => init(info, to),
+        });
+        // ムーブした値は未初期化として扱います。
+        self.swap_remove_with(row, entities, |info, ptr| {
+            if !dst.contains(info.type_id()) {
+                take(info, ptr);
+            }
+        });
+        moved
+    }
+
+    /// 型の列を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 各行の値、列が無い場合はNoneです。
+    /// 
+    pub fn column<T: 'static>(&self) -> Option<&[T]> {
+        let data = self.datas.get(&TypeId::of::<T>())?;
+        // Safety: 列は型の識別子で引いたため、値はTの初期化済みの値です。
+        Some(unsafe { slice::from_raw_parts(data.ptr(0) as *const T, self.ids.len()) })
+    }
+
+    /// 型の列を可変で取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 各行の値、列が無い場合はNoneです。
+    /// 
+    pub fn column_mut<T: 'static>(&mut self) -> Option<&mut [T]> {
+        let data = self.datas.get_mut(&TypeId::of::<T>())?;
+        // Safety: 列は型の識別子で引いたため、値はTの初期化済みの値です。
+        Some(unsafe { slice::from_raw_parts_mut(data.ptr(0) as *mut T, self.ids.len()) })
+    }
+
+    /// 行の値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `row` - 行です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、列が無い、または、行が範囲外の場合はNoneです。
+    /// 
+    pub fn get<T: 'static>(&self, row: usize) -> Option<&T> {
+        self.column::<T>()?.get(row)
+    }
+
+    /// 行の値を可変で取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `row` - 行です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、列が無い、または、行が範囲外の場合はNoneです。
+    /// 
+    pub fn get_mut<T: 'static>(&mut self, row: usize) -> Option<&mut T> {
+        self.column_mut::<T>()?.get_mut(row)
+    }
+
+    /// 列の先頭の位置を取得します。
+    /// 
+    /// 行数分の値は初期化済みです。位置は行の追加で変わる可能性があります。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 列のデータの型の識別子です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 先頭の位置、列が無い場合はNoneです。書き込む場合は、呼び出し側が他の参照と重ならないことを保証します。
+    /// 
+    pub fn column_ptr(&self, id: TypeId) -> Option<*mut u8> {
+        self.datas.get(&id).map(|data| data.ptr(0))
+    }
+
+    /// 行の値の位置を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 列のデータの型の識別子です。
+    /// * `row` - 行です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値の位置、列が無い、または、行が範囲外の場合はNoneです。
+    /// 
+    pub fn get_ptr(&self, id: TypeId, row: usize) -> Option<*const u8> {
+        let data = self.datas.get(&id)?;
+        (row < self.ids.len()).then(|| data.ptr(row) as *const u8)
+    }
+
+    /// 行の値の位置を可変で取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 列のデータの型の識別子です。
+    /// * `row` - 行です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値の位置、列が無い、または、行が範囲外の場合はNoneです。
+    /// 
+    pub fn get_ptr_mut(&mut self, id: TypeId, row: usize) -> Option<*mut u8> {
+        let data = self.datas.get_mut(&id)?;
+        (row < self.ids.len()).then(|| data.ptr(row))
+    }
+}
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        let len = self.ids.len();
+        for data in self.datas.values_mut() {
+            for row in 0..len {
+                // Safety: 各行の値は初期化済みで、以降は使いません。
+                unsafe { drop_buf(data.info, data.ptr(row)) };
+            }
+            data.resize(self.cap, 0);
+        }
+    }
+}
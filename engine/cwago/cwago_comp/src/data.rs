@@ -6,16 +6,81 @@
 // (C) 2023 CwagoCommunity.
 //
 //! コンポーネントデータを提供します。
+//! 
+//! ```
+//! use cwago_comp::data::{Data, Serialize, Deserialize};
+//! 
+//! #[derive(Default, Serialize, Deserialize, Data)]
+//! #[data(name = "game::Health", version = 1)]
+//! struct Health(f32);
+//! 
+//! #[derive(Default, Data)]
+//! #[data(tag, no_serialize)]
+//! struct Player;
+//! 
+//! assert_eq!(Health::info().name().as_str(), "game::Health");
+//! assert!(Player::info().is_tag());
+//! ```
 // =========================
 
 pub use serde::{
     Serialize, 
     Deserialize
 };
+pub use cwago_comp_derive::Data;
 
 use crate::ty::Info;
 
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use super::*;
+    use crate::ty::{
+        info_by_name, 
+        info_by_type_id
+    };
+
+    #[derive(Default, Serialize, Deserialize, Data)]
+    #[data(name = "test.data.health", version = 3)]
+    struct Health(u32);
+
+    #[derive(Default, Data)]
+    #[data(no_serialize)]
+    struct Handle(*const u8);
+
+    #[derive(Default, Serialize, Deserialize, Data)]
+    #[data(tag)]
+    struct Marker;
+
+    #[test]
+    fn test_derive() {
+        let info = Health::info();
+        assert_eq!(info.name().as_str(), "test.data.health");
+        assert_eq!(info.version(), 3);
+        assert_eq!(info.size(), 4);
+        assert!(info.is_serializable());
+        assert!(!info.is_tag());
+        assert!(std::ptr::eq(Health::info(), info), "同じ型情報を返す必要があります。");
+        assert!(std::ptr::eq(info_by_name("test.data.health").unwrap(), info));
+
+        let handle = Handle::info();
+        assert!(!handle.is_serializable());
+        assert!(Handle::default().0.is_null());
+        assert_eq!(handle.name().as_str(), concat!(module_path!(), "::Handle"));
+        assert!(std::ptr::eq(info_by_type_id(std::any::TypeId::of::<Handle>()).unwrap(), handle));
+
+        let marker = Marker::info();
+        assert!(marker.is_tag());
+        assert_eq!(marker.size(), 0);
+        assert_ne!(marker.index(), info.index());
+    }
+}
+
 /// コンポーネントデータトレイトです。
-pub trait Data<'de>: Sized + Serialize + Deserialize<'de> + 'static {
+/// 
+/// 通常は`#[derive(Data)]`で実装します。
+/// 
+pub trait Data: Sized + 'static {
+    /// 型情報を取得します。
     fn info() -> &'static Info;
-}
\ No newline at end of file
+}
@@ -15,11 +15,122 @@ use std::{
     }
 };
 
+use cwago_utility::{
+    arena::{
+        GenArena, 
+        Key, 
+        RawKey
+    }, 
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result, 
+        ResultExt
+    }, 
+    msg
+};
+
+use serde::{
+    Deserialize, 
+    Serialize
+};
+
+use crate::messages::ENTITIES_EXHAUSTED;
+
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use super::*;
+
+    fn at(chunk: usize, row: usize) -> Location {
+        Location { chunk, row }
+    }
+
+    #[test]
+    fn test_spawn_despawn() {
+        let mut entities = Entities::new();
+        let a = entities.spawn(at(0, 0));
+        let b = entities.spawn(at(0, 1));
+        assert_ne!(a, b);
+        assert_eq!(entities.len(), 2);
+        assert!(entities.is_alive(a));
+        assert_eq!(entities.location(b), Some(at(0, 1)));
+
+        assert_eq!(entities.despawn(a), Some(at(0, 0)));
+        assert_eq!(entities.despawn(a), None, "解放済みのIdは無効である必要があります。");
+        assert!(!entities.is_alive(a));
+        assert_eq!(entities.location(a), None);
+
+        // 添え字は世代値を上げて再利用します。
+        let c = entities.spawn(at(1, 0));
+        assert_eq!(c.index(), a.index());
+        assert_eq!(c.version(), a.version() + 1);
+        assert!(!entities.is_alive(a));
+        assert!(entities.is_alive(c));
+        assert!(!entities.set_location(a, at(2, 0)));
+        assert!(entities.set_location(c, at(2, 0)));
+        assert_eq!(entities.location(c), Some(at(2, 0)));
+
+        let mut alive: Vec<_> = entities.iter().collect();
+        alive.sort_by_key(|(id, _)| id.index());
+        assert_eq!(alive, [(c, at(2, 0)), (b, at(0, 1))]);
+    }
+
+    #[test]
+    fn test_key() {
+        // 世代値の管理はGenArenaと共通のキーで行います。
+        let id = Id::new(3, 7);
+        assert_eq!(id.raw(), RawKey::new(3, 7));
+        assert_eq!(Id::from(id.raw()), id);
+    }
+
+    #[test]
+    fn test_max() {
+        let mut entities = Entities::with_max(2);
+        let a = entities.spawn(at(0, 0));
+        entities.spawn(at(0, 1));
+        assert_eq!(entities.try_spawn(at(0, 2)).unwrap_err().kind(), ErrorKind::Exhausted);
+        entities.despawn(a);
+        assert!(entities.try_spawn(at(0, 2)).is_ok());
+        entities.clear();
+        assert!(entities.is_empty());
+        assert!(!entities.is_alive(a));
+    }
+
+    #[test]
+    fn test_retire() {
+        let mut entities = Entities::new();
+        entities.spawn(at(0, 0));
+        // 世代値が一巡する2つ手前まで進めた状態を復元します。
+        let mut json = serde_json::to_value(&entities).unwrap();
+        json["locs"][0][0] = (u32::MAX - 2).into();
+        let mut entities: Entities = serde_json::from_value(json).unwrap();
+        let (a, _) = entities.iter().next().unwrap();
+        assert_eq!(a.version(), u32::MAX - 2);
+
+        // 最後の世代値までは同じ添え字を再利用します。
+        assert_eq!(entities.despawn(a), Some(at(0, 0)));
+        let b = entities.spawn(at(0, 1));
+        assert_eq!(b.index(), a.index());
+        assert_eq!(b.version(), u32::MAX - 1);
+
+        // 世代値が一巡する添え字は退役させ、再利用しません。
+        assert_eq!(entities.despawn(b), Some(at(0, 1)));
+        let c = entities.spawn(at(0, 2));
+        assert_ne!(c.index(), b.index(), "退役した添え字を再利用してはいけません。");
+        assert!(!entities.is_alive(a));
+        assert!(!entities.is_alive(b));
+        assert!(!entities.is_alive(Id::new(b.index(), u32::MAX)));
+        assert_eq!(entities.iter().collect::<Vec<_>>(), [(c, at(0, 2))]);
+        assert_eq!(entities.len(), 1);
+    }
+}
+
 /// 同時に存在可能なエンティティの最大値です。
 pub const EXISTENCES_MAX: usize = u32::MAX as usize;
 
 /// エンティティIDです。
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Id {
     idx: u32, // エンティティ配列の添え字です。
     ver: u32, // idxを使いまわす際の重複を回避する世代値です。
@@ -39,7 +150,36 @@ impl Id {
     pub(super) fn new(idx: u32, ver: u32) -> Id {
         Id { idx, ver }
     }
-    
+
+    /// 添え字を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// エンティティ配列の添え字です。
+    /// 
+    pub fn index(&self) -> u32 {
+        self.idx
+    }
+
+    /// 世代値を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 世代値です。
+    /// 
+    pub fn version(&self) -> u32 {
+        self.ver
+    }
+}
+impl From<RawKey> for Id {
+    fn from(raw: RawKey) -> Self {
+        Id::new(raw.index(), raw.version())
+    }
+}
+impl Key for Id {
+    fn raw(&self) -> RawKey {
+        RawKey::new(self.idx, self.ver)
+    }
 }
 impl Display for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -47,4 +187,181 @@ impl Display for Id {
         let id = h | (self.ver as usize); 
         write!(f, "{}", id)
     }
-}
\ No newline at end of file
+}
+
+/// エンティティのデータの位置です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Location {
+    /// チャンクの添え字です。
+    pub chunk: usize,
+    /// チャンク内の行です。
+    pub row: usize,
+}
+
+/// エンティティIDの割り当てを管理します。
+/// 
+/// 添え字の再利用と世代値は`GenArena`で管理するため、古いIdは無効として判別できます。
+/// 世代値が一巡する添え字は`GenArena`と同じく退役させ、再利用しません。
+/// 保存と復元では世代値と退役した添え字も引き継ぎます。
+/// 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entities {
+    locs: GenArena<Location, Id>, // データの位置です。
+    max: usize,                   // 同時に存在可能な数です。
+}
+impl Entities {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn new() -> Entities {
+        Self::with_max(EXISTENCES_MAX)
+    }
+
+    /// 同時に存在可能な数を指定して作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `max` - 同時に存在可能な数です。`EXISTENCES_MAX`より大きい場合は`EXISTENCES_MAX`になります。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn with_max(max: usize) -> Entities {
+        Entities { locs: GenArena::new(), max: max.min(EXISTENCES_MAX) }
+    }
+
+    /// Idを割り当てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `loc` - データの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// Idです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 同時に存在可能な数、または、添え字の上限に達した場合に異常終了します。
+    /// 
+    pub fn spawn(&mut self, loc: Location) -> Id {
+        self.try_spawn(loc).or_abort()
+    }
+
+    /// Idを割り当てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `loc` - データの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// Id、同時に存在可能な数、または、添え字の上限に達した場合はエラーです。
+    /// 
+    pub fn try_spawn(&mut self, loc: Location) -> Result<Id> {
+        if self.locs.len() >= self.max || !self.locs.can_insert() {
+            return Err(CwagoError::new(ErrorKind::Exhausted, msg!(ENTITIES_EXHAUSTED, max = self.max)));
+        }
+        let id = self.locs.insert(loc);
+        cwago_utility::profile_counter!("entities", self.locs.len());
+        Ok(id)
+    }
+
+    /// Idを解放します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 解放するIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 解放したエンティティのデータの位置、無効なIdの場合はNoneです。
+    /// 
+    pub fn despawn(&mut self, id: Id) -> Option<Location> {
+        let loc = self.locs.remove(id)?;
+        cwago_utility::profile_counter!("entities", self.locs.len());
+        Some(loc)
+    }
+
+    /// Idが有効かを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 確認するIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 割り当て中の場合はtrueです。
+    /// 
+    pub fn is_alive(&self, id: Id) -> bool {
+        self.locs.contains_key(id)
+    }
+
+    /// データの位置を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - エンティティIdです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// データの位置、無効なIdの場合はNoneです。
+    /// 
+    pub fn location(&self, id: Id) -> Option<Location> {
+        self.locs.get(id).copied()
+    }
+
+    /// データの位置を更新します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - エンティティIdです。
+    /// * `loc` - 新しいデータの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 更新した場合はtrue、無効なIdの場合はfalseです。
+    /// 
+    pub fn set_location(&mut self, id: Id, loc: Location) -> bool {
+        match self.locs.get_mut(id) {
+            Some(old) => {
+                *old = loc;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 割り当て中の数を取得します。
+    pub fn len(&self) -> usize {
+        self.locs.len()
+    }
+
+    /// 割り当て中のIdが無いかを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.locs.is_empty()
+    }
+
+    /// すべてのIdを解放します。
+    /// 
+    /// 解放したIdは以降も無効として判別できます。
+    /// 
+    pub fn clear(&mut self) {
+        self.locs.clear();
+        cwago_utility::profile_counter!("entities", self.locs.len());
+    }
+
+    /// 割り当て中のIdとデータの位置を走査します。
+    pub fn iter(&self) -> impl Iterator<Item = (Id, Location)> + '_ {
+        self.locs.iter().map(|(id, loc)| (id, *loc))
+    }
+}
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
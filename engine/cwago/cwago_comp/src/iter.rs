@@ -6,100 +6,863 @@
 // (C) 2023 CwagoCommunity.
 //
 //! イテレータを提供します。
+//! 
+//! `Query`は要求に一致するアーキタイプを記録し、一致したチャンクのエンティティ毎にデータを走査します。
 // =========================
 
-use std::{marker::PhantomData, any::TypeId};
+use std::{
+    marker::PhantomData, 
+    any::TypeId, 
+    slice
+};
 
-use crate::data::Data;
+use cwago_utility::{
+    bitset::BitSet, 
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result, 
+        ResultExt
+    }, 
+    msg
+};
 
-pub struct Iter<T> {
-    _ph: PhantomData<T>,
+use crate::{
+    arch::{
+        Arch, 
+        Archetypes
+    }, 
+    chunk::Chunk, 
+    data::Data, 
+    ent::Id, 
+    messages::QUERY_ALIASED, 
+    ty::Info
+};
+
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use super::*;
+    use crate::ent::Entities;
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.iter.pos", no_serialize)]
+    struct Pos(i32);
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.iter.vel", no_serialize)]
+    struct Vel(i32);
+
+    #[derive(Debug, Default, PartialEq, Data)]
+    #[data(name = "test.iter.frozen", tag, no_serialize)]
+    struct Frozen;
+
+    // エンティティを作成し、データを追加します。
+    fn spawn(archs: &mut Archetypes, entities: &mut Entities, pos: Option<i32>, vel: Option<i32>, frozen: bool) -> Id {
+        let id = archs.spawn(entities);
+        if let Some(pos) = pos {
+            archs.insert(entities, id, Pos(pos));
+        }
+        if let Some(vel) = vel {
+            archs.insert(entities, id, Vel(vel));
+        }
+        if frozen {
+            archs.insert(entities, id, Frozen);
+        }
+        id
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut entities = Entities::new();
+        let mut archs = Archetypes::new();
+        spawn(&mut archs, &mut entities, Some(1), None, false);
+        spawn(&mut archs, &mut entities, Some(2), Some(20), false);
+        spawn(&mut archs, &mut entities, None, Some(30), false);
+        spawn(&mut archs, &mut entities, Some(4), Some(40), true);
+        spawn(&mut archs, &mut entities, Some(5), Some(50), false);
+
+        let mut query = Query::<(&Pos, &mut Vel)>::new();
+        for (pos, vel) in query.iter_mut(&mut archs) {
+            vel.0 += pos.0;
+        }
+        assert_eq!(query.matched().len(), 2);
+
+        let mut query = Query::<(&Vel, &Vel)>::new();
+        let mut vels: Vec<i32> = query.iter(&archs).map(|(vel, _)| vel.0).collect();
+        vels.sort();
+        assert_eq!(vels, [22, 30, 44, 55]);
+
+        // 目印はデータを取得せずに絞り込みます。
+        let mut query = Query::<(&Pos, With<&Frozen>)>::new();
+        assert_eq!(query.iter(&archs).map(|(pos, ())| pos.0).collect::<Vec<_>>(), [4]);
+
+        // Orはいずれかを持つエンティティに一致し、持たないデータはNoneです。
+        let mut query = Query::<(Or<(&Pos, &Vel)>, With<&Vel>)>::new();
+        let mut items: Vec<(Option<i32>, Option<i32>)> = query.iter(&archs)
+            .map(|((pos, vel), ())| (pos.map(|p| p.0), vel.map(|v| v.0)))
+            .collect();
+        items.sort();
+        assert_eq!(items, [(None, Some(30)), (Some(2), Some(22)), (Some(4), Some(44)), (Some(5), Some(55))]);
+        let mut query = Query::<Or<(&Pos, &Vel)>>::new();
+        assert_eq!(query.iter(&archs).count(), 5);
+    }
+
+    #[test]
+    fn test_elements() {
+        let mut entities = Entities::new();
+        let mut archs = Archetypes::new();
+        let a = spawn(&mut archs, &mut entities, Some(1), None, false);
+        let b = spawn(&mut archs, &mut entities, Some(2), Some(20), false);
+        let c = spawn(&mut archs, &mut entities, None, Some(30), true);
+
+        let mut query = Query::<(Id, &Pos, Without<&Vel>)>::new();
+        assert_eq!(query.iter(&archs).map(|(id, pos, ())| (id, pos.0)).collect::<Vec<_>>(), [(a, 1)]);
+
+        // Optionはすべてのエンティティに一致し、持たないデータはNoneです。
+        let mut query = Query::<(Id, Option<&mut Vel>)>::new();
+        for (_, vel) in query.iter_mut(&mut archs) {
+            if let Some(vel) = vel {
+                vel.0 += 1;
+            }
+        }
+        let mut query = Query::<(Id, Option<&Vel>)>::new();
+        let mut items: Vec<(Id, Option<i32>)> = query.iter(&archs).map(|(id, vel)| (id, vel.map(|v| v.0))).collect();
+        items.sort_by_key(|(id, _)| id.index());
+        assert_eq!(items, [(a, None), (b, Some(21)), (c, Some(31))]);
+
+        let mut query = Query::<(Id, With<(&Vel, &Frozen)>)>::new();
+        assert_eq!(query.iter(&archs).map(|(id, ())| id).collect::<Vec<_>>(), [c]);
+        let mut query = Query::<(Id, Without<&Frozen>, With<(&Vel,)>)>::new();
+        assert_eq!(query.iter(&archs).map(|(id, (), ())| id).collect::<Vec<_>>(), [b]);
+        assert!(Query::<(&mut Vel, Option<&Vel>)>::try_new().is_err(), "Optionも参照を取得します。");
+        assert!(Query::<(&mut Vel, Without<&Vel>)>::try_new().is_ok());
+    }
+
+    #[test]
+    fn test_arity() {
+        let mut entities = Entities::new();
+        let mut archs = Archetypes::new();
+        spawn(&mut archs, &mut entities, Some(1), Some(2), false);
+        type Wide<'a> = (
+            &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos,
+            &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Vel,
+        );
+        let mut query = Query::<Wide>::new();
+        let item = query.iter(&archs).next().unwrap();
+        assert_eq!((item.0.0, item.15.0), (1, 2));
+
+        let mut query = Query::<Or<(&Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Frozen,
+            &Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Frozen, &Vel)>>::new();
+        assert_eq!(query.iter(&archs).count(), 1);
+        let mut query = Query::<(&Pos,)>::new();
+        assert_eq!(query.iter(&archs).next().unwrap().0.0, 1);
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut entities = Entities::new();
+        let mut archs = Archetypes::new();
+        spawn(&mut archs, &mut entities, Some(1), None, false);
+        let mut query = Query::<(&Pos, With<&Pos>)>::new();
+        assert_eq!(query.iter(&archs).count(), 1);
+        let matched = query.matched().len();
+
+        // 新しいアーキタイプは次の走査で一致を確認します。
+        spawn(&mut archs, &mut entities, Some(2), Some(2), false);
+        spawn(&mut archs, &mut entities, None, Some(3), false);
+        assert_eq!(query.iter(&archs).count(), 2);
+        assert_eq!(query.matched().len(), matched + 1);
+
+        // 空のチャンクは走査しません。
+        let id = spawn(&mut archs, &mut entities, Some(4), None, true);
+        archs.despawn(&mut entities, id);
+        assert_eq!(query.iter(&archs).count(), 2);
+    }
+
+    #[test]
+    fn test_aliased() {
+        let e = Query::<(&mut Pos, &Pos)>::try_new().err().expect("可変参照の重複はエラーになる必要があります。");
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+        assert!(Query::<Or<(&mut Pos, &mut Pos)>>::try_new().is_err());
+        assert!(Query::<(&mut Pos, With<&Pos>)>::try_new().is_ok(), "Withはデータを取得しません。");
+        assert!(Query::<(&Pos, &Pos)>::try_new().is_ok());
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut archs = Archetypes::new();
+        let both = archs.get_or_insert(&[Pos::info(), Vel::info()]);
+        let pos = archs.get_or_insert(&[Pos::info()]);
+        let req = <(&Pos, With<&mut Vel>) as Archetype>::req();
+        assert!(req.matches(archs.get(both).unwrap()));
+        assert!(!req.matches(archs.get(pos).unwrap()));
+        let req = <Or<(&Vel, &Frozen)> as Archetype>::req();
+        assert!(req.matches(archs.get(both).unwrap()));
+        assert!(!req.matches(archs.get(pos).unwrap()));
+
+        // 必須と除外の型は索引の集合で確認します。
+        let req = <(&Pos, Without<&Vel>, With<(&Frozen,)>) as Archetype>::req();
+        let with: BitSet = [Pos::info().index(), Frozen::info().index()].into_iter().collect();
+        let without: BitSet = [Vel::info().index()].into_iter().collect();
+        assert_eq!(req.with, with);
+        assert_eq!(req.without, without);
+        let frozen = archs.get_or_insert(&[Pos::info(), Frozen::info()]);
+        assert!(req.matches(archs.get(frozen).unwrap()));
+        assert!(!req.matches(archs.get(pos).unwrap()));
+        assert!(!req.matches(archs.get(both).unwrap()));
+    }
 }
-pub struct IterMut<T> {
-    _ph: PhantomData<T>,
+
+/// 共有参照のみの要求の走査です。
+pub struct Iter<'a, T>
+where T: Archetype
+{
+    cursor: Cursor<'a, T>, // 走査位置です。
+}
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Archetype
+{
+    type Item = T::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next()
+    }
+}
+
+/// 可変参照を含む要求の走査です。
+pub struct IterMut<'a, T>
+where T: Archetype
+{
+    cursor: Cursor<'a, T>,                  // 走査位置です。
+    _ph: PhantomData<&'a mut Archetypes>,   // アーキタイプを排他的に借用します。
+}
+impl<'a, T> Iterator for IterMut<'a, T>
+where T: Archetype
+{
+    type Item = T::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next()
+    }
 }
 
+// 一致したチャンクを順に走査します。
+struct Cursor<'a, T>
+where T: Archetype
+{
+    archs: &'a Archetypes,             // アーキタイプです。
+    matched: slice::Iter<'a, usize>,   // 残りの一致したアーキタイプです。
+    fetch: Option<T::Fetch>,           // 現在のチャンクの取得状態です。
+    row: usize,                        // 現在のチャンクの次の行です。
+    len: usize,                        // 現在のチャンクの行数です。
+}
+impl<'a, T> Cursor<'a, T>
+where T: Archetype
+{
+    fn new(archs: &'a Archetypes, matched: &'a [usize]) -> Self {
+        Cursor { archs, matched: matched.iter(), fetch: None, row: 0, len: 0 }
+    }
+
+    fn next(&mut self) -> Option<T::Item<'a>> {
+        loop {
+            if let Some(fetch) = self.fetch.filter(|_| self.row < self.len) {
+                let row = self.row;
+                self.row += 1;
+                // Safety: 行は範囲内で、要求は可変参照を重複させず、各行は一度のみ取得します。
+                return Some(unsafe { T::item(fetch, row) });
+            }
+            let chunk = self.archs.get(*self.matched.next()?)?.chunk();
+            self.fetch = T::fetch(chunk);
+            self.row = 0;
+            self.len = chunk.len();
+        }
+    }
+}
+
+/// アーキタイプの要求です。
+/// 
+/// 必ず持つ型と持たない型は型情報の索引の集合にまとめ、部分集合と素の判定で確認します。
+/// 
 pub struct Req {
-    req: ReqElem,
+    req: ReqElem,     // 要求の要素です。
+    with: BitSet,     // 必ず持つ型の索引です。
+    without: BitSet,  // 持たない型の索引です。
+}
+impl Req {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `req` - 要求の要素です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 必ず持つ型と持たない型を集めた要求です。
+    /// 
+    pub fn new(req: ReqElem) -> Req {
+        let mut with = BitSet::new();
+        let mut without = BitSet::new();
+        req.masks(&mut with, &mut without);
+        Req { req, with, without }
+    }
+
+    /// アーキタイプが要求を満たすかを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `arch` - 確認するアーキタイプです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 満たす場合はtrueです。
+    /// 
+    pub fn matches(&self, arch: &Arch) -> bool {
+        let mask = arch.mask();
+        self.with.is_subset(mask) && self.without.is_disjoint(mask) && self.req.matches_or(mask)
+    }
+
+    /// 可変参照が他の取得と重ならないかを確認します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 重ならない場合はOk、重なる場合はエラーです。
+    /// 
+    pub fn validate(&self) -> Result<()> {
+        let mut accesses = Vec::new();
+        self.req.accesses(&mut accesses);
+        for (i, access) in accesses.iter().enumerate() {
+            let aliased = accesses[i + 1..].iter()
+                .any(|other| other.id() == access.id() && (access.is_mut() || other.is_mut()));
+            if aliased {
+                let name = access.info().name().as_str();
+                return Err(CwagoError::new(ErrorKind::InvalidArgument, msg!(QUERY_ALIASED, name = name)));
+            }
+        }
+        Ok(())
+    }
 }
-enum ReqElem {
+
+/// 要求の要素です。
+pub enum ReqElem {
+    /// データを取得します。
     Type(ReqType),
+    /// すべての要素を満たします。
     And(Vec<ReqElem>),
+    /// いずれかの要素を満たします。
     Or(Vec<ReqElem>),
+    /// データを持ちますが、取得しません。
     With(ReqType),
+    /// データを持ちません。
+    Without(ReqType),
+    /// データを持つ場合のみ取得します。
+    Option(ReqType),
+    /// エンティティIdを取得します。
+    Id,
 }
-enum ReqType {
-    Ref(TypeId),
-    Mut(TypeId),
+impl ReqElem {
+    // 型情報の索引の集合が要素を満たすかを取得します。
+    fn matches(&self, mask: &BitSet) -> bool {
+        match self {
+            ReqElem::Type(ty) | ReqElem::With(ty) => mask.contains(ty.info().index()),
+            ReqElem::And(elems) => elems.iter().all(|elem| elem.matches(mask)),
+            ReqElem::Or(elems) => elems.iter().any(|elem| elem.matches(mask)),
+            ReqElem::Without(ty) => !mask.contains(ty.info().index()),
+            ReqElem::Option(_) | ReqElem::Id => true,
+        }
+    }
+
+    // 必ず持つ型と持たない型を集めます。Orの内側は集めません。
+    fn masks(&self, with: &mut BitSet, without: &mut BitSet) {
+        match self {
+            ReqElem::Type(ty) | ReqElem::With(ty) => {
+                with.insert(ty.info().index());
+            },
+            ReqElem::Without(ty) => {
+                without.insert(ty.info().index());
+            },
+            ReqElem::And(elems) => elems.iter().for_each(|elem| elem.masks(with, without)),
+            ReqElem::Or(_) | ReqElem::Option(_) | ReqElem::Id => (),
+        }
+    }
+
+    // 集合にまとめられないOrの要素のみを確認します。
+    fn matches_or(&self, mask: &BitSet) -> bool {
+        match self {
+            ReqElem::And(elems) => elems.iter().all(|elem| elem.matches_or(mask)),
+            ReqElem::Or(_) => self.matches(mask),
+            _ => true,
+        }
+    }
+
+    // データを取得する型を集めます。
+    fn accesses<'a>(&'a self, out: &mut Vec<&'a ReqType>) {
+        match self {
+            ReqElem::Type(ty) | ReqElem::Option(ty) => out.push(ty),
+            ReqElem::And(elems) | ReqElem::Or(elems) => elems.iter().for_each(|elem| elem.accesses(out)),
+            ReqElem::With(_) | ReqElem::Without(_) | ReqElem::Id => (),
+        }
+    }
 }
 
-pub trait Archetype {
+/// 要求するデータの型です。
+pub enum ReqType {
+    /// 共有参照です。
+    Ref(&'static Info),
+    /// 可変参照です。
+    Mut(&'static Info),
+}
+impl ReqType {
+    /// 型情報を取得します。
+    pub fn info(&self) -> &'static Info {
+        match self {
+            ReqType::Ref(info) | ReqType::Mut(info) => info,
+        }
+    }
+
+    /// 型の識別子を取得します。
+    pub fn id(&self) -> TypeId {
+        self.info().type_id()
+    }
+
+    /// 可変参照かを取得します。
+    pub fn is_mut(&self) -> bool {
+        matches!(self, ReqType::Mut(_))
+    }
+}
+
+/// 要求するアーキタイプのトレイトです。
+pub trait Archetype: ArchetypeElement {
+    /// 要求を取得します。
     fn req() -> Req;
 }
+impl<T> Archetype for T
+where T: ArchetypeElement
+{
+    fn req() -> Req {
+        Req::new(<T as ArchetypeElement>::req())
+    }
+}
 
-trait ArchetypeElement {
+/// 要求の要素のトレイトです。
+/// 
+/// # Safety
+/// 
+/// `fetch`は`req`を満たすチャンクでのみSomeを返し、`item`はその取得状態から要求どおりの参照のみを作成する必要があります。
+/// 
+pub unsafe trait ArchetypeElement {
+    /// エンティティ毎に取得する値です。
+    type Item<'a>;
+
+    /// チャンク毎の取得状態です。
+    type Fetch: Copy;
+
+    /// 要求の要素を取得します。
     fn req() -> ReqElem;
+
+    /// チャンクの取得状態を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `chunk` - 走査するチャンクです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 取得状態、要素を満たさない場合はNoneです。
+    /// 
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch>;
+
+    /// 行の値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `fetch` - チャンクの取得状態です。
+    /// * `row` - 行です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 行の値です。
+    /// 
+    /// # Safety
+    /// 
+    /// 行はチャンクの範囲内で、取得した可変参照は他の参照と重なってはいけません。
+    /// 
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a>;
 }
 
+/// 共有参照のみの要求の要素を示すトレイトです。
+/// 
+/// # Safety
+/// 
+/// 可変参照を取得しない要素にのみ実装します。
+/// 
+pub unsafe trait ReadOnly: ArchetypeElement {}
+
+/// いずれかを満たす要素です。持たないデータはNoneになります。
 pub struct Or<T> {
     _ph: PhantomData<T>,
 }
+
+/// データを持つことを要求しますが、取得しない要素です。
+/// 
+/// 組にした場合はすべてを持つことを要求します。
+/// 
 pub struct With<T> {
     _ph: PhantomData<T>,
 }
 
-impl<'a, 'de, D> ArchetypeElement for &'a D
-where D: Data<'de>
+/// データを持たないことを要求する要素です。
+pub struct Without<T> {
+    _ph: PhantomData<T>,
+}
+
+/// アーキタイプの要求に一致するエンティティを走査します。
+/// 
+/// 一致したアーキタイプを記録し、走査の度に新しく作成されたアーキタイプのみを確認します。
+/// 記録はアーキタイプの集まり毎のため、別の集まりには別の`Query`を使います。
+/// 
+pub struct Query<T>
+where T: Archetype
+{
+    req: Req,                 // 要求です。
+    matched: Vec<usize>,      // 一致したアーキタイプの添え字です。
+    checked: usize,           // 確認済みのアーキタイプの数です。
+    _ph: PhantomData<fn() -> T>,
+}
+impl<T> Query<T>
+where T: Archetype
+{
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 要求の可変参照が他の取得と重なる場合に異常終了します。
+    /// 
+    pub fn new() -> Self {
+        Self::try_new().or_abort()
+    }
+
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、要求の可変参照が他の取得と重なる場合はエラーです。
+    /// 
+    pub fn try_new() -> Result<Self> {
+        let req = <T as Archetype>::req();
+        req.validate()?;
+        Ok(Query { req, matched: Vec::new(), checked: 0, _ph: PhantomData })
+    }
+
+    /// 新しく作成されたアーキタイプの一致を確認します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `archs` - アーキタイプの集まりです。
+    /// 
+    pub fn update(&mut self, archs: &Archetypes) {
+        for (index, arch) in archs.iter().enumerate().skip(self.checked) {
+            if self.req.matches(arch) {
+                self.matched.push(index);
+            }
+        }
+        self.checked = archs.count();
+    }
+
+    /// 一致したアーキタイプの添え字を取得します。
+    pub fn matched(&self) -> &[usize] {
+        &self.matched
+    }
+
+    /// 一致するエンティティを走査します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `archs` - アーキタイプの集まりです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// エンティティ毎の値のイテレータです。
+    /// 
+    pub fn iter<'a>(&'a mut self, archs: &'a Archetypes) -> Iter<'a, T>
+    where T: ReadOnly
+    {
+        self.update(archs);
+        Iter { cursor: Cursor::new(archs, &self.matched) }
+    }
+
+    /// 一致するエンティティを可変で走査します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `archs` - アーキタイプの集まりです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// エンティティ毎の値のイテレータです。
+    /// 
+    pub fn iter_mut<'a>(&'a mut self, archs: &'a mut Archetypes) -> IterMut<'a, T> {
+        self.update(archs);
+        IterMut { cursor: Cursor::new(archs, &self.matched), _ph: PhantomData }
+    }
+}
+impl<T> Default for Query<T>
+where T: Archetype
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<D> ArchetypeElement for &D
+where D: Data
 {
+    type Item<'a> = &'a D;
+    type Fetch = *const D;
+
     fn req() -> ReqElem {
-        ReqElem::Type(ReqType::Ref(TypeId::of::<D>()))
+        ReqElem::Type(ReqType::Ref(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        chunk.column_ptr(TypeId::of::<D>()).map(|ptr| ptr as *const D)
+    }
+
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+        &*fetch.add(row)
     }
 }
+unsafe impl<D> ReadOnly for &D where D: Data {}
 
-impl<'a, 'de, D> ArchetypeElement for &'a mut D
-where D: Data<'de>
+unsafe impl<D> ArchetypeElement for &mut D
+where D: Data
 {
+    type Item<'a> = &'a mut D;
+    type Fetch = *mut D;
+
     fn req() -> ReqElem {
-        ReqElem::Type(ReqType::Mut(TypeId::of::<D>()))
+        ReqElem::Type(ReqType::Mut(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        chunk.column_ptr(TypeId::of::<D>()).map(|ptr| ptr as *mut D)
+    }
+
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+        &mut *fetch.add(row)
     }
 }
 
-impl<'a, 'de, D> ArchetypeElement for With<&'a D>
-where D: Data<'de>
+unsafe impl<D> ArchetypeElement for With<&D>
+where D: Data
 {
+    type Item<'a> = ();
+    type Fetch = ();
+
     fn req() -> ReqElem {
-        ReqElem::With(ReqType::Ref(TypeId::of::<D>()))
+        ReqElem::With(ReqType::Ref(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        chunk.contains(TypeId::of::<D>()).then_some(())
     }
+
+    unsafe fn item<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> {}
 }
+unsafe impl<D> ReadOnly for With<&D> where D: Data {}
 
-impl<'a, 'de, D> ArchetypeElement for With<&'a mut D>
-where D: Data<'de>
+unsafe impl<D> ArchetypeElement for With<&mut D>
+where D: Data
 {
+    type Item<'a> = ();
+    type Fetch = ();
+
     fn req() -> ReqElem {
-        ReqElem::With(ReqType::Mut(TypeId::of::<D>()))
+        ReqElem::With(ReqType::Mut(D::info()))
     }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        chunk.contains(TypeId::of::<D>()).then_some(())
+    }
+
+    unsafe fn item<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> {}
 }
+unsafe impl<D> ReadOnly for With<&mut D> where D: Data {}
 
-// サンプル
-impl<T0, T1> ArchetypeElement for (T0, T1,)
-where T0: ArchetypeElement, T1: ArchetypeElement 
+unsafe impl<D> ArchetypeElement for Without<&D>
+where D: Data
 {
+    type Item<'a> = ();
+    type Fetch = ();
+
     fn req() -> ReqElem {
-        ReqElem::And(vec![
-            T0::req(),
-            T1::req(),
-        ])
+        ReqElem::Without(ReqType::Ref(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        (!chunk.contains(TypeId::of::<D>())).then_some(())
     }
+
+    unsafe fn item<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> {}
 }
+unsafe impl<D> ReadOnly for Without<&D> where D: Data {}
 
-// サンプル
-impl<T0, T1> ArchetypeElement for Or<(T0, T1,)>
-where T0: ArchetypeElement, T1: ArchetypeElement 
+unsafe impl<D> ArchetypeElement for Without<&mut D>
+where D: Data
 {
+    type Item<'a> = ();
+    type Fetch = ();
+
     fn req() -> ReqElem {
-        ReqElem::Or(vec![
-            T0::req(),
-            T1::req(),
-        ])
+        ReqElem::Without(ReqType::Mut(D::info()))
     }
-}
\ No newline at end of file
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        (!chunk.contains(TypeId::of::<D>())).then_some(())
+    }
+
+    unsafe fn item<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> {}
+}
+unsafe impl<D> ReadOnly for Without<&mut D> where D: Data {}
+
+unsafe impl<D> ArchetypeElement for Option<&D>
+where D: Data
+{
+    type Item<'a> = Option<&'a D>;
+    type Fetch = Option<*const D>;
+
+    fn req() -> ReqElem {
+        ReqElem::Option(ReqType::Ref(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        Some(<&D>::fetch(chunk))
+    }
+
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+        fetch.map(|f| <&D>::item(f, row))
+    }
+}
+unsafe impl<D> ReadOnly for Option<&D> where D: Data {}
+
+unsafe impl<D> ArchetypeElement for Option<&mut D>
+where D: Data
+{
+    type Item<'a> = Option<&'a mut D>;
+    type Fetch = Option<*mut D>;
+
+    fn req() -> ReqElem {
+        ReqElem::Option(ReqType::Mut(D::info()))
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        Some(<&mut D>::fetch(chunk))
+    }
+
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+        fetch.map(|f| <&mut D>::item(f, row))
+    }
+}
+
+unsafe impl ArchetypeElement for Id {
+    type Item<'a> = Id;
+    type Fetch = *const Id;
+
+    fn req() -> ReqElem {
+        ReqElem::Id
+    }
+
+    fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+        Some(chunk.ids().as_ptr())
+    }
+
+    unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+        *fetch.add(row)
+    }
+}
+unsafe impl ReadOnly for Id {}
+
+// 組の要素を実装します。
+macro_rules! impl_tuple {
+    ($($t:ident $i:tt),+) => {
+        unsafe impl<$($t),+> ArchetypeElement for ($($t,)+)
+        where $($t: ArchetypeElement),+
+        {
+            type Item<'a> = ($($t::Item<'a>,)+);
+            type Fetch = ($($t::Fetch,)+);
+
+            fn req() -> ReqElem {
+                ReqElem::And(vec![$($t::req()),+])
+            }
+
+            fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+                Some(($($t::fetch(chunk)?,)+))
+            }
+
+            unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+                ($($t::item(fetch.$i, row),)+)
+            }
+        }
+        unsafe impl<$($t),+> ReadOnly for ($($t,)+) where $($t: ReadOnly),+ {}
+
+        unsafe impl<$($t),+> ArchetypeElement for Or<($($t,)+)>
+        where $($t: ArchetypeElement),+
+        {
+            type Item<'a> = ($(Option<$t::Item<'a>>,)+);
+            type Fetch = ($(Option<$t::Fetch>,)+);
+
+            fn req() -> ReqElem {
+                ReqElem::Or(vec![$($t::req()),+])
+            }
+
+            fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+                let fetch = ($($t::fetch(chunk),)+);
+                ($(fetch.$i.is_some())||+).then_some(fetch)
+            }
+
+            unsafe fn item<'a>(fetch: Self::Fetch, row: usize) -> Self::Item<'a> {
+                ($(fetch.$i.map(|f| $t::item(f, row)),)+)
+            }
+        }
+        unsafe impl<$($t),+> ReadOnly for Or<($($t,)+)> where $($t: ReadOnly),+ {}
+
+        unsafe impl<$($t),+> ArchetypeElement for With<($($t,)+)>
+        where $(With<$t>: ArchetypeElement),+
+        {
+            type Item<'a> = ();
+            type Fetch = ();
+
+            fn req() -> ReqElem {
+                ReqElem::And(vec![$(<With<$t> as ArchetypeElement>::req()),+])
+            }
+
+            fn fetch(chunk: &Chunk) -> Option<Self::Fetch> {
+                $(<With<$t> as ArchetypeElement>::fetch(chunk)?;)+
+                Some(())
+            }
+
+            unsafe fn item<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> {}
+        }
+        unsafe impl<$($t),+> ReadOnly for With<($($t,)+)> where $(With<$t>: ArchetypeElement),+ {}
+    };
+}
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11, T12 12);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11, T12 12, T13 13);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11, T12 12, T13 13, T14 14);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11, T12 12, T13 13, T14 14, T15 15);
@@ -8,8 +8,13 @@
 //! cwago_compライブラリのメインファイルです。
 // =========================
 
-mod ent;
-mod ty;
-mod data;
-mod iter;
-mod chunk;
\ No newline at end of file
+// 導出マクロが生成する`::cwago_comp`のパスをこのクレート内でも解決します。
+extern crate self as cwago_comp;
+
+pub mod ent;
+pub mod ty;
+pub mod data;
+pub mod iter;
+pub mod chunk;
+pub mod arch;
+pub mod messages;
\ No newline at end of file
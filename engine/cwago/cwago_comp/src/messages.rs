@@ -0,0 +1,88 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/src/messages.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_compの診断メッセージです。
+// =========================
+
+use cwago_utility::msg::Message;
+
+pub(crate) const TYPE_INFO_FAILED: Message = Message::new(
+    "comp.type_info_failed",
+    "'{name}'の動的型情報の作成に失敗しました。",
+    "Failed to create runtime type information for '{name}'."
+);
+
+pub(crate) const DESERIALIZE_FAILED: Message = Message::new(
+    "comp.deserialize_failed",
+    "'{name}'のデシリアライズに失敗しました。",
+    "Failed to deserialize '{name}'."
+);
+
+pub(crate) const TYPE_NAME_DUPLICATED: Message = Message::new(
+    "comp.type_name_duplicated",
+    "型名'{name}'は別の型に使われています。",
+    "Type name '{name}' is already used by another type."
+);
+
+pub(crate) const TYPE_RENAMED: Message = Message::new(
+    "comp.type_renamed",
+    "型は'{registered}'として登録済みのため、'{name}'では登録できません。",
+    "The type is already registered as '{registered}' and cannot be registered as '{name}'."
+);
+
+pub(crate) const TYPE_NOT_SERIALIZABLE: Message = Message::new(
+    "comp.type_not_serializable",
+    "'{name}'はシリアライズしない型です。",
+    "'{name}' is not serializable."
+);
+
+pub(crate) const ENTITIES_EXHAUSTED: Message = Message::new(
+    "comp.entities_exhausted",
+    "同時に存在可能なエンティティの数{max}に達しました。",
+    "The number of entities reached the limit of {max}."
+);
+
+pub(crate) const CHUNK_DUPLICATED_COLUMN: Message = Message::new(
+    "comp.chunk_duplicated_column",
+    "チャンクの列'{name}'が重複しています。",
+    "Chunk column '{name}' is duplicated."
+);
+
+pub(crate) const ENTITY_NOT_FOUND: Message = Message::new(
+    "comp.entity_not_found",
+    "エンティティ'{id}'は存在しません。",
+    "Entity '{id}' does not exist."
+);
+
+pub(crate) const QUERY_ALIASED: Message = Message::new(
+    "comp.query_aliased",
+    "クエリが'{name}'の可変参照と他の参照を同時に取得します。",
+    "Query takes a mutable reference to '{name}' together with another reference to it."
+);
+
+/// cwago_compのすべての診断メッセージです。
+pub const MESSAGES: &[&Message] = &[
+    &TYPE_INFO_FAILED,
+    &DESERIALIZE_FAILED,
+    &TYPE_NAME_DUPLICATED,
+    &TYPE_RENAMED,
+    &TYPE_NOT_SERIALIZABLE,
+    &ENTITIES_EXHAUSTED,
+    &CHUNK_DUPLICATED_COLUMN,
+    &ENTITY_NOT_FOUND,
+    &QUERY_ALIASED,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        cwago_utility::msg::check_catalog_source(MESSAGES, include_str!("messages.rs"));
+    }
+}
@@ -11,45 +11,208 @@
 use std::{
     mem::{
         size_of, 
+        align_of, 
         forget
     }, 
-    ptr::drop_in_place, sync::Once, any::type_name
+    ptr::drop_in_place, 
+    sync::{
+        OnceLock, 
+        RwLock
+    }, 
+    any::{
+        type_name, 
+        TypeId
+    }
+};
+
+use cwago_utility::{
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result, 
+        ResultExt
+    }, 
+    hash::FxHashMap, 
+    msg, 
+    name::Name
+};
+
+use crate::messages::{
+    DESERIALIZE_FAILED, 
+    TYPE_INFO_FAILED, 
+    TYPE_NAME_DUPLICATED, 
+    TYPE_NOT_SERIALIZABLE, 
+    TYPE_RENAMED
 };
 
-use cwago_utility::log::error;
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_error() {
+        let info = info_of::<u32>("u32");
+        assert_eq!(info.name(), Name::new("u32"));
+        let mut value = 7u32;
+        let ptr = &mut value as *mut u32 as *mut u8;
+
+        // 正しいデータはデシリアライズできるかテストします。
+        let mut de = serde_json::Deserializer::from_reader("42".as_bytes());
+        let mut de = <dyn erased_serde::Deserializer>::erase(&mut de);
+        unsafe { deserialize_buf(info, ptr, &mut de) }.expect("デシリアライズに失敗しました。");
+        assert_eq!(value, 42);
+
+        // 壊れたデータはエラーとして返り、値が変化しないかテストします。
+        let mut de = serde_json::Deserializer::from_reader("\"broken\"".as_bytes());
+        let mut de = <dyn erased_serde::Deserializer>::erase(&mut de);
+        let e = unsafe { deserialize_buf(info, ptr, &mut de) }.expect_err("壊れたデータを受け付けました。");
+        assert_eq!(e.kind(), ErrorKind::Deserialize);
+        assert!(std::error::Error::source(&e).is_some());
+        assert_eq!(value, 42);
+    }
+
+    // 異なる大きさと初期値を持つ型を並べて定義します。
+    macro_rules! types {
+        ($($ty:ident($inner:ty) = $value:expr;)*) => {
+            $(
+                #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+                struct $ty($inner);
+                impl Default for $ty {
+                    fn default() -> Self {
+                        $ty($value)
+                    }
+                }
+            )*
+        };
+    }
+    types! {
+        TypeA(u8) = 1;
+        TypeB(u16) = 2;
+        TypeC(u32) = 3;
+        TypeD(u64) = 4;
+        TypeE(u128) = 5;
+        TypeF([u8; 3]) = [6; 3];
+        TypeG([u64; 5]) = [7; 5];
+        TypeH(String) = "h".to_string();
+        TypeI(Vec<u32>) = vec![9, 9];
+        TypeJ((u8, u64)) = (10, 10);
+    }
+
+    // 型情報で初期化した値が型の初期値と一致するかを確認します。
+    fn check<T>(name: &'static str) -> &'static Info
+    where T: Default + PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned + 'static
+    {
+        let info = info_of::<T>(name);
+        assert_eq!(info.name(), Name::new(name));
+        assert_eq!(info.size(), size_of::<T>(), "{}の型サイズが一致しません。", name);
+        assert_eq!(info.align(), align_of::<T>(), "{}のアラインメントが一致しません。", name);
+        assert_eq!(info.type_id(), TypeId::of::<T>());
+        assert!(std::ptr::eq(info_by_type_id(TypeId::of::<T>()).unwrap(), info));
+        assert!(std::ptr::eq(info_by_name(name).unwrap(), info));
+        assert!(std::ptr::eq(info_by_index(info.index()).unwrap(), info));
+
+        let mut value = std::mem::MaybeUninit::<T>::uninit();
+        unsafe {
+            initialize_buf(info, value.as_mut_ptr() as *mut u8);
+            assert_eq!(value.assume_init_ref(), &T::default(), "{}の初期値が一致しません。", name);
+            drop_buf(info, value.as_mut_ptr() as *mut u8);
+        }
+        info
+    }
+
+    #[test]
+    fn test_registry() {
+        let infos = [
+            check::<TypeA>("test.registry.a"),
+            check::<TypeB>("test.registry.b"),
+            check::<TypeC>("test.registry.c"),
+            check::<TypeD>("test.registry.d"),
+            check::<TypeE>("test.registry.e"),
+            check::<TypeF>("test.registry.f"),
+            check::<TypeG>("test.registry.g"),
+            check::<TypeH>("test.registry.h"),
+            check::<TypeI>("test.registry.i"),
+            check::<TypeJ>("test.registry.j"),
+        ];
+        let mut indices: Vec<_> = infos.iter().map(|info| info.index()).collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), infos.len(), "索引が重複しています。");
+
+        // 2回目以降は同じ型情報を返します。
+        assert!(std::ptr::eq(info_of::<TypeA>("test.registry.a"), infos[0]));
+        assert!(info_by_name("test.registry.missing").is_none());
+    }
+
+    #[test]
+    fn test_registry_conflict() {
+        info_of::<i8>("test.conflict.i8");
+        let e = try_info_of::<i16>("test.conflict.i8").expect_err("重複した名前を受け付けました。");
+        assert_eq!(e.kind(), ErrorKind::Type);
+        let e = try_info_of::<i8>("test.conflict.other").expect_err("別名での登録を受け付けました。");
+        assert_eq!(e.kind(), ErrorKind::Type);
+        assert!(info_by_name("test.conflict.other").is_none());
+    }
+
+    #[test]
+    fn test_registry_threads() {
+        let infos: Vec<usize> = (0..8)
+            .map(|_| std::thread::spawn(|| info_of::<i64>("test.threads.i64") as *const Info as usize))
+            .map(|h| h.join().unwrap())
+            .collect();
+        assert!(infos.iter().all(|i| *i == infos[0]), "同じ型情報を共有する必要があります。");
+    }
+}
 
 /// 動的型情報です。
 pub struct Info {
-    name: &'static str,                    // 型名です。
+    name: Name,                            // 型名です。
+    id: TypeId,                            // 型の識別子です。
+    index: usize,                          // 登録順の索引です。
+    version: u32,                          // スキーマの版です。
+    tag: bool,                             // 値を持たない目印の型かです。
     size: usize,                           // 型サイズです。
+    align: usize,                          // アラインメントです。
     init: unsafe fn(*mut ()),              // デフォルト初期化します。
     drop: unsafe fn(*mut ()),              // ドロップします。
-    mov: unsafe fn(*mut(), *mut()),        // 第1引数の内容を第2引数の位置にムーブします。
-    cast_se: unsafe fn(
-        *mut()
-    ) -> *mut dyn erased_serde::Serialize, // erased_serde::Serializeトレイトポインタに変換します。
-    run_de: unsafe fn(
-        *mut(), 
-        &mut (dyn for<'s> erased_serde::Deserializer<'s>)
-    ) -> Result<(), erased_serde::Error>,  // デシリアライズします。
+    mov: unsafe fn(*mut(), *mut()),        // 第1引数の値を未初期化の第2引数の位置にムーブします。
+    cast_se: Option<SerializeFn>,          // erased_serde::Serializeトレイトポインタに変換します。
+    run_de: Option<DeserializeFn>,         // デシリアライズします。
 }
+
+// シリアライズトレイトポインタへの変換関数の型です。
+type SerializeFn = unsafe fn(*mut ()) -> *mut dyn erased_serde::Serialize;
+
+// デシリアライズ関数の型です。
+type DeserializeFn = unsafe fn(
+    *mut (), 
+    &mut dyn for<'s> erased_serde::Deserializer<'s>
+) -> std::result::Result<(), erased_serde::Error>;
+
 impl Info {
     /// 作成します。
     /// 
     /// # 引数
     /// 
-    /// * `name` - 他の型と区別可能な一意の名前です。
+    /// * `desc` - 登録の設定です。
+    /// * `index` - 登録順の索引です。
     /// 
     /// # 戻り値
     /// 
-    /// インスタンスです。
+    /// シリアライズできないインスタンスです。
     /// 
-    fn new<'de, T>(name: &'static str) -> Self
-    where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static 
+    fn new<T>(desc: &InfoDesc, index: usize) -> Self
+    where T: Sized + Default + 'static 
     {
         Self { 
-            name, 
+            name: Name::from_static(desc.name), 
+            id: TypeId::of::<T>(), 
+            index, 
+            version: desc.version, 
+            tag: desc.tag, 
             size: size_of::<T>(), 
+            align: align_of::<T>(), 
             init: |ptr|{
                 let ptr = ptr as *mut T;
                 let ini = T::default();
@@ -63,23 +226,36 @@ impl Info {
                 // ptrをドロップします。
                 unsafe{ drop_in_place(ptr) }; 
             }, 
-            mov: |l, r|{
-                let l = l as *mut T;
-                let r = r as *mut T;
-                // lをドロップします。
-                unsafe{ drop_in_place(l) };    
-                // rをlにバイト単位でコピーします。
-                unsafe{ l.copy_from(r, 1) };
+            mov: |from, to|{
+                let from = from as *mut T;
+                let to = to as *mut T;
+                // fromをtoにバイト単位でコピーします。以降fromは未初期化として扱います。
+                unsafe{ to.copy_from_nonoverlapping(from, 1) };
             }, 
-            cast_se: |ptr|{
+            cast_se: None, 
+            run_de: None, 
+        }
+    }
+
+    /// シリアライズできるようにします。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    fn with_serde<'de, T>(self) -> Self
+    where T: Sized + serde::Serialize + serde::Deserialize<'de> + 'static 
+    {
+        Self { 
+            cast_se: Some(|ptr|{
                 let ptr = ptr as *mut T;
-                let ptr = ptr as *mut dyn erased_serde::Serialize;
-                ptr
-            }, run_de: {
+                ptr as *mut dyn erased_serde::Serialize
+            }), 
+            run_de: Some({
                 |
                     ptr: *mut(), 
-                    de: &mut (dyn for<'s> erased_serde::Deserializer<'s>)
-                | -> Result<(), erased_serde::Error> 
+                    de: &mut dyn for<'s> erased_serde::Deserializer<'s>
+                | -> std::result::Result<(), erased_serde::Error> 
                 {
                     let ptr = ptr as *mut T;
                     let val = erased_serde::deserialize::<T>(de)?;
@@ -91,7 +267,8 @@ impl Info {
                     forget(val);
                     Ok(())
                 }
-            } 
+            }), 
+            ..self
         }
     }
 
@@ -101,10 +278,62 @@ impl Info {
     /// 
     /// 他の型と区別可能な一意の名前です。
     /// 
-    pub fn name(&self) -> &'static str {
+    pub fn name(&self) -> Name {
         self.name
     }
 
+    /// 型の識別子を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 型の識別子です。
+    /// 
+    pub fn type_id(&self) -> TypeId {
+        self.id
+    }
+
+    /// 登録順の索引を取得します。
+    /// 
+    /// 登録された型に0から隙間なく割り当てられるため、ビット集合の位置に使えます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 索引です。
+    /// 
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// スキーマの版を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 保存したデータとの互換性を判断するための版です。
+    /// 
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// 値を持たない目印の型かを取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 目印の型の場合はtrueです。
+    /// 
+    pub fn is_tag(&self) -> bool {
+        self.tag
+    }
+
+    /// シリアライズできるかを取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// シリアライズできる場合はtrueです。
+    /// 
+    pub fn is_serializable(&self) -> bool {
+        self.cast_se.is_some()
+    }
+
     /// 型サイズを取得します。
     /// 
     /// # 戻り値
@@ -115,6 +344,16 @@ impl Info {
         self.size
     }
 
+    /// アラインメントを取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// アラインメントです。常に2の累乗です。
+    /// 
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
     /// バッファ上の位置を初期化します。
     /// 
     /// # 引数
@@ -137,10 +376,12 @@ impl Info {
 
     /// バッファ上のある位置から別の位置へ値をムーブします。
     /// 
+    /// ムーブ先の値はドロップしません。ムーブ後の`from`は未初期化になります。
+    /// 
     /// # 引数
     /// 
     /// * `from` - ムーブする値の位置のポインタです。
-    /// * `to` - ムーブ先の位置のポインタです。
+    /// * `to` - ムーブ先の未初期化の位置のポインタです。
     /// 
     unsafe fn move_ptr(&self, from: *mut (), to: *mut ()) {
         (self.mov)(from, to)
@@ -154,13 +395,13 @@ impl Info {
     /// 
     /// # 戻り値
     /// 
-    /// 変換したシリアライズトレイトポインタです。
+    /// 変換したシリアライズトレイトポインタ、シリアライズできない型の場合はNoneです。
     /// 
     unsafe fn cast_serialize(
         &self, 
         ptr: *mut ()
-    ) -> *mut dyn erased_serde::Serialize {
-        (self.cast_se)(ptr)
+    ) -> Option<*mut dyn erased_serde::Serialize> {
+        self.cast_se.map(|cast_se| cast_se(ptr))
     }
 
     /// デシリアライズします。
@@ -177,13 +418,72 @@ impl Info {
     unsafe fn deserialize(
         &self, 
         ptr: *mut (), 
-        deserializer: &mut (dyn for<'de> erased_serde::Deserializer<'de>)
-    ) -> Result<(), erased_serde::Error> {
-        (self.run_de)(ptr, deserializer)
+        deserializer: &mut dyn for<'de> erased_serde::Deserializer<'de>
+    ) -> Result<()> {
+        let Some(run_de) = self.run_de else {
+            return Err(CwagoError::new(ErrorKind::Deserialize, msg!(TYPE_NOT_SERIALIZABLE, name = self.name)));
+        };
+        run_de(ptr, deserializer).map_err(|e| {
+            CwagoError::from_source(ErrorKind::Deserialize, e)
+                .context(msg!(DESERIALIZE_FAILED, name = self.name))
+        })
+    }
+}
+
+impl std::fmt::Debug for Info {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Info")
+            .field("name", &self.name)
+            .field("index", &self.index)
+            .field("version", &self.version)
+            .field("tag", &self.tag)
+            .field("size", &self.size)
+            .field("align", &self.align)
+            .finish()
+    }
+}
+
+// 登録済みの型情報です。
+#[derive(Default)]
+struct Registry {
+    by_id: FxHashMap<TypeId, &'static Info>, // 型の識別子から型情報を引きます。
+    by_name: FxHashMap<Name, &'static Info>, // 型名から型情報を引きます。
+    by_index: Vec<&'static Info>,            // 登録順の型情報です。
+}
+
+// 型情報の登録表です。
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// 型情報の登録の設定です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfoDesc {
+    /// 他の型と区別可能な一意の名前です。
+    pub name: &'static str,
+    /// スキーマの版です。
+    pub version: u32,
+    /// 値を持たない目印の型かです。
+    pub tag: bool,
+}
+impl InfoDesc {
+    /// 版が0の設定を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - 他の型と区別可能な一意の名前です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub const fn new(name: &'static str) -> Self {
+        Self { name, version: 0, tag: false }
     }
 }
 
-/// 作成します。
+/// 型情報を取得します。未登録の場合は登録します。
 /// 
 /// # 引数
 /// 
@@ -191,23 +491,190 @@ impl Info {
 /// 
 /// # 戻り値
 /// 
-/// インスタンスです。
+/// 型情報です。
+/// 
+/// # 異常終了
+/// 
+/// 名前が他の型に使われている、または、型が別の名前で登録済みの場合に異常終了します。
 /// 
 pub fn info_of<'de, T>(name: &'static str) -> &'static Info
 where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static 
 {
-    static mut INFO: Option<Info> = None;
-    static ONCE: Once = Once::new();
-    ONCE.call_once(|| unsafe{
-        INFO = Some(Info::new::<'de, T>(name));
-    });
-    match unsafe{ INFO.as_ref() } {
+    try_info_of::<'de, T>(name).or_abort()
+}
+
+/// 型情報を取得します。未登録の場合は登録します。
+/// 
+/// # 引数
+/// 
+/// * `name` - 他の型と区別可能な一意の名前です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、名前が他の型に使われている、または、型が別の名前で登録済みの場合はエラーです。
+/// 
+pub fn try_info_of<'de, T>(name: &'static str) -> Result<&'static Info>
+where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static 
+{
+    try_register_serde::<'de, T>(InfoDesc::new(name))
+}
+
+/// シリアライズできる型の型情報を取得します。未登録の場合は登録します。
+/// 
+/// # 引数
+/// 
+/// * `desc` - 登録の設定です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報です。
+/// 
+/// # 異常終了
+/// 
+/// 名前が他の型に使われている、または、型が別の名前で登録済みの場合に異常終了します。
+/// 
+pub fn register_serde<'de, T>(desc: InfoDesc) -> &'static Info
+where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static 
+{
+    try_register_serde::<'de, T>(desc).or_abort()
+}
+
+/// シリアライズできる型の型情報を取得します。未登録の場合は登録します。
+/// 
+/// # 引数
+/// 
+/// * `desc` - 登録の設定です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、名前が他の型に使われている、または、型が別の名前で登録済みの場合はエラーです。
+/// 
+pub fn try_register_serde<'de, T>(desc: InfoDesc) -> Result<&'static Info>
+where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static 
+{
+    try_register_with::<T>(desc, |index| Info::new::<T>(&desc, index).with_serde::<'de, T>())
+}
+
+/// シリアライズしない型の型情報を取得します。未登録の場合は登録します。
+/// 
+/// # 引数
+/// 
+/// * `desc` - 登録の設定です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報です。
+/// 
+/// # 異常終了
+/// 
+/// 名前が他の型に使われている、または、型が別の名前で登録済みの場合に異常終了します。
+/// 
+pub fn register<T>(desc: InfoDesc) -> &'static Info
+where T: Sized + Default + 'static 
+{
+    try_register::<T>(desc).or_abort()
+}
+
+/// シリアライズしない型の型情報を取得します。未登録の場合は登録します。
+/// 
+/// # 引数
+/// 
+/// * `desc` - 登録の設定です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、名前が他の型に使われている、または、型が別の名前で登録済みの場合はエラーです。
+/// 
+pub fn try_register<T>(desc: InfoDesc) -> Result<&'static Info>
+where T: Sized + Default + 'static 
+{
+    try_register_with::<T>(desc, |index| Info::new::<T>(&desc, index))
+}
+
+// 型情報を取得します。未登録の場合はbuildで作成して登録します。
+fn try_register_with<T: 'static>(desc: InfoDesc, build: impl FnOnce(usize) -> Info) -> Result<&'static Info> {
+    let id = TypeId::of::<T>();
+    let found = registry().read().unwrap_or_else(|e| e.into_inner()).by_id.get(&id).copied();
+    let info = match found {
         Some(info) => info,
         None => {
-            error!("'{}'の動的型情報の作成に失敗しました。", type_name::<T>());
-            panic!()
+            let mut registry = registry().write().unwrap_or_else(|e| e.into_inner());
+            // 読み込みから書き込みの間に、他のスレッドが登録している場合があります。
+            match registry.by_id.get(&id).copied() {
+                Some(info) => info,
+                None => {
+                    let name = Name::from_static(desc.name);
+                    if registry.by_name.contains_key(&name) {
+                        return Err(CwagoError::new(ErrorKind::Type, msg!(TYPE_NAME_DUPLICATED, name = name)))
+                            .context(msg!(TYPE_INFO_FAILED, name = type_name::<T>()));
+                    }
+                    let info: &'static Info = Box::leak(Box::new(build(registry.by_index.len())));
+                    registry.by_id.insert(id, info);
+                    registry.by_name.insert(name, info);
+                    registry.by_index.push(info);
+                    return Ok(info);
+                },
+            }
         },
+    };
+    if info.name.as_str() != desc.name {
+        return Err(CwagoError::new(ErrorKind::Type, msg!(TYPE_RENAMED, name = desc.name, registered = info.name)))
+            .context(msg!(TYPE_INFO_FAILED, name = type_name::<T>()));
     }
+    Ok(info)
+}
+
+/// 型の識別子から登録済みの型情報を取得します。
+/// 
+/// # 引数
+/// 
+/// * `id` - 型の識別子です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、未登録の場合はNoneです。
+/// 
+pub fn info_by_type_id(id: TypeId) -> Option<&'static Info> {
+    registry().read().unwrap_or_else(|e| e.into_inner()).by_id.get(&id).copied()
+}
+
+/// 型名から登録済みの型情報を取得します。
+/// 
+/// # 引数
+/// 
+/// * `name` - 型名です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、未登録の場合はNoneです。
+/// 
+pub fn info_by_name(name: &str) -> Option<&'static Info> {
+    let name = Name::get(name)?;
+    registry().read().unwrap_or_else(|e| e.into_inner()).by_name.get(&name).copied()
+}
+
+/// 索引から登録済みの型情報を取得します。
+/// 
+/// # 引数
+/// 
+/// * `index` - 登録順の索引です。
+/// 
+/// # 戻り値
+/// 
+/// 型情報、未登録の場合はNoneです。
+/// 
+pub fn info_by_index(index: usize) -> Option<&'static Info> {
+    registry().read().unwrap_or_else(|e| e.into_inner()).by_index.get(index).copied()
+}
+
+/// 登録済みの型の数を取得します。
+/// 
+/// # 戻り値
+/// 
+/// 型の数です。索引はこれより小さい値です。
+/// 
+pub fn info_count() -> usize {
+    registry().read().unwrap_or_else(|e| e.into_inner()).by_index.len()
 }
 
 /// バッファ上の位置を初期化します。
@@ -216,6 +683,10 @@ where T: Sized + Default + serde::Serialize + serde::Deserialize<'de> + 'static
 /// 
 /// * `ptr` - バッファ上の初期化位置のポインタです。
 /// 
+/// # Safety
+/// 
+/// `ptr`は`info`の型に対して整列された未初期化の領域を指している必要があります。
+/// 
 pub unsafe fn initialize_buf(info: &'static Info, ptr: *mut u8) {
     info.initialize(ptr as *mut ())
 }
@@ -226,16 +697,26 @@ pub unsafe fn initialize_buf(info: &'static Info, ptr: *mut u8) {
 /// 
 /// * `ptr` - バッファ上の解体位置のポインタです。
 /// 
+/// # Safety
+/// 
+/// `ptr`は`info`の型の初期化済みの値を指している必要があります。
+/// 
 pub unsafe fn drop_buf(info: &'static Info, ptr: *mut u8) {
     info.drop(ptr as *mut ())
 }
 
 /// バッファ上のある位置から別の位置へ値をムーブします。
 /// 
+/// ムーブ先の値はドロップしません。ムーブ後の`from`は未初期化になります。
+/// 
 /// # 引数
 /// 
 /// * `from` - ムーブする値の位置のポインタです。
-/// * `to` - ムーブ先の位置のポインタです。
+/// * `to` - ムーブ先の未初期化の位置のポインタです。
+/// 
+/// # Safety
+/// 
+/// `from`は`info`の型の初期化済みの値を、`to`は整列された未初期化の領域を指し、互いに重ならない必要があります。
 /// 
 pub unsafe fn move_buf(info: &'static Info, from: *mut u8, to: *mut u8) {
     info.move_ptr(from as *mut (), to as *mut ())
@@ -249,12 +730,16 @@ pub unsafe fn move_buf(info: &'static Info, from: *mut u8, to: *mut u8) {
 /// 
 /// # 戻り値
 /// 
-/// 変換したシリアライズトレイトポインタです。
+/// 変換したシリアライズトレイトポインタ、シリアライズできない型の場合はNoneです。
+/// 
+/// # Safety
+/// 
+/// `ptr`は`info`の型の初期化済みの値を指している必要があります。
 /// 
 pub unsafe fn cast_serialize_from_buf(
     info: &'static Info, 
     ptr: *mut u8
-) -> *mut dyn erased_serde::Serialize {
+) -> Option<*mut dyn erased_serde::Serialize> {
     info.cast_serialize(ptr as *mut ())
 }
 
@@ -269,10 +754,14 @@ pub unsafe fn cast_serialize_from_buf(
 /// 
 /// 失敗した際、エラーを返します。
 /// 
+/// # Safety
+/// 
+/// `ptr`は`info`の型の初期化済みの値を指している必要があります。
+/// 
 pub unsafe fn deserialize_buf(
     info: &'static Info, 
     ptr: *mut u8, 
-    deserializer: &mut (dyn for<'de> erased_serde::Deserializer<'de>)
-) -> Result<(), erased_serde::Error> {
+    deserializer: &mut dyn for<'de> erased_serde::Deserializer<'de>
+) -> Result<()> {
     info.deserialize(ptr as *mut (), deserializer)
 }
\ No newline at end of file
@@ -0,0 +1,17 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/compile_fail.rs
+// (C) 2023 CwagoCommunity.
+//
+//! クエリと導出マクロの誤用がコンパイルエラーになることを確認します。
+// =========================
+
+#[test]
+// Miriではコンパイラを起動できないため除外します。
+#[cfg_attr(miri, ignore)]
+fn test_compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
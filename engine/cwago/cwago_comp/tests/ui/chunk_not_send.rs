@@ -0,0 +1,17 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/chunk_not_send.rs
+// (C) 2023 CwagoCommunity.
+//
+//! チャンクは他のスレッドへ移動できません。
+// =========================
+
+use cwago_comp::chunk::Chunk;
+
+fn assert_send<T: Send>() {}
+
+fn main() {
+    assert_send::<Chunk>();
+}
@@ -0,0 +1,18 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/derive_duplicated.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 同じ設定を重ねて指定できません。
+// =========================
+
+use cwago_comp::data::Data;
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+#[data(version = 1, version = 2)]
+struct Pos(i32);
+
+fn main() {}
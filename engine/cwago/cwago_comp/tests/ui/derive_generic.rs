@@ -0,0 +1,17 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/derive_generic.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 型引数を持つ型には導出できません。
+// =========================
+
+use cwago_comp::data::Data;
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+struct Wrapper<T>(T);
+
+fn main() {}
@@ -0,0 +1,17 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/derive_tag_not_zst.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 型サイズが0でない型は目印にできません。
+// =========================
+
+use cwago_comp::data::Data;
+
+#[derive(Default, Data)]
+#[data(tag, no_serialize)]
+struct Frozen(bool);
+
+fn main() {}
@@ -0,0 +1,17 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/derive_unknown_key.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 未知の設定は指定できません。
+// =========================
+
+use cwago_comp::data::Data;
+
+#[derive(Default, Data)]
+#[data(no_serialize, size = 4)]
+struct Pos(i32);
+
+fn main() {}
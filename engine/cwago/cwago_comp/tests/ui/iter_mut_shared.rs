@@ -0,0 +1,27 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/iter_mut_shared.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 可変参照を含むクエリは共有参照で走査できません。
+// =========================
+
+use cwago_comp::{
+    arch::Archetypes, 
+    data::Data, 
+    iter::Query
+};
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+struct Pos(i32);
+
+fn main() {
+    let archs = Archetypes::new();
+    let mut query = Query::<(&mut Pos,)>::new();
+    for (pos,) in query.iter(&archs) {
+        pos.0 += 1;
+    }
+}
@@ -0,0 +1,22 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/not_data.rs
+// (C) 2023 CwagoCommunity.
+//
+//! Dataでない型は要求できません。
+// =========================
+
+use cwago_comp::{
+    data::Data, 
+    iter::Query
+};
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+struct Pos(i32);
+
+fn main() {
+    let _ = Query::<(&u32, &Pos)>::new();
+}
@@ -0,0 +1,25 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/or_not_tuple.rs
+// (C) 2023 CwagoCommunity.
+//
+//! Orは組のみを受け取ります。
+// =========================
+
+use cwago_comp::{
+    data::Data, 
+    iter::{
+        Or, 
+        Query
+    }
+};
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+struct Pos(i32);
+
+fn main() {
+    let _ = Query::<Or<&Pos>>::new();
+}
@@ -0,0 +1,28 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp/tests/ui/too_many.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 組の要素は16個までです。
+// =========================
+
+use cwago_comp::{
+    data::Data, 
+    iter::Query
+};
+
+#[derive(Default, Data)]
+#[data(no_serialize)]
+struct Pos(i32);
+
+type Wide<'a> = (
+    &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos,
+    &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos, &'a Pos,
+    &'a Pos,
+);
+
+fn main() {
+    let _ = Query::<Wide>::new();
+}
@@ -0,0 +1,138 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_comp_derive/src/lib.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_compの導出マクロを提供します。
+// =========================
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    DeriveInput,
+    Error,
+    LitInt,
+    LitStr
+};
+
+/// `cwago_comp::data::Data`を実装します。
+/// 
+/// 型は`Default`を実装している必要があります。
+/// `#[data(...)]`属性で登録の設定を指定します。
+/// 
+/// * `name = "game::Health"` - 型名です。省略した場合はモジュールのパスと型の名前を連結します。
+/// * `version = 1` - スキーマの版です。省略した場合は0です。
+/// * `no_serialize` - シリアライズしません。`Serialize`と`Deserialize`の実装が不要になります。
+/// * `tag` - 値を持たない目印の型です。型サイズが0でない場合はコンパイルエラーです。
+/// 
+#[proc_macro_derive(Data, attributes(data))]
+pub fn derive_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+// 属性の設定です。
+#[derive(Default)]
+struct Attrs {
+    name: Option<LitStr>,    // 型名です。
+    version: Option<LitInt>, // スキーマの版です。
+    no_serialize: bool,      // シリアライズしないかです。
+    tag: bool,               // 目印の型かです。
+}
+
+// 属性を読み込みます。
+fn parse_attrs(input: &DeriveInput) -> syn::Result<Attrs> {
+    let mut attrs = Attrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("data")) {
+        attr.parse_nested_meta(|meta| {
+            let duplicated = || meta.error("duplicated data attribute");
+            if meta.path.is_ident("name") {
+                if attrs.name.is_some() {
+                    return Err(duplicated());
+                }
+                let name: LitStr = meta.value()?.parse()?;
+                if name.value().is_empty() {
+                    return Err(Error::new(name.span(), "type name must not be empty"));
+                }
+                attrs.name = Some(name);
+            } else if meta.path.is_ident("version") {
+                if attrs.version.is_some() {
+                    return Err(duplicated());
+                }
+                let version: LitInt = meta.value()?.parse()?;
+                version.base10_parse::<u32>()?;
+                attrs.version = Some(version);
+            } else if meta.path.is_ident("no_serialize") {
+                if attrs.no_serialize {
+                    return Err(duplicated());
+                }
+                attrs.no_serialize = true;
+            } else if meta.path.is_ident("tag") {
+                if attrs.tag {
+                    return Err(duplicated());
+                }
+                attrs.tag = true;
+            } else {
+                return Err(meta.error("expected `name`, `version`, `no_serialize` or `tag`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+// 実装を生成します。
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    // 型情報は型毎に1つのため、型引数毎に名前を分けられません。
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "#[derive(Data)] does not support generic types"));
+    }
+    let attrs = parse_attrs(input)?;
+    let ident = &input.ident;
+    let name = match &attrs.name {
+        Some(name) => quote!(#name),
+        None => {
+            let ident = LitStr::new(&ident.to_string(), Span::call_site());
+            quote!(::std::concat!(::std::module_path!(), "::", #ident))
+        },
+    };
+    let version = match &attrs.version {
+        Some(version) => quote!(#version),
+        None => quote!(0),
+    };
+    let tag = attrs.tag;
+    let register = if attrs.no_serialize {
+        quote!(::cwago_comp::ty::register::<Self>)
+    } else {
+        quote!(::cwago_comp::ty::register_serde::<Self>)
+    };
+    let assert_tag = tag.then(|| quote! {
+        const _: () = ::std::assert!(
+            ::std::mem::size_of::<#ident>() == 0,
+            "#[data(tag)] requires a zero-sized type"
+        );
+    });
+
+    Ok(quote! {
+        #assert_tag
+
+        impl ::cwago_comp::data::Data for #ident {
+            fn info() -> &'static ::cwago_comp::ty::Info {
+                // 型毎の実装のため、この静的変数は型毎に別です。
+                static INFO: ::std::sync::OnceLock<&'static ::cwago_comp::ty::Info> = ::std::sync::OnceLock::new();
+                *INFO.get_or_init(|| #register(::cwago_comp::ty::InfoDesc {
+                    name: #name,
+                    version: #version,
+                    tag: #tag,
+                }))
+            }
+        }
+    })
+}
@@ -0,0 +1,58 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/lib.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_mathライブラリのメインファイルです。
+//! 
+//! 座標系は右手系、行列は列優先で、ベクトルに左から掛けます。
+//! すべての型は`Default`と`serde`を実装し、コンポーネントのフィールドにそのまま使えます。
+// =========================
+
+pub mod vec;
+pub mod mat;
+pub mod quat;
+pub mod shape;
+pub mod messages;
+mod simd;
+
+pub use vec::{
+    Vec2, 
+    Vec3, 
+    Vec4
+};
+pub use mat::{
+    Mat3, 
+    Mat4
+};
+pub use quat::Quat;
+pub use shape::{
+    Aabb, 
+    Plane, 
+    Ray, 
+    Side
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // コンポーネントのフィールドとして使うための制約です。
+    fn assert_field<'de, T>()
+    where T: Sized + Copy + Default + Send + Sync + serde::Serialize + serde::Deserialize<'de> + 'static {}
+
+    #[test]
+    fn test_field_bounds() {
+        assert_field::<Vec2>();
+        assert_field::<Vec3>();
+        assert_field::<Vec4>();
+        assert_field::<Mat3>();
+        assert_field::<Mat4>();
+        assert_field::<Quat>();
+        assert_field::<Aabb>();
+        assert_field::<Ray>();
+        assert_field::<Plane>();
+    }
+}
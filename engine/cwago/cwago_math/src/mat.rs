@@ -0,0 +1,488 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/mat.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 列優先の行列を提供します。
+// =========================
+
+use std::ops::{
+    Mul,
+    MulAssign
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    quat::Quat,
+    simd,
+    vec::{
+        Vec3,
+        Vec4
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_mat3() {
+        let m = Mat3::from_cols(
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(1.0, 3.0, 0.0),
+            Vec3::new(0.0, 1.0, 4.0)
+        );
+        assert_eq!(m.determinant(), 24.0);
+        let inv = m.inverse().expect("逆行列が求まりません。");
+        assert!((m * inv).abs_diff_eq(Mat3::IDENTITY, 1e-6));
+        assert_eq!(m * Vec3::new(1.0, 1.0, 1.0), Vec3::new(3.0, 4.0, 4.0));
+        assert_eq!(m.transpose().transpose(), m);
+        assert!(Mat3::from_cols(Vec3::X, Vec3::X, Vec3::Z).inverse().is_none());
+
+        let r = Mat3::from_quat(Quat::from_rotation_z(FRAC_PI_2));
+        assert!((r * Vec3::X).abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn test_mat4() {
+        let q = Quat::from_rotation_y(FRAC_PI_2);
+        let m = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), q, Vec3::new(1.0, 2.0, 3.0));
+        assert!(m.transform_point3(Vec3::X).abs_diff_eq(Vec3::new(1.0, 2.0, 1.0), 1e-6));
+        assert!(m.transform_vector3(Vec3::X).abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-6));
+
+        let inv = m.inverse().expect("逆行列が求まりません。");
+        assert!((m * inv).abs_diff_eq(Mat4::IDENTITY, 1e-5));
+        assert!((inv * m).abs_diff_eq(Mat4::IDENTITY, 1e-5));
+        assert!((m.determinant() - 8.0).abs() < 1e-5);
+        assert_eq!(Mat4::default(), Mat4::IDENTITY);
+
+        let t = Mat4::from_translation(Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!((t * t).transform_point3(Vec3::ZERO), Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_camera() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        assert!(view.transform_point3(Vec3::ZERO).abs_diff_eq(Vec3::new(0.0, 0.0, -5.0), 1e-6));
+
+        // 近平面は深度0、遠平面は深度1になります。
+        let proj = Mat4::perspective_rh(FRAC_PI_2, 1.0, 1.0, 10.0);
+        let near = proj * Vec4::new(0.0, 0.0, -1.0, 1.0);
+        let far = proj * Vec4::new(0.0, 0.0, -10.0, 1.0);
+        assert!((near.z / near.w).abs() < 1e-6);
+        assert!((far.z / far.w - 1.0).abs() < 1e-6);
+
+        let ortho = Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 0.0, 10.0);
+        assert!(ortho.transform_point3(Vec3::new(1.0, 1.0, -10.0)).abs_diff_eq(Vec3::new(1.0, 1.0, 1.0), 1e-6));
+    }
+}
+
+/// 3x3行列です。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Mat3 {
+    /// 列です。
+    pub cols: [Vec3; 3],
+}
+impl Mat3 {
+    /// 単位行列です。
+    pub const IDENTITY: Mat3 = Mat3::from_cols(Vec3::X, Vec3::Y, Vec3::Z);
+    /// すべての要素が0の行列です。
+    pub const ZERO: Mat3 = Mat3::from_cols(Vec3::ZERO, Vec3::ZERO, Vec3::ZERO);
+
+    /// 列から作成します。
+    #[inline]
+    pub const fn from_cols(x: Vec3, y: Vec3, z: Vec3) -> Mat3 {
+        Mat3 { cols: [x, y, z] }
+    }
+
+    /// 拡大縮小から作成します。
+    #[inline]
+    pub const fn from_scale(scale: Vec3) -> Mat3 {
+        Mat3::from_cols(
+            Vec3::new(scale.x, 0.0, 0.0),
+            Vec3::new(0.0, scale.y, 0.0),
+            Vec3::new(0.0, 0.0, scale.z)
+        )
+    }
+
+    /// 回転から作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `q` - 単位四元数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 回転行列です。
+    /// 
+    pub fn from_quat(q: Quat) -> Mat3 {
+        let (x2, y2, z2) = (q.x + q.x, q.y + q.y, q.z + q.z);
+        let (xx, xy, xz) = (q.x * x2, q.x * y2, q.x * z2);
+        let (yy, yz, zz) = (q.y * y2, q.y * z2, q.z * z2);
+        let (wx, wy, wz) = (q.w * x2, q.w * y2, q.w * z2);
+        Mat3::from_cols(
+            Vec3::new(1.0 - (yy + zz), xy + wz, xz - wy),
+            Vec3::new(xy - wz, 1.0 - (xx + zz), yz + wx),
+            Vec3::new(xz + wy, yz - wx, 1.0 - (xx + yy))
+        )
+    }
+
+    /// 行を取得します。
+    #[inline]
+    pub fn row(&self, i: usize) -> Vec3 {
+        Vec3::new(self.cols[0][i], self.cols[1][i], self.cols[2][i])
+    }
+
+    /// 転置行列を取得します。
+    #[inline]
+    pub fn transpose(&self) -> Mat3 {
+        Mat3::from_cols(self.row(0), self.row(1), self.row(2))
+    }
+
+    /// 行列式を取得します。
+    #[inline]
+    pub fn determinant(&self) -> f32 {
+        self.cols[2].dot(self.cols[0].cross(self.cols[1]))
+    }
+
+    /// 逆行列を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 逆行列、または、正則でない場合はNoneです。
+    /// 
+    pub fn inverse(&self) -> Option<Mat3> {
+        let [a, b, c] = self.cols;
+        let (bc, ca, ab) = (b.cross(c), c.cross(a), a.cross(b));
+        let det = c.dot(ab);
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Mat3::from_cols(bc, ca, ab).transpose() * det.recip())
+    }
+
+    /// 誤差を許して等しいかを判定します。
+    pub fn abs_diff_eq(&self, other: Mat3, epsilon: f32) -> bool {
+        self.cols.iter().zip(other.cols).all(|(a, b)| a.abs_diff_eq(b, epsilon))
+    }
+}
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    #[inline]
+    fn mul(self, v: Vec3) -> Vec3 {
+        self.cols[0] * v.x + self.cols[1] * v.y + self.cols[2] * v.z
+    }
+}
+impl Mul for Mat3 {
+    type Output = Mat3;
+    #[inline]
+    fn mul(self, other: Mat3) -> Mat3 {
+        Mat3::from_cols(self * other.cols[0], self * other.cols[1], self * other.cols[2])
+    }
+}
+impl Mul<f32> for Mat3 {
+    type Output = Mat3;
+    #[inline]
+    fn mul(self, s: f32) -> Mat3 {
+        Mat3::from_cols(self.cols[0] * s, self.cols[1] * s, self.cols[2] * s)
+    }
+}
+impl MulAssign for Mat3 {
+    #[inline]
+    fn mul_assign(&mut self, other: Mat3) {
+        *self = *self * other;
+    }
+}
+
+/// 4x4行列です。
+/// 
+/// x86_64ではSSEで乗算します。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Mat4 {
+    /// 列です。
+    pub cols: [Vec4; 4],
+}
+impl Mat4 {
+    /// 単位行列です。
+    pub const IDENTITY: Mat4 = Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::Z, Vec4::W);
+    /// すべての要素が0の行列です。
+    pub const ZERO: Mat4 = Mat4::from_cols(Vec4::ZERO, Vec4::ZERO, Vec4::ZERO, Vec4::ZERO);
+
+    /// 列から作成します。
+    #[inline]
+    pub const fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Mat4 {
+        Mat4 { cols: [x, y, z, w] }
+    }
+
+    /// 平行移動から作成します。
+    #[inline]
+    pub const fn from_translation(t: Vec3) -> Mat4 {
+        Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::Z, t.extend(1.0))
+    }
+
+    /// 拡大縮小から作成します。
+    #[inline]
+    pub const fn from_scale(s: Vec3) -> Mat4 {
+        Mat4::from_mat3(Mat3::from_scale(s))
+    }
+
+    /// 回転から作成します。
+    #[inline]
+    pub fn from_quat(q: Quat) -> Mat4 {
+        Mat4::from_mat3(Mat3::from_quat(q))
+    }
+
+    /// 3x3行列を左上に持つ行列を作成します。
+    #[inline]
+    pub const fn from_mat3(m: Mat3) -> Mat4 {
+        Mat4::from_cols(m.cols[0].extend(0.0), m.cols[1].extend(0.0), m.cols[2].extend(0.0), Vec4::W)
+    }
+
+    /// 拡大縮小、回転、平行移動の順に適用する行列を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `scale` - 拡大縮小です。
+    /// * `rotation` - 回転です。
+    /// * `translation` - 平行移動です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 変換行列です。
+    /// 
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Mat4 {
+        let r = Mat3::from_quat(rotation);
+        Mat4::from_cols(
+            (r.cols[0] * scale.x).extend(0.0),
+            (r.cols[1] * scale.y).extend(0.0),
+            (r.cols[2] * scale.z).extend(0.0),
+            translation.extend(1.0)
+        )
+    }
+
+    /// 右手系のビュー行列を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `eye` - 視点です。
+    /// * `target` - 注視点です。
+    /// * `up` - 上方向です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 視点が原点で-Z方向を向く空間への変換行列です。
+    /// 
+    pub fn look_at_rh(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let f = (target - eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+        Mat4::from_cols(
+            Vec4::new(s.x, u.x, -f.x, 0.0),
+            Vec4::new(s.y, u.y, -f.y, 0.0),
+            Vec4::new(s.z, u.z, -f.z, 0.0),
+            Vec4::new(-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0)
+        )
+    }
+
+    /// 右手系の透視投影行列を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `fov_y` - 垂直方向の視野角(ラジアン)です。
+    /// * `aspect` - 幅を高さで割った比です。
+    /// * `near` - 近平面までの距離です。
+    /// * `far` - 遠平面までの距離です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 深度を0から1に写す投影行列です。
+    /// 
+    pub fn perspective_rh(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let h = (fov_y * 0.5).tan().recip();
+        let r = far / (near - far);
+        Mat4::from_cols(
+            Vec4::new(h / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, h, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, r, -1.0),
+            Vec4::new(0.0, 0.0, r * near, 0.0)
+        )
+    }
+
+    /// 右手系の平行投影行列を作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `left`, `right`, `bottom`, `top` - 視野の範囲です。
+    /// * `near`, `far` - 近平面と遠平面までの距離です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 深度を0から1に写す投影行列です。
+    /// 
+    pub fn orthographic_rh(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        let rw = (right - left).recip();
+        let rh = (top - bottom).recip();
+        let r = (near - far).recip();
+        Mat4::from_cols(
+            Vec4::new(2.0 * rw, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * rh, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, r, 0.0),
+            Vec4::new(-(right + left) * rw, -(top + bottom) * rh, r * near, 1.0)
+        )
+    }
+
+    /// 行を取得します。
+    #[inline]
+    pub fn row(&self, i: usize) -> Vec4 {
+        Vec4::new(self.cols[0][i], self.cols[1][i], self.cols[2][i], self.cols[3][i])
+    }
+
+    /// 転置行列を取得します。
+    #[inline]
+    pub fn transpose(&self) -> Mat4 {
+        Mat4::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    /// 行列式を取得します。
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.cofactors();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    /// 逆行列を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 逆行列、または、正則でない場合はNoneです。
+    /// 
+    pub fn inverse(&self) -> Option<Mat4> {
+        let (s, c) = self.cofactors();
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let r = det.recip();
+        // 転置しても逆行列の関係は変わらないので、列をそのまま行として計算します。
+        let [[a00, a01, a02, a03], [a10, a11, a12, a13], [a20, a21, a22, a23], [a30, a31, a32, a33]] = self.to_array();
+        Some(Mat4::from_cols(
+            Vec4::new(
+                a11 * c[5] - a12 * c[4] + a13 * c[3],
+                a02 * c[4] - a01 * c[5] - a03 * c[3],
+                a31 * s[5] - a32 * s[4] + a33 * s[3],
+                a22 * s[4] - a21 * s[5] - a23 * s[3]
+            ) * r,
+            Vec4::new(
+                a12 * c[2] - a10 * c[5] - a13 * c[1],
+                a00 * c[5] - a02 * c[2] + a03 * c[1],
+                a32 * s[2] - a30 * s[5] - a33 * s[1],
+                a20 * s[5] - a22 * s[2] + a23 * s[1]
+            ) * r,
+            Vec4::new(
+                a10 * c[4] - a11 * c[2] + a13 * c[0],
+                a01 * c[2] - a00 * c[4] - a03 * c[0],
+                a30 * s[4] - a31 * s[2] + a33 * s[0],
+                a21 * s[2] - a20 * s[4] - a23 * s[0]
+            ) * r,
+            Vec4::new(
+                a11 * c[1] - a10 * c[3] - a12 * c[0],
+                a00 * c[3] - a01 * c[1] + a02 * c[0],
+                a31 * s[1] - a30 * s[3] - a32 * s[0],
+                a20 * s[3] - a21 * s[1] + a22 * s[0]
+            ) * r
+        ))
+    }
+
+    /// 点を変換します。
+    /// 
+    /// W要素を1として変換し、射影の場合はWで割ります。
+    /// 
+    #[inline]
+    pub fn transform_point3(&self, p: Vec3) -> Vec3 {
+        let v = *self * p.extend(1.0);
+        if v.w == 1.0 { v.truncate() } else { v.truncate() / v.w }
+    }
+
+    /// 方向を変換します。
+    /// 
+    /// W要素を0として変換するので平行移動は適用されません。
+    /// 
+    #[inline]
+    pub fn transform_vector3(&self, v: Vec3) -> Vec3 {
+        (*self * v.extend(0.0)).truncate()
+    }
+
+    /// 2次元配列に変換します。
+    #[inline]
+    pub fn to_array(&self) -> [[f32; 4]; 4] {
+        self.cols.map(Vec4::to_array)
+    }
+
+    /// 誤差を許して等しいかを判定します。
+    pub fn abs_diff_eq(&self, other: Mat4, epsilon: f32) -> bool {
+        self.cols.iter().zip(other.cols).all(|(a, b)| a.abs_diff_eq(b, epsilon))
+    }
+
+    // 逆行列と行列式に使う2x2の小行列式です。
+    fn cofactors(&self) -> ([f32; 6], [f32; 6]) {
+        let [[a00, a01, a02, a03], [a10, a11, a12, a13], [a20, a21, a22, a23], [a30, a31, a32, a33]] = self.to_array();
+        (
+            [
+                a00 * a11 - a01 * a10,
+                a00 * a12 - a02 * a10,
+                a00 * a13 - a03 * a10,
+                a01 * a12 - a02 * a11,
+                a01 * a13 - a03 * a11,
+                a02 * a13 - a03 * a12,
+            ],
+            [
+                a20 * a31 - a21 * a30,
+                a20 * a32 - a22 * a30,
+                a20 * a33 - a23 * a30,
+                a21 * a32 - a22 * a31,
+                a21 * a33 - a23 * a31,
+                a22 * a33 - a23 * a32,
+            ]
+        )
+    }
+}
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    #[inline]
+    fn mul(self, v: Vec4) -> Vec4 {
+        Vec4::from(simd::mat4_mul_vec4(&self.to_array(), v.to_array()))
+    }
+}
+impl Mul for Mat4 {
+    type Output = Mat4;
+    #[inline]
+    fn mul(self, other: Mat4) -> Mat4 {
+        let m = simd::mat4_mul(&self.to_array(), &other.to_array());
+        Mat4 { cols: m.map(Vec4::from) }
+    }
+}
+impl MulAssign for Mat4 {
+    #[inline]
+    fn mul_assign(&mut self, other: Mat4) {
+        *self = *self * other;
+    }
+}
@@ -0,0 +1,32 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/messages.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_mathの診断メッセージです。
+// =========================
+
+use cwago_utility::msg::Message;
+
+pub(crate) const VEC_INDEX_OUT_OF_RANGE: Message = Message::new(
+    "math.vec_index_out_of_range",
+    "ベクトルの添え字{index}が要素数{len}の範囲外です。",
+    "Vector index {index} is out of range for length {len}."
+);
+
+/// cwago_mathのすべての診断メッセージです。
+pub const MESSAGES: &[&Message] = &[
+    &VEC_INDEX_OUT_OF_RANGE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        cwago_utility::msg::check_catalog_source(MESSAGES, include_str!("messages.rs"));
+    }
+}
@@ -0,0 +1,265 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/quat.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 回転を表す四元数を提供します。
+// =========================
+
+use std::ops::{
+    Mul,
+    MulAssign,
+    Neg
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    simd,
+    vec::{
+        Vec3,
+        Vec4
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let q = Quat::from_rotation_z(FRAC_PI_2);
+        assert!((q * Vec3::X).abs_diff_eq(Vec3::Y, 1e-6));
+        assert!((q.inverse() * Vec3::Y).abs_diff_eq(Vec3::X, 1e-6));
+
+        let a = Quat::from_axis_angle(Vec3::Y, FRAC_PI_2);
+        assert!(a.abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2), 1e-6));
+        // 合成は右から順に適用されます。
+        assert!(((q * a) * Vec3::Z).abs_diff_eq(q * (a * Vec3::Z), 1e-6));
+        assert!(((q * a) * Vec3::Z).abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn test_slerp() {
+        let a = Quat::IDENTITY;
+        let b = Quat::from_rotation_x(FRAC_PI_2);
+        let half = a.slerp(b, 0.5);
+        assert!(half.abs_diff_eq(Quat::from_rotation_x(FRAC_PI_2 * 0.5), 1e-6));
+        assert!(a.slerp(b, 0.0).abs_diff_eq(a, 1e-6));
+        assert!(a.slerp(b, 1.0).abs_diff_eq(b, 1e-6));
+        // 遠回りせずに補間します。
+        assert!(a.slerp(-b, 0.5).abs_diff_eq(half, 1e-6));
+        assert_eq!(Quat::default(), Quat::IDENTITY);
+    }
+}
+
+/// 回転を表す単位四元数です。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(C, align(16))]
+pub struct Quat {
+    /// 虚部のX要素です。
+    pub x: f32,
+    /// 虚部のY要素です。
+    pub y: f32,
+    /// 虚部のZ要素です。
+    pub z: f32,
+    /// 実部です。
+    pub w: f32,
+}
+impl Quat {
+    /// 回転しない四元数です。
+    pub const IDENTITY: Quat = Quat::from_xyzw(0.0, 0.0, 0.0, 1.0);
+
+    /// 要素から作成します。
+    /// 
+    /// 正規化はしません。
+    /// 
+    #[inline]
+    pub const fn from_xyzw(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        Quat { x, y, z, w }
+    }
+
+    /// 回転軸と角度から作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `axis` - 正規化された回転軸です。
+    /// * `angle` - 右手系で反時計回りのラジアンです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    #[inline]
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (s, c) = (angle * 0.5).sin_cos();
+        let v = axis * s;
+        Quat::from_xyzw(v.x, v.y, v.z, c)
+    }
+
+    /// X軸回りの回転を作成します。
+    #[inline]
+    pub fn from_rotation_x(angle: f32) -> Quat {
+        Self::from_axis_angle(Vec3::X, angle)
+    }
+
+    /// Y軸回りの回転を作成します。
+    #[inline]
+    pub fn from_rotation_y(angle: f32) -> Quat {
+        Self::from_axis_angle(Vec3::Y, angle)
+    }
+
+    /// Z軸回りの回転を作成します。
+    #[inline]
+    pub fn from_rotation_z(angle: f32) -> Quat {
+        Self::from_axis_angle(Vec3::Z, angle)
+    }
+
+    /// 4次元ベクトルに変換します。
+    #[inline]
+    pub const fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, self.w)
+    }
+
+    /// 4次元ベクトルから変換します。
+    #[inline]
+    pub const fn from_vec4(v: Vec4) -> Quat {
+        Quat::from_xyzw(v.x, v.y, v.z, v.w)
+    }
+
+    /// 内積を取得します。
+    #[inline]
+    pub fn dot(self, other: Quat) -> f32 {
+        simd::dot4(self.to_vec4().to_array(), other.to_vec4().to_array())
+    }
+
+    /// 長さを取得します。
+    #[inline]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// 正規化します。
+    #[inline]
+    pub fn normalize(self) -> Quat {
+        Quat::from_vec4(self.to_vec4() * self.length().recip())
+    }
+
+    /// 共役を取得します。
+    #[inline]
+    pub fn conjugate(self) -> Quat {
+        Quat::from_xyzw(-self.x, -self.y, -self.z, self.w)
+    }
+
+    /// 逆回転を取得します。
+    /// 
+    /// 単位四元数を前提に共役を返します。
+    /// 
+    #[inline]
+    pub fn inverse(self) -> Quat {
+        self.conjugate()
+    }
+
+    /// 虚部を取得します。
+    #[inline]
+    pub fn xyz(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    /// 球面線形補間します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `end` - 補間先です。
+    /// * `t` - 0で自身、1で`end`になる係数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 短い経路で補間した回転です。
+    /// 
+    pub fn slerp(self, mut end: Quat, t: f32) -> Quat {
+        let mut d = self.dot(end);
+        // 短い経路を選びます。
+        if d < 0.0 {
+            end = -end;
+            d = -d;
+        }
+        // ほぼ同じ向きの場合は線形補間します。
+        if d > 0.9995 {
+            let v = self.to_vec4().lerp(end.to_vec4(), t);
+            return Quat::from_vec4(v).normalize();
+        }
+        let theta = d.acos();
+        let s = theta.sin().recip();
+        let a = ((1.0 - t) * theta).sin() * s;
+        let b = (t * theta).sin() * s;
+        Quat::from_vec4(self.to_vec4() * a + end.to_vec4() * b)
+    }
+
+    /// 誤差を許して等しいかを判定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `other` - 比較する四元数です。
+    /// * `epsilon` - 要素毎に許す差です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// すべての要素の差が`epsilon`以下の場合trueです。
+    /// 
+    #[inline]
+    pub fn abs_diff_eq(self, other: Quat, epsilon: f32) -> bool {
+        self.to_vec4().abs_diff_eq(other.to_vec4(), epsilon)
+    }
+}
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+impl Neg for Quat {
+    type Output = Quat;
+    #[inline]
+    fn neg(self) -> Quat {
+        Quat::from_vec4(-self.to_vec4())
+    }
+}
+impl Mul for Quat {
+    type Output = Quat;
+
+    /// 回転を合成します。`a * b`は`b`の後に`a`を適用します。
+    #[inline]
+    fn mul(self, b: Quat) -> Quat {
+        let a = self;
+        Quat::from_xyzw(
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z
+        )
+    }
+}
+impl MulAssign for Quat {
+    #[inline]
+    fn mul_assign(&mut self, other: Quat) {
+        *self = *self * other;
+    }
+}
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    /// ベクトルを回転します。
+    #[inline]
+    fn mul(self, v: Vec3) -> Vec3 {
+        let u = self.xyz();
+        let t = u.cross(v) * 2.0;
+        v + t * self.w + u.cross(t)
+    }
+}
@@ -0,0 +1,332 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/shape.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 境界ボックス、レイ、平面と交差判定を提供します。
+// =========================
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::vec::Vec3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aabb() {
+        let a = Aabb::from_points([Vec3::new(1.0, -1.0, 0.0), Vec3::new(-1.0, 1.0, 2.0)]);
+        assert_eq!(a.min, Vec3::new(-1.0, -1.0, 0.0));
+        assert_eq!(a.center(), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(a.half_extents(), Vec3::splat(1.0));
+        assert!(a.contains_point(Vec3::new(0.5, 0.5, 0.5)));
+        assert!(!a.contains_point(Vec3::new(0.5, 0.5, 2.5)));
+
+        let b = Aabb::new(Vec3::splat(1.0), Vec3::splat(3.0));
+        assert!(a.intersects_aabb(&b));
+        assert!(!a.intersects_aabb(&Aabb::new(Vec3::splat(1.5), Vec3::splat(3.0))));
+        assert_eq!(a.union(&b), Aabb::new(Vec3::new(-1.0, -1.0, 0.0), Vec3::splat(3.0)));
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::EMPTY.union(&b), b);
+    }
+
+    #[test]
+    fn test_ray() {
+        let aabb = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        assert_eq!(ray.intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(ray.at(4.0), Vec3::new(-1.0, 0.0, 0.0));
+        // 内側から撃つと距離0で当たります。
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::Y).intersect_aabb(&aabb), Some(0.0));
+        assert_eq!(Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X).intersect_aabb(&aabb), None);
+        assert_eq!(Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::X).intersect_aabb(&aabb), None);
+        // 面に沿う光線は交差し、面の外側に平行な光線は交差しません。
+        assert_eq!(Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::X).intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(-5.0, 1.0, 1.0), Vec3::X).intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(0.0, -1.0, -5.0), Vec3::Z).intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(-5.0, 1.0 + 1e-6, 0.0), Vec3::X).intersect_aabb(&aabb), None);
+
+        let ground = Plane::from_point_normal(Vec3::ZERO, Vec3::Y);
+        let down = Ray::new(Vec3::new(1.0, 10.0, 0.0), -Vec3::Y);
+        assert_eq!(down.intersect_plane(&ground), Some(10.0));
+        assert_eq!(Ray::new(Vec3::Y, Vec3::X).intersect_plane(&ground), None);
+        assert_eq!(Ray::new(Vec3::Y, Vec3::Y).intersect_plane(&ground), None);
+    }
+
+    #[test]
+    fn test_plane() {
+        let p = Plane::from_point_normal(Vec3::new(0.0, 2.0, 0.0), Vec3::Y);
+        assert_eq!(p.signed_distance(Vec3::new(3.0, 5.0, 0.0)), 3.0);
+        assert_eq!(p.signed_distance(Vec3::ZERO), -2.0);
+
+        let q = Plane::from_points(Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert!(q.normal.abs_diff_eq(Vec3::Z, 1e-6));
+
+        let aabb = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_eq!(p.classify_aabb(&aabb), Side::Back);
+        assert_eq!(Plane::from_point_normal(Vec3::ZERO, Vec3::X).classify_aabb(&aabb), Side::Intersecting);
+        assert_eq!(Plane::from_point_normal(Vec3::splat(-3.0), Vec3::X).classify_aabb(&aabb), Side::Front);
+    }
+}
+
+/// 軸に平行な境界ボックスです。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    /// 最小の角です。
+    pub min: Vec3,
+    /// 最大の角です。
+    pub max: Vec3,
+}
+impl Aabb {
+    /// 何も含まない境界ボックスです。
+    /// 
+    /// 他のボックスとの和は、そのボックスになります。
+    pub const EMPTY: Aabb = Aabb::new(Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY));
+
+    /// 作成します。
+    #[inline]
+    pub const fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// 点をすべて含む最小の境界ボックスを作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `points` - 点です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 境界ボックス、点が無い場合は`EMPTY`です。
+    /// 
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        points.into_iter().fold(Aabb::EMPTY, |a, p| Aabb::new(a.min.min(p), a.max.max(p)))
+    }
+
+    /// 中心を取得します。
+    #[inline]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// 中心から各面までの距離を取得します。
+    #[inline]
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// 空かを取得します。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// 点を含むかを判定します。
+    #[inline]
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x
+            && p.y >= self.min.y && p.y <= self.max.y
+            && p.z >= self.min.z && p.z <= self.max.z
+    }
+
+    /// 他の境界ボックスと交差するかを判定します。
+    /// 
+    /// 面が接する場合も交差とします。
+    /// 
+    #[inline]
+    pub fn intersects_aabb(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x
+            && self.min.y <= other.max.y && self.max.y >= other.min.y
+            && self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+
+    /// 両方を含む最小の境界ボックスを取得します。
+    #[inline]
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+}
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+/// 半直線です。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ray {
+    /// 始点です。
+    pub origin: Vec3,
+    /// 正規化された方向です。
+    pub direction: Vec3,
+}
+impl Ray {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `origin` - 始点です。
+    /// * `direction` - 方向です。正規化して保持します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    #[inline]
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray { origin, direction: direction.normalize_or_zero() }
+    }
+
+    /// 始点から距離`t`の点を取得します。
+    #[inline]
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// 境界ボックスとの交差を判定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `aabb` - 境界ボックスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最初に交差する距離、交差しない場合はNoneです。始点が内側の場合は0です。
+    /// 面に沿う光線も、面は境界ボックスに含むため交差します。
+    /// 
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let (origin, direction) = (self.origin[axis], self.direction[axis]);
+            let (min, max) = (aabb.min[axis], aabb.max[axis]);
+            // 方向が0の軸は逆数が±無限大になり、始点が面上にある場合は0×無限大で非数になります。
+            // 距離では比べず、始点が面を含む範囲内にあるかで判定します。
+            if direction == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let inv = direction.recip();
+            let (t1, t2) = ((min - origin) * inv, (max - origin) * inv);
+            near = near.max(t1.min(t2));
+            far = far.min(t1.max(t2));
+        }
+        (near <= far).then_some(near)
+    }
+
+    /// 平面との交差を判定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `plane` - 平面です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 交差する距離、平行または後方の場合はNoneです。
+    /// 
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denom = plane.normal.dot(self.direction);
+        if denom.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = -plane.signed_distance(self.origin) / denom;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+/// 平面に対する位置です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// 法線の向く側です。
+    Front,
+    /// 法線と反対の側です。
+    Back,
+    /// 平面と交差しています。
+    Intersecting,
+}
+
+/// `normal・p + d = 0`を満たす点の集合である平面です。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Plane {
+    /// 正規化された法線です。
+    pub normal: Vec3,
+    /// 原点からの符号付き距離の符号を反転した値です。
+    pub d: f32,
+}
+impl Plane {
+    /// 通る点と法線から作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `point` - 平面上の点です。
+    /// * `normal` - 法線です。正規化して保持します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    #[inline]
+    pub fn from_point_normal(point: Vec3, normal: Vec3) -> Plane {
+        let normal = normal.normalize_or_zero();
+        Plane { normal, d: -normal.dot(point) }
+    }
+
+    /// 3点から作成します。
+    /// 
+    /// 法線は`a`、`b`、`c`を反時計回りに見る側を向きます。
+    /// 
+    #[inline]
+    pub fn from_points(a: Vec3, b: Vec3, c: Vec3) -> Plane {
+        Self::from_point_normal(a, (b - a).cross(c - a))
+    }
+
+    /// 点までの符号付き距離を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `p` - 点です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 法線の向く側で正の距離です。
+    /// 
+    #[inline]
+    pub fn signed_distance(&self, p: Vec3) -> f32 {
+        self.normal.dot(p) + self.d
+    }
+
+    /// 境界ボックスが平面のどちら側にあるかを判定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `aabb` - 境界ボックスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 境界ボックスの位置です。
+    /// 
+    pub fn classify_aabb(&self, aabb: &Aabb) -> Side {
+        let r = aabb.half_extents().dot(self.normal.abs());
+        let s = self.signed_distance(aabb.center());
+        if s > r {
+            Side::Front
+        } else if s < -r {
+            Side::Back
+        } else {
+            Side::Intersecting
+        }
+    }
+}
+impl Default for Plane {
+    fn default() -> Self {
+        Self::from_point_normal(Vec3::ZERO, Vec3::Y)
+    }
+}
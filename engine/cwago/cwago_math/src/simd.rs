@@ -0,0 +1,128 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/simd.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 4要素の演算を提供します。
+//! 
+//! x86_64ではSSE2(常に利用可能)を使い、それ以外ではスカラーで計算します。
+// =========================
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+#[cfg(test)]
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mat4_mul() {
+        let a = [[1.0, 2.0, 3.0, 4.0], [5.0, 6.0, 7.0, 8.0], [9.0, 10.0, 11.0, 12.0], [13.0, 14.0, 15.0, 16.0]];
+        let b = [[2.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [1.0, 1.0, 1.0, 1.0]];
+
+        // スカラーで計算した結果と一致するかテストします。
+        let mut expected = [[0.0f32; 4]; 4];
+        for (c, col) in expected.iter_mut().enumerate() {
+            for (r, v) in col.iter_mut().enumerate() {
+                *v = (0..4).map(|k| a[k][r] * b[c][k]).sum();
+            }
+        }
+        assert_eq!(mat4_mul(&a, &b), expected);
+        assert_eq!(mat4_mul_vec4(&a, [1.0, 0.0, 0.0, 1.0]), [14.0, 16.0, 18.0, 20.0]);
+    }
+}
+
+/// 要素毎に加算します。
+#[inline]
+pub(crate) fn add4(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        store(_mm_add_ps(load(a), load(b)))
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+    }
+}
+
+/// 要素毎に減算します。
+#[inline]
+pub(crate) fn sub4(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        store(_mm_sub_ps(load(a), load(b)))
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]]
+    }
+}
+
+/// 要素毎に乗算します。
+#[inline]
+pub(crate) fn mul4(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        store(_mm_mul_ps(load(a), load(b)))
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        [a[0] * b[0], a[1] * b[1], a[2] * b[2], a[3] * b[3]]
+    }
+}
+
+/// 内積を計算します。
+#[inline]
+pub(crate) fn dot4(a: [f32; 4], b: [f32; 4]) -> f32 {
+    let m = mul4(a, b);
+    (m[0] + m[1]) + (m[2] + m[3])
+}
+
+/// 列優先の4x4行列とベクトルを乗算します。
+#[inline]
+pub(crate) fn mat4_mul_vec4(m: &[[f32; 4]; 4], v: [f32; 4]) -> [f32; 4] {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let mut r = _mm_mul_ps(load(m[0]), _mm_set1_ps(v[0]));
+        r = _mm_add_ps(r, _mm_mul_ps(load(m[1]), _mm_set1_ps(v[1])));
+        r = _mm_add_ps(r, _mm_mul_ps(load(m[2]), _mm_set1_ps(v[2])));
+        r = _mm_add_ps(r, _mm_mul_ps(load(m[3]), _mm_set1_ps(v[3])));
+        store(r)
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let mut r = [0.0; 4];
+        for (i, out) in r.iter_mut().enumerate() {
+            *out = m[0][i] * v[0] + m[1][i] * v[1] + m[2][i] * v[2] + m[3][i] * v[3];
+        }
+        r
+    }
+}
+
+/// 列優先の4x4行列を乗算します。
+#[inline]
+pub(crate) fn mat4_mul(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    [
+        mat4_mul_vec4(a, b[0]),
+        mat4_mul_vec4(a, b[1]),
+        mat4_mul_vec4(a, b[2]),
+        mat4_mul_vec4(a, b[3]),
+    ]
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+unsafe fn load(a: [f32; 4]) -> __m128 {
+    _mm_loadu_ps(a.as_ptr())
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+unsafe fn store(v: __m128) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    _mm_storeu_ps(out.as_mut_ptr(), v);
+    out
+}
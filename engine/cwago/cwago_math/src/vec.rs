@@ -0,0 +1,499 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_math/src/vec.rs
+// (C) 2023 CwagoCommunity.
+//
+//! ベクトルを提供します。
+// =========================
+
+use std::ops::{
+    Add,
+    AddAssign,
+    Div,
+    DivAssign,
+    Index,
+    IndexMut,
+    Mul,
+    MulAssign,
+    Neg,
+    Sub,
+    SubAssign
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use cwago_utility::msg;
+
+use crate::{
+    messages::VEC_INDEX_OUT_OF_RANGE,
+    simd
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec3() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(4.0, 5.0, 6.0);
+        assert_eq!(a + b, Vec3::new(5.0, 7.0, 9.0));
+        assert_eq!(b - a, Vec3::splat(3.0));
+        assert_eq!(a * 2.0, Vec3::new(2.0, 4.0, 6.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(a.dot(b), 32.0);
+        assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+        assert_eq!(Vec3::new(3.0, 0.0, 4.0).length(), 5.0);
+        assert!(Vec3::new(3.0, 0.0, 4.0).normalize().abs_diff_eq(Vec3::new(0.6, 0.0, 0.8), 1e-6));
+        assert_eq!(Vec3::ZERO.normalize_or_zero(), Vec3::ZERO);
+        assert_eq!(a.lerp(b, 0.5), Vec3::new(2.5, 3.5, 4.5));
+        assert_eq!(a.min(Vec3::splat(2.0)), Vec3::new(1.0, 2.0, 2.0));
+        assert_eq!(a[2], 3.0);
+        assert_eq!(Vec3::from([1.0, 2.0, 3.0]), a);
+    }
+
+    #[test]
+    fn test_vec4_simd() {
+        let a = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        let b = Vec4::new(5.0, 6.0, 7.0, 8.0);
+        assert_eq!(a + b, Vec4::new(6.0, 8.0, 10.0, 12.0));
+        assert_eq!(a * b, Vec4::new(5.0, 12.0, 21.0, 32.0));
+        assert_eq!(a.dot(b), 70.0);
+        assert_eq!(a.truncate(), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(Vec2::new(1.0, 2.0).extend(3.0).extend(4.0), a);
+    }
+
+    #[test]
+    fn test_serde() {
+        let v = Vec2::new(1.5, -2.0);
+        let json = serde_json::to_string(&v).unwrap();
+        assert_eq!(json, "{\"x\":1.5,\"y\":-2.0}");
+        assert_eq!(serde_json::from_str::<Vec2>(&json).unwrap(), v);
+    }
+}
+
+// ベクトルの共通の実装です。
+macro_rules! impl_vec {
+    ($name:ident, $n:literal, $($field:ident),+) => {
+        impl $name {
+            /// すべての要素が0のベクトルです。
+            pub const ZERO: $name = $name::splat(0.0);
+            /// すべての要素が1のベクトルです。
+            pub const ONE: $name = $name::splat(1.0);
+
+            /// 作成します。
+            /// 
+            /// # 引数
+            /// 
+            /// * 各要素の値です。
+            /// 
+            /// # 戻り値
+            /// 
+            /// インスタンスです。
+            /// 
+            #[inline]
+            pub const fn new($($field: f32),+) -> $name {
+                $name { $($field),+ }
+            }
+
+            /// すべての要素を同じ値で作成します。
+            /// 
+            /// # 引数
+            /// 
+            /// * `v` - 要素の値です。
+            /// 
+            /// # 戻り値
+            /// 
+            /// インスタンスです。
+            /// 
+            #[inline]
+            pub const fn splat(v: f32) -> $name {
+                $name { $($field: v),+ }
+            }
+
+            /// 配列に変換します。
+            #[inline]
+            pub const fn to_array(self) -> [f32; $n] {
+                [$(self.$field),+]
+            }
+
+            /// 長さの2乗を取得します。
+            #[inline]
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            /// 長さを取得します。
+            #[inline]
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            /// 他のベクトルとの距離を取得します。
+            #[inline]
+            pub fn distance(self, other: $name) -> f32 {
+                (self - other).length()
+            }
+
+            /// 長さ1にしたベクトルを取得します。
+            /// 
+            /// 長さが0の場合、要素は非数になります。
+            /// 
+            #[inline]
+            pub fn normalize(self) -> $name {
+                self / self.length()
+            }
+
+            /// 長さ1にしたベクトルを取得します。
+            /// 
+            /// # 戻り値
+            /// 
+            /// 正規化したベクトル、長さが0や非有限の場合は0ベクトルです。
+            /// 
+            #[inline]
+            pub fn normalize_or_zero(self) -> $name {
+                let r = self.length().recip();
+                if r.is_finite() && r > 0.0 { self * r } else { $name::ZERO }
+            }
+
+            /// 線形補間します。
+            /// 
+            /// # 引数
+            /// 
+            /// * `other` - 補間先です。
+            /// * `t` - 0で自身、1で`other`になる係数です。
+            /// 
+            /// # 戻り値
+            /// 
+            /// 補間したベクトルです。
+            /// 
+            #[inline]
+            pub fn lerp(self, other: $name, t: f32) -> $name {
+                self + (other - self) * t
+            }
+
+            /// 要素毎の最小値を取得します。
+            #[inline]
+            pub fn min(self, other: $name) -> $name {
+                $name { $($field: self.$field.min(other.$field)),+ }
+            }
+
+            /// 要素毎の最大値を取得します。
+            #[inline]
+            pub fn max(self, other: $name) -> $name {
+                $name { $($field: self.$field.max(other.$field)),+ }
+            }
+
+            /// 要素毎の絶対値を取得します。
+            #[inline]
+            pub fn abs(self) -> $name {
+                $name { $($field: self.$field.abs()),+ }
+            }
+
+            /// 要素の最小値を取得します。
+            #[inline]
+            pub fn min_element(self) -> f32 {
+                self.to_array().into_iter().fold(f32::INFINITY, f32::min)
+            }
+
+            /// 要素の最大値を取得します。
+            #[inline]
+            pub fn max_element(self) -> f32 {
+                self.to_array().into_iter().fold(f32::NEG_INFINITY, f32::max)
+            }
+
+            /// 要素毎の逆数を取得します。
+            #[inline]
+            pub fn recip(self) -> $name {
+                $name { $($field: self.$field.recip()),+ }
+            }
+
+            /// すべての要素が有限かを取得します。
+            #[inline]
+            pub fn is_finite(self) -> bool {
+                $(self.$field.is_finite())&&+
+            }
+
+            /// 誤差を許して等しいかを判定します。
+            /// 
+            /// # 引数
+            /// 
+            /// * `other` - 比較するベクトルです。
+            /// * `epsilon` - 要素毎に許す差です。
+            /// 
+            /// # 戻り値
+            /// 
+            /// すべての要素の差が`epsilon`以下の場合trueです。
+            /// 
+            #[inline]
+            pub fn abs_diff_eq(self, other: $name, epsilon: f32) -> bool {
+                $((self.$field - other.$field).abs() <= epsilon)&&+
+            }
+        }
+        impl From<[f32; $n]> for $name {
+            #[inline]
+            fn from(a: [f32; $n]) -> Self {
+                let [$($field),+] = a;
+                $name { $($field),+ }
+            }
+        }
+        impl From<$name> for [f32; $n] {
+            #[inline]
+            fn from(v: $name) -> Self {
+                v.to_array()
+            }
+        }
+        impl Index<usize> for $name {
+            type Output = f32;
+            fn index(&self, index: usize) -> &f32 {
+                [$(&self.$field),+].get(index).copied()
+                    .unwrap_or_else(|| panic!("{}", msg!(VEC_INDEX_OUT_OF_RANGE, index = index, len = $n)))
+            }
+        }
+        impl IndexMut<usize> for $name {
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                [$(&mut self.$field),+].into_iter().nth(index)
+                    .unwrap_or_else(|| panic!("{}", msg!(VEC_INDEX_OUT_OF_RANGE, index = index, len = $n)))
+            }
+        }
+        impl Neg for $name {
+            type Output = $name;
+            #[inline]
+            fn neg(self) -> $name {
+                $name { $($field: -self.$field),+ }
+            }
+        }
+        impl Mul<$name> for f32 {
+            type Output = $name;
+            #[inline]
+            fn mul(self, v: $name) -> $name {
+                v * self
+            }
+        }
+        impl AddAssign for $name {
+            #[inline]
+            fn add_assign(&mut self, other: $name) {
+                *self = *self + other;
+            }
+        }
+        impl SubAssign for $name {
+            #[inline]
+            fn sub_assign(&mut self, other: $name) {
+                *self = *self - other;
+            }
+        }
+        impl MulAssign<f32> for $name {
+            #[inline]
+            fn mul_assign(&mut self, s: f32) {
+                *self = *self * s;
+            }
+        }
+        impl DivAssign<f32> for $name {
+            #[inline]
+            fn div_assign(&mut self, s: f32) {
+                *self = *self / s;
+            }
+        }
+        impl Div<f32> for $name {
+            type Output = $name;
+            #[inline]
+            fn div(self, s: f32) -> $name {
+                $name { $($field: self.$field / s),+ }
+            }
+        }
+        impl Div for $name {
+            type Output = $name;
+            #[inline]
+            fn div(self, other: $name) -> $name {
+                $name { $($field: self.$field / other.$field),+ }
+            }
+        }
+    };
+}
+
+// 要素毎の演算をスカラーで実装します。
+macro_rules! impl_scalar_ops {
+    ($name:ident, $($field:ident),+) => {
+        impl $name {
+            /// 内積を取得します。
+            #[inline]
+            pub fn dot(self, other: $name) -> f32 {
+                0.0 $(+ self.$field * other.$field)+
+            }
+        }
+        impl Add for $name {
+            type Output = $name;
+            #[inline]
+            fn add(self, other: $name) -> $name {
+                $name { $($field: self.$field + other.$field),+ }
+            }
+        }
+        impl Sub for $name {
+            type Output = $name;
+            #[inline]
+            fn sub(self, other: $name) -> $name {
+                $name { $($field: self.$field - other.$field),+ }
+            }
+        }
+        impl Mul for $name {
+            type Output = $name;
+            #[inline]
+            fn mul(self, other: $name) -> $name {
+                $name { $($field: self.$field * other.$field),+ }
+            }
+        }
+        impl Mul<f32> for $name {
+            type Output = $name;
+            #[inline]
+            fn mul(self, s: f32) -> $name {
+                $name { $($field: self.$field * s),+ }
+            }
+        }
+    };
+}
+
+/// 2次元ベクトルです。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Vec2 {
+    /// X要素です。
+    pub x: f32,
+    /// Y要素です。
+    pub y: f32,
+}
+impl_vec!(Vec2, 2, x, y);
+impl_scalar_ops!(Vec2, x, y);
+impl Vec2 {
+    /// X軸の単位ベクトルです。
+    pub const X: Vec2 = Vec2::new(1.0, 0.0);
+    /// Y軸の単位ベクトルです。
+    pub const Y: Vec2 = Vec2::new(0.0, 1.0);
+
+    /// Z要素を追加します。
+    #[inline]
+    pub const fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+
+    /// 2次元の外積(Z成分)を取得します。
+    #[inline]
+    pub fn perp_dot(self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+}
+
+/// 3次元ベクトルです。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Vec3 {
+    /// X要素です。
+    pub x: f32,
+    /// Y要素です。
+    pub y: f32,
+    /// Z要素です。
+    pub z: f32,
+}
+impl_vec!(Vec3, 3, x, y, z);
+impl_scalar_ops!(Vec3, x, y, z);
+impl Vec3 {
+    /// X軸の単位ベクトルです。
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    /// Y軸の単位ベクトルです。
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    /// Z軸の単位ベクトルです。
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    /// W要素を追加します。
+    #[inline]
+    pub const fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    /// Z要素を取り除きます。
+    #[inline]
+    pub const fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// 外積を取得します。
+    #[inline]
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x
+        )
+    }
+}
+
+/// 4次元ベクトルです。
+/// 
+/// 16バイトに整列され、x86_64ではSSEで演算します。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[repr(C, align(16))]
+pub struct Vec4 {
+    /// X要素です。
+    pub x: f32,
+    /// Y要素です。
+    pub y: f32,
+    /// Z要素です。
+    pub z: f32,
+    /// W要素です。
+    pub w: f32,
+}
+impl_vec!(Vec4, 4, x, y, z, w);
+impl Vec4 {
+    /// X軸の単位ベクトルです。
+    pub const X: Vec4 = Vec4::new(1.0, 0.0, 0.0, 0.0);
+    /// Y軸の単位ベクトルです。
+    pub const Y: Vec4 = Vec4::new(0.0, 1.0, 0.0, 0.0);
+    /// Z軸の単位ベクトルです。
+    pub const Z: Vec4 = Vec4::new(0.0, 0.0, 1.0, 0.0);
+    /// W軸の単位ベクトルです。
+    pub const W: Vec4 = Vec4::new(0.0, 0.0, 0.0, 1.0);
+
+    /// W要素を取り除きます。
+    #[inline]
+    pub const fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    /// 内積を取得します。
+    #[inline]
+    pub fn dot(self, other: Vec4) -> f32 {
+        simd::dot4(self.to_array(), other.to_array())
+    }
+}
+impl Add for Vec4 {
+    type Output = Vec4;
+    #[inline]
+    fn add(self, other: Vec4) -> Vec4 {
+        Vec4::from(simd::add4(self.to_array(), other.to_array()))
+    }
+}
+impl Sub for Vec4 {
+    type Output = Vec4;
+    #[inline]
+    fn sub(self, other: Vec4) -> Vec4 {
+        Vec4::from(simd::sub4(self.to_array(), other.to_array()))
+    }
+}
+impl Mul for Vec4 {
+    type Output = Vec4;
+    #[inline]
+    fn mul(self, other: Vec4) -> Vec4 {
+        Vec4::from(simd::mul4(self.to_array(), other.to_array()))
+    }
+}
+impl Mul<f32> for Vec4 {
+    type Output = Vec4;
+    #[inline]
+    fn mul(self, s: f32) -> Vec4 {
+        Vec4::from(simd::mul4(self.to_array(), [s; 4]))
+    }
+}
@@ -13,14 +13,18 @@ use std::{
     sync::Mutex
 };
 
-use cwago_utility::log::error;
-
 use super::{
+    error::{
+        AllocError, 
+        AllocResult
+    }, 
     fix::FixMemory, 
-    os::OSMemory
+    os::OSMemory, 
+    MemoryConfig
 };
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
     use std::ptr::null_mut;
 
@@ -32,10 +36,9 @@ mod tests {
     #[test]
     fn test_dy_memory() {
 
-        std::env::set_var("RUST_LOG", "error");
-        env_logger::init();
+        cwago_utility::logging::init();
 
-        let mut mem = DyMemory::new();
+        let mem = DyMemory::try_new(&MemoryConfig::default()).expect("可変長メモリの作成に失敗しました。");
 
         // サイズが1~256までで作成可能かテストします。
         for size in 1..SIZE_MAX {
@@ -49,7 +52,7 @@ mod tests {
     
                 // メモリが確保可能かテストします。
                 for i in 0..LENGTH_MAX {
-                    ptrs[i] = mem.alloc(layout);
+                    ptrs[i] = mem.try_alloc(layout).expect("メモリ確保に失敗しました。");
                     assert_ne!(ptrs[i], null_mut(), "{}回目のメモリ確保で失敗しました。", i);
                 }
     
@@ -63,7 +66,7 @@ mod tests {
     
                 // メモリを要素数解放します。
                 for i in 0..LENGTH_MAX {
-                    mem.dealloc(ptrs[i], layout);
+                    mem.try_dealloc(ptrs[i], layout).expect("メモリ解放に失敗しました。");
                 }
             }
         }
@@ -80,26 +83,50 @@ pub(super) struct DyMemory {
     memory256: Mutex<FixMemory>,
 }
 impl DyMemory {
-
-    const COUNT16: usize = 32;  // memory16の1要素の要素数です。
-    const COUNT32: usize = 32;  // memory32の1要素の要素数です。
-    const COUNT64: usize = 32;  // memory64の1要素の要素数です。
-    const COUNT128: usize = 16; // memory128の1要素の要素数です。
-    const COUNT256: usize = 16; // memory256の1要素の要素数です。
     /// 作成します。
     /// 
+    /// # 引数
+    /// 
+    /// * config - 各固定長メモリのプールの要素数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// DyMemoryのインスタンス、または、エラーです。
+    /// 
+    pub(super) fn try_new(config: &MemoryConfig) -> AllocResult<DyMemory> {
+        Ok(DyMemory { 
+            memory16: Mutex::new(FixMemory::try_new(16usize, config.count16)?), 
+            memory32: Mutex::new(FixMemory::try_new(32usize, config.count32)?), 
+            memory64: Mutex::new(FixMemory::try_new(64usize, config.count64)?), 
+            memory128: Mutex::new(FixMemory::try_new(128usize, config.count128)?), 
+            memory256: Mutex::new(FixMemory::try_new(256usize, config.count256)?) 
+        })
+    }
+
+    /// 以降に追加するプールの要素数を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * config - 各固定長メモリのプールの要素数です。
+    /// 
     /// # 戻り値
     /// 
-    /// DyMemoryのインスタンスです。
+    /// 失敗した際、エラーを返します。
     /// 
-    pub(super) fn new() -> DyMemory {
-        DyMemory { 
-            memory16: Mutex::new(FixMemory::new(16usize, Self::COUNT16)), 
-            memory32: Mutex::new(FixMemory::new(32usize, Self::COUNT32)), 
-            memory64: Mutex::new(FixMemory::new(64usize, Self::COUNT64)), 
-            memory128: Mutex::new(FixMemory::new(128usize, Self::COUNT128)), 
-            memory256: Mutex::new(FixMemory::new(256usize, Self::COUNT256)) 
+    pub(super) fn configure(&self, config: &MemoryConfig) -> AllocResult<()> {
+        for (memory, count) in [
+            (&self.memory16, config.count16), 
+            (&self.memory32, config.count32), 
+            (&self.memory64, config.count64), 
+            (&self.memory128, config.count128), 
+            (&self.memory256, config.count256), 
+        ] {
+            match memory.lock() {
+                Ok(mut mem) => mem.set_elements_count(count)?,
+                Err(_) => return Err(AllocError::AllocPoisoned),
+            }
         }
+        Ok(())
     }
 
     /// メモリを確保します。
@@ -110,51 +137,15 @@ impl DyMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリのポインタです。
+    /// 確保したメモリのポインタ、または、エラーです。
     /// 
-    pub(super) fn alloc(&mut self, layout: Layout) -> *mut u8 {
-        if layout.align() <= 16 {
-            match self.memory16.lock() {
-                Ok(mut mem) => mem.alloc(),
-                Err(_) => {
-                    error!("メモリ確保中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 32 {
-            match self.memory32.lock() {
-                Ok(mut mem) => mem.alloc(),
-                Err(_) => {
-                    error!("メモリ確保中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 64 {
-            match self.memory64.lock() {
-                Ok(mut mem) => mem.alloc(),
-                Err(_) => {
-                    error!("メモリ確保中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 128 {
-            match self.memory128.lock() {
-                Ok(mut mem) => mem.alloc(),
-                Err(_) => {
-                    error!("メモリ確保中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 256 {
-            match self.memory256.lock() {
-                Ok(mut mem) => mem.alloc(),
-                Err(_) => {
-                    error!("メモリ確保中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else {
-            OSMemory::alloc(layout)
+    pub(super) fn try_alloc(&self, layout: Layout) -> AllocResult<*mut u8> {
+        match self.fix_memory(layout) {
+            Some(memory) => match memory.lock() {
+                Ok(mut mem) => mem.try_alloc(),
+                Err(_) => Err(AllocError::AllocPoisoned),
+            },
+            None => OSMemory::try_alloc(layout),
         }
     }
 
@@ -165,60 +156,48 @@ impl DyMemory {
     /// * pointer - 解放するメモリのポインタです。
     /// * layout - 解放するメモリのレイアウトです。
     /// 
-    pub(super) fn dealloc(&mut self, pointer: *mut u8, layout: Layout) {
-        if layout.align() <= 16 {
-            match self.memory16.lock() {
-                Ok(mut mem) => if !mem.dealloc(pointer) {
-                    OSMemory::dealloc(pointer, layout);
-                },
-                Err(_) => {
-                    error!("メモリ解放中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-            
-        } else if layout.align() <= 32 {
-            match self.memory32.lock() {
-                Ok(mut mem) => if !mem.dealloc(pointer) {
-                    OSMemory::dealloc(pointer, layout);
-                },
-                Err(_) => {
-                    error!("メモリ解放中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 64 {
-            match self.memory64.lock() {
-                Ok(mut mem) => if !mem.dealloc(pointer) {
-                    OSMemory::dealloc(pointer, layout);
-                },
-                Err(_) => {
-                    error!("メモリ解放中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 128 {
-            match self.memory128.lock() {
-                Ok(mut mem) => if !mem.dealloc(pointer) {
-                    OSMemory::dealloc(pointer, layout);
-                },
-                Err(_) => {
-                    error!("メモリ解放中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
-        } else if layout.align() <= 256 {
-            match self.memory256.lock() {
+    /// # 戻り値
+    /// 
+    /// 失敗した際、エラーを返します。
+    /// 
+    pub(super) fn try_dealloc(&self, pointer: *mut u8, layout: Layout) -> AllocResult<()> {
+        match self.fix_memory(layout) {
+            Some(memory) => match memory.lock() {
                 Ok(mut mem) => if !mem.dealloc(pointer) {
                     OSMemory::dealloc(pointer, layout);
                 },
-                Err(_) => {
-                    error!("メモリ解放中に他スレッドが異常終了しました。");
-                    panic!()
-                },
-            }
+                Err(_) => return Err(AllocError::DeallocPoisoned),
+            },
+            None => OSMemory::dealloc(pointer, layout),
+        }
+        Ok(())
+    }
+
+    /// レイアウトに対応する固定長メモリを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * layout - メモリのレイアウトです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 固定長メモリ、または、OSメモリを使う場合はNoneです。
+    /// 
+    fn fix_memory(&self, layout: Layout) -> Option<&Mutex<FixMemory>> {
+        // 要素はサイズと整列長の大きい方に収まる必要があります。
+        let size = layout.size().max(layout.align());
+        if size <= 16 {
+            Some(&self.memory16)
+        } else if size <= 32 {
+            Some(&self.memory32)
+        } else if size <= 64 {
+            Some(&self.memory64)
+        } else if size <= 128 {
+            Some(&self.memory128)
+        } else if size <= 256 {
+            Some(&self.memory256)
         } else {
-            OSMemory::dealloc(pointer, layout);
+            None
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,124 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_memory/src/error.rs
+// (C) 2023 CwagoCommunity.
+//
+//! アロケータ内部のエラーを提供します。
+//! 
+//! グローバルアロケータの中でメモリを確保すると再入するため、
+//! 内部のエラーは確保を行わない値で表し、ロックを解放した後に`CwagoError`へ変換します。
+// =========================
+
+use std::{
+    alloc::Layout, 
+    sync::atomic::{
+        AtomicUsize, 
+        Ordering
+    }
+};
+
+use cwago_utility::{
+    error::{
+        CwagoError, 
+        ErrorKind
+    }, 
+    msg
+};
+
+use super::messages::*;
+
+/// アロケータ内部のエラーです。メモリを確保せずに作成、複製できます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AllocError {
+    /// 要求サイズが0です。
+    ZeroSize,
+    /// OSからのメモリ確保に失敗しました。
+    Os { size: usize, align: usize },
+    /// プールの要素サイズ、または、要素数が不正です。
+    PoolInvalid { size: usize, count: usize },
+    /// 確保中に他スレッドが異常終了しました。
+    AllocPoisoned,
+    /// 解放中に他スレッドが異常終了しました。
+    DeallocPoisoned,
+    /// 初期化に失敗しました。
+    InitFailed,
+    /// メモリ確保に失敗しました。
+    Failed,
+}
+impl AllocError {
+    /// 記録用の符号を取得します。0は使いません。
+    fn code(&self) -> usize {
+        match self {
+            AllocError::ZeroSize => 1,
+            AllocError::Os { .. } => 2,
+            AllocError::PoolInvalid { .. } => 3,
+            AllocError::AllocPoisoned => 4,
+            AllocError::DeallocPoisoned => 5,
+            AllocError::InitFailed => 6,
+            AllocError::Failed => 7,
+        }
+    }
+}
+impl From<AllocError> for CwagoError {
+    fn from(e: AllocError) -> Self {
+        match e {
+            AllocError::ZeroSize => CwagoError::new(ErrorKind::InvalidArgument, msg!(ZERO_SIZE)),
+            AllocError::Os { size, align } => CwagoError::new(
+                ErrorKind::Memory,
+                msg!(ALLOC_LAYOUT_FAILED, size = size, align = align)
+            ),
+            AllocError::PoolInvalid { size, count } => CwagoError::new(
+                ErrorKind::InvalidArgument,
+                msg!(POOL_INVALID, size = size, count = count)
+            ),
+            AllocError::AllocPoisoned => CwagoError::new(ErrorKind::Memory, msg!(ALLOC_POISONED)),
+            AllocError::DeallocPoisoned => CwagoError::new(ErrorKind::Memory, msg!(DEALLOC_POISONED)),
+            AllocError::InitFailed => CwagoError::new(ErrorKind::Memory, msg!(INIT_FAILED)),
+            AllocError::Failed => CwagoError::new(ErrorKind::Memory, msg!(ALLOC_FAILED)),
+        }
+    }
+}
+
+/// アロケータ内部の結果です。
+pub(super) type AllocResult<T> = std::result::Result<T, AllocError>;
+
+// グローバルアロケータで最後に起きたエラーです。
+static LAST_CODE: AtomicUsize = AtomicUsize::new(0);   // エラーの符号、無い場合は0です。
+static LAST_SIZE: AtomicUsize = AtomicUsize::new(0);   // 要求サイズ、または、要素サイズです。
+static LAST_EXTRA: AtomicUsize = AtomicUsize::new(0);  // 整列長、または、要素数です。
+
+/// エラーを記録します。メモリを確保しません。
+pub(super) fn record(e: AllocError) {
+    let (size, extra) = match e {
+        AllocError::Os { size, align } => (size, align),
+        AllocError::PoolInvalid { size, count } => (size, count),
+        _ => (0, 0),
+    };
+    LAST_SIZE.store(size, Ordering::Relaxed);
+    LAST_EXTRA.store(extra, Ordering::Relaxed);
+    LAST_CODE.store(e.code(), Ordering::Release);
+}
+
+/// 記録したエラーを取り出します。
+pub(super) fn take() -> Option<AllocError> {
+    let code = LAST_CODE.swap(0, Ordering::Acquire);
+    let size = LAST_SIZE.load(Ordering::Relaxed);
+    let extra = LAST_EXTRA.load(Ordering::Relaxed);
+    match code {
+        1 => Some(AllocError::ZeroSize),
+        2 => Some(AllocError::Os { size, align: extra }),
+        3 => Some(AllocError::PoolInvalid { size, count: extra }),
+        4 => Some(AllocError::AllocPoisoned),
+        5 => Some(AllocError::DeallocPoisoned),
+        6 => Some(AllocError::InitFailed),
+        7 => Some(AllocError::Failed),
+        _ => None,
+    }
+}
+
+/// レイアウトのOS確保失敗を作成します。
+pub(super) fn os(layout: Layout) -> AllocError {
+    AllocError::Os { size: layout.size(), align: layout.align() }
+}
@@ -10,23 +10,23 @@
 
 use std::{
     alloc::Layout,
-    mem::{
-        size_of, 
-        transmute
-    },
-    ptr::{
-        null_mut, 
-        drop_in_place
-    }
+    mem::size_of,
+    ptr::drop_in_place
 };
-use cwago_utility::log::error;
 use super::{
+    error::{
+        AllocError, 
+        AllocResult
+    }, 
     os::OSMemory,
     pool::Pool
 };
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
+    use std::ptr::null_mut;
+
     use super::*;
 
     const SIZE_MAX: usize = 256;
@@ -36,8 +36,7 @@ mod tests {
     #[test]
     fn test_fix_memory() {
 
-        std::env::set_var("RUST_LOG", "error");
-        env_logger::init();
+        cwago_utility::logging::init();
 
         // サイズが1~256までで作成可能かテストします。
         for size in 1..SIZE_MAX {
@@ -52,7 +51,7 @@ mod tests {
     fn test_fix_memory_one(size: usize, count: usize) {
 
         // 作成します。
-        let mut mem = FixMemory::new(size, count);
+        let mut mem = FixMemory::try_new(size, count).expect("固定長メモリの作成に失敗しました。");
 
         // 使いまわしが可能かテストします。
         for _lap in 0..3usize {
@@ -61,7 +60,7 @@ mod tests {
 
             // メモリが確保可能かテストします。
             for i in 0..LENGTH_MAX {
-                ptrs[i] = mem.alloc();
+                ptrs[i] = mem.try_alloc().expect("メモリ確保に失敗しました。");
                 assert_ne!(ptrs[i], null_mut(), "{}回目のメモリ確保で失敗しました。", i);
             }
 
@@ -111,13 +110,12 @@ impl FixMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 成功した際はインスタンス、失敗した際はNoneが返ります。
+    /// 成功した際はインスタンス、失敗した際はエラーが返ります。
     /// 
-    pub(super) fn new(elements_size: usize, elements_count: usize) -> FixMemory {
+    pub(super) fn try_new(elements_size: usize, elements_count: usize) -> AllocResult<FixMemory> {
         // サイズ、または、要素数0の場合作成されません。
         if elements_size == 0 || elements_count == 0 {
-            error!("メモリ要素サイズ:{}, 要素数:{} でメモリプールの作成に失敗しました。", elements_size, elements_count);
-            panic!()
+            return Err(AllocError::PoolInvalid { size: elements_size, count: elements_count });
         }
 
         // プール配列の配列長と初期要素数です。
@@ -125,7 +123,7 @@ impl FixMemory {
         let pools_count = 1usize;
 
         // プール配列を作成します。
-        let (pools, pools_layout) = Self::alloc_pools(pools_length);
+        let (pools, pools_layout) = Self::alloc_pools(pools_length)?;
 
         // プールのメモリレイアウトを作成します。
         let pool_size = size_of::<Pool>();
@@ -133,17 +131,16 @@ impl FixMemory {
         let pool_layout = unsafe { Layout::from_size_align_unchecked(pool_size, pool_align) };
 
         // 初期プールを作成します。
-        let alloc_pool = Self::new_pool(pool_layout, elements_size, elements_count);
-        if alloc_pool == null_mut() {
-
-            // 進行不可能です。
-            // エラーログを残して、異常終了します。
-            error!("メモリ確保に失敗しました。");
-            panic!();
-        }
+        let alloc_pool = match Self::new_pool(pool_layout, elements_size, elements_count) {
+            Ok(pool) => pool,
+            Err(e) => {
+                Self::dealloc_pools(pools, pools_layout);
+                return Err(e);
+            },
+        };
         unsafe { *pools = alloc_pool };
 
-        FixMemory { 
+        Ok(FixMemory { 
             elements_size, 
             elements_count, 
             pools_length, 
@@ -152,23 +149,41 @@ impl FixMemory {
             pool_layout, 
             pools, 
             alloc_pool
+        })
+    }
+
+    /// 以降に追加するプールが管理する要素数を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * elements_count - 1つのプールが管理する要素数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 要素数が0の場合、エラーを返します。
+    /// 
+    pub(super) fn set_elements_count(&mut self, elements_count: usize) -> AllocResult<()> {
+        if elements_count == 0 {
+            return Err(AllocError::PoolInvalid { size: self.elements_size, count: elements_count });
         }
+        self.elements_count = elements_count;
+        Ok(())
     }
 
     /// メモリを確保します。
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリへのポインタ、または、ヌルポインタです。
+    /// 確保したメモリへのポインタ、または、エラーです。
     /// 
-    pub(super) fn alloc(&mut self) -> *mut u8 {
+    pub(super) fn try_alloc(&mut self) -> AllocResult<*mut u8> {
         // メモリ確保用プールが空なので、プールを確保します。
         if unsafe { &*self.alloc_pool }.is_empty() {
-            self.add_pool();
+            self.add_pool()?;
         }
 
         // メモリ確保用プールからメモリを確保します。
-        unsafe { &mut *self.alloc_pool }.alloc()
+        Ok(unsafe { &mut *self.alloc_pool }.alloc())
     }
 
     /// メモリを解放します。
@@ -201,12 +216,12 @@ impl FixMemory {
     
 
     /// プールを追加して、メモリ確保用プールに設定します。
-    fn add_pool(&mut self) {
+    fn add_pool(&mut self) -> AllocResult<()> {
         if self.pools_count == self.pools_length {
-            self.expand_pools();
+            self.expand_pools()?;
         }
 
-        self.alloc_pool = Self::new_pool(self.pool_layout, self.elements_size, self.elements_count);
+        self.alloc_pool = Self::new_pool(self.pool_layout, self.elements_size, self.elements_count)?;
 
         // 挿入位置を取得します。
         let index = Self::insert_index(self.alloc_pool, self.pools, self.pools_count);
@@ -219,6 +234,7 @@ impl FixMemory {
         // 挿入します。
         unsafe { (*self.pools.add(index)) = self.alloc_pool };
         self.pools_count += 1;
+        Ok(())
     } 
 
     /// プールを削除します。
@@ -231,7 +247,7 @@ impl FixMemory {
         Self::drop_pool(unsafe { *self.pools.add(index) }, self.pool_layout);
 
         // 削除位置から後ろを詰めます。
-        for i in index..self.pools_count {
+        for i in index..(self.pools_count - 1) {
             unsafe { *self.pools.add(i) = *self.pools.add(i + 1) }; 
         }
         
@@ -269,24 +285,21 @@ impl FixMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリプールです。
+    /// 確保したメモリプール、または、エラーです。
     /// 
-    /// # 異常終了
-    /// 
-    /// ヒープメモリの確保に失敗した場合異常終了します。
-    /// メモリプールの作成に失敗した場合異常終了します。
-    /// 
-    fn new_pool(layout: Layout, size: usize, count: usize) -> *mut Pool {
-        let pool: *mut Pool = unsafe { transmute(OSMemory::alloc_zeroed(layout)) };
-        if pool == null_mut() {
+    fn new_pool(layout: Layout, size: usize, count: usize) -> AllocResult<*mut Pool> {
+        let pool = OSMemory::try_alloc_zeroed(layout)? as *mut Pool;
 
-            error!("メモリ要求サイズ:{}, 整列長:{} でメモリの確保に失敗しました。", layout.size(), layout.align());
-            panic!();
+        // 未初期化の領域なので、ドロップせずに書き込みます。
+        match Pool::try_new(size, count) {
+            Ok(p) => unsafe { pool.write(p) },
+            Err(e) => {
+                OSMemory::dealloc(pool as *mut u8, layout);
+                return Err(e);
+            },
         }
-
-        unsafe { *pool = Pool::new(size, count) };
         
-        pool
+        Ok(pool)
     }
 
     /// メモリプールを解放します。
@@ -298,14 +311,14 @@ impl FixMemory {
     /// 
     fn drop_pool(pool: *mut Pool, layout: Layout) {
         unsafe { drop_in_place(pool) };
-        OSMemory::dealloc(unsafe { transmute(pool) }, layout);
+        OSMemory::dealloc(pool as *mut u8, layout);
     }
 
     /// プール配列を拡張します。 
-    fn expand_pools(&mut self) {
+    fn expand_pools(&mut self) -> AllocResult<()> {
         // 拡張した新配列を作成します。
         let length = self.pools_length * Self::EXPANSION_MULTIPLY;
-        let (pools, layout) = Self::alloc_pools(length);
+        let (pools, layout) = Self::alloc_pools(length)?;
 
         // 内容を移動させます。
         for i in 0..self.pools_count {
@@ -319,6 +332,7 @@ impl FixMemory {
         self.pools = pools;
         self.pools_length = length;
         self.pools_layout = layout;
+        Ok(())
     }
 
     /// プール配列を作成します。
@@ -329,21 +343,17 @@ impl FixMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 成功した際は配列ポインタとメモリレイアウトが返ります。
-    fn alloc_pools(length: usize) -> (*mut *mut Pool, Layout) {
+    /// 成功した際は配列ポインタとメモリレイアウト、失敗した際はエラーが返ります。
+    fn alloc_pools(length: usize) -> AllocResult<(*mut *mut Pool, Layout)> {
         // 配列のサイズと整列長です。
         let size = size_of::<*mut Pool>() * length;
         let align = size.next_power_of_two();
         
         // 配列を確保します。
         let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
-        let buffer = unsafe { transmute(OSMemory::alloc(layout)) };
-        if buffer == null_mut() {
-            error!("メモリ要求サイズ:{}, 整列長:{} でメモリの確保に失敗しました。", layout.size(), layout.align());
-            panic!();
-        }
+        let buffer = OSMemory::try_alloc(layout)? as *mut *mut Pool;
 
-        (buffer, layout)
+        Ok((buffer, layout))
     }
 
     /// プール配列を解体します。
@@ -354,7 +364,7 @@ impl FixMemory {
     /// * layout - プール配列のメモリレイアウトです。
     /// 
     fn dealloc_pools(pools: *mut *mut Pool, layout: Layout) {
-        OSMemory::dealloc(unsafe { transmute(pools) }, layout);
+        OSMemory::dealloc(pools as *mut u8, layout);
     }
 
     /// ポインタから管理プールの位置を取得します。
@@ -392,6 +402,6 @@ impl Drop for FixMemory {
         for i in 0..self.pools_count {
             Self::drop_pool(unsafe { *self.pools.add(i) }, self.pool_layout);
         }
-        Self::dealloc_pools(self.pools, self.pool_layout);
+        Self::dealloc_pools(self.pools, self.pools_layout);
     }
 }
\ No newline at end of file
@@ -9,23 +9,58 @@
 // =========================
 
 use std::{
-    alloc::GlobalAlloc, 
-    sync::Once
+    alloc::{
+        GlobalAlloc, 
+        Layout
+    }, 
+    ptr::{
+        addr_of, 
+        null_mut, 
+        NonNull
+    }, 
+    sync::{
+        atomic::{
+            AtomicUsize, 
+            Ordering
+        }, 
+        Mutex, 
+        Once
+    }
 };
 
-use cwago_utility::log::error;
+use cwago_utility::{
+    config::Config, 
+    error::{
+        CwagoError, 
+        ErrorKind, 
+        Result
+    }, 
+    log::warn, 
+    msg, 
+    profile_counter
+};
+use serde::{
+    Deserialize, 
+    Serialize
+};
 
+mod error;
 mod os;
 mod pool;
 mod fix;
 mod dy;
+pub mod messages;
+
+use error::{
+    AllocError, 
+    AllocResult
+};
+use messages::POOL_INVALID;
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
-    use std::{
-        ptr::null_mut, 
-        alloc::Layout
-    };
+    use std::sync::Arc;
 
     use super::*;
 
@@ -35,8 +70,7 @@ mod tests {
     #[test]
     fn test_allocator() {
 
-        std::env::set_var("RUST_LOG", "error");
-        env_logger::init();
+        cwago_utility::logging::init();
 
         let mem = Allocator::new();
 
@@ -71,13 +105,187 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_config() {
+
+        cwago_utility::logging::init();
+
+        // 共有のアロケータを変えないよう、変更はこのテストの可変長メモリへ適用します。
+        let applied = Arc::new(Mutex::new(None));
+        let target = applied.clone();
+        let mut config = Config::new();
+        register_config_with(&mut config, move |c| {
+            *target.lock().unwrap() = Some(*c);
+            Ok(())
+        }).expect("設定の登録に失敗しました。");
+        assert_eq!(config.get::<MemoryConfig>(CONFIG_KEY).unwrap(), MemoryConfig::default());
+
+        // 要素数0は拒否されます。
+        let zero = MemoryConfig { count16: 0, ..MemoryConfig::default() };
+        assert!(dy::DyMemory::try_new(&zero).is_err());
+
+        // 変更後も確保、解放できるかテストします。
+        config.load_args(vec!["--memory.count16=4".to_string()]).unwrap();
+        let applied = applied.lock().unwrap().take().expect("変更が通知されていません。");
+        assert_eq!(applied.count16, 4);
+        let mem = dy::DyMemory::try_new(&MemoryConfig::default()).expect("可変長メモリの作成に失敗しました。");
+        mem.configure(&applied).expect("設定の適用に失敗しました。");
+        assert!(mem.configure(&zero).is_err());
+        let layout = Layout::from_size_align(16, 16).unwrap();
+        let ptrs = (0..64)
+            .map(|_| mem.try_alloc(layout).expect("メモリ確保に失敗しました。"))
+            .collect::<Vec<_>>();
+        for ptr in ptrs {
+            mem.try_dealloc(ptr, layout).expect("メモリ解放に失敗しました。");
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+
+        cwago_utility::logging::init();
+
+        let mem = Allocator::new();
+        let before = Allocator::stats();
+        let layout = Layout::from_size_align(1000, 8).unwrap();
+        let ptr = mem.try_alloc(layout).expect("メモリ確保に失敗しました。");
+        let during = Allocator::stats();
+        assert!(during.allocations > before.allocations);
+        assert!(during.peak_bytes >= 1000);
+        unsafe { mem.try_dealloc(ptr, layout) }.expect("メモリ解放に失敗しました。");
+        assert!(Allocator::stats().deallocations > before.deallocations);
+        Allocator::record_profile();
+    }
+
+    #[test]
+    fn test_try_alloc() {
+
+        cwago_utility::logging::init();
+
+        let mem = Allocator::new();
+
+        // サイズ0の確保はエラーになるかテストします。
+        let layout = Layout::from_size_align(0, 1).unwrap();
+        let e = mem.try_alloc(layout).expect_err("サイズ0の確保が成功しました。");
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+
+        // 整列長より大きいサイズを確保できるかテストします。
+        for size in [17usize, 100, 255, 256, 257, 4096] {
+            let layout = Layout::from_size_align(size, 1).unwrap();
+            let ptr = mem.try_alloc(layout).expect("メモリ確保に失敗しました。");
+            unsafe { ptr.as_ptr().write_bytes(0xab, size) };
+            unsafe { mem.try_dealloc(ptr, layout) }.expect("メモリ解放に失敗しました。");
+        }
+    }
+
+    #[test]
+    fn test_take_error() {
+
+        cwago_utility::logging::init();
+
+        let mem = Allocator::new();
+
+        // GlobalAllocの失敗は記録され、後から取り出せるかテストします。
+        let layout = Layout::from_size_align(0, 1).unwrap();
+        assert_eq!(unsafe { mem.alloc(layout) }, null_mut(), "サイズ0の確保が成功しました。");
+        let e = Allocator::take_error().expect("エラーが記録されていません。");
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+        assert!(Allocator::take_error().is_none(), "取り出したエラーが残っています。");
+    }
+}
+
+/// 設定のメモリの項目名です。
+pub const CONFIG_KEY: &str = "memory";
+
+/// メモリアロケータの設定です。
+/// 
+/// 各サイズの固定長メモリで、1つのプールが管理する要素数を指定します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// 16バイト以下の要素の数です。
+    pub count16: usize,
+    /// 32バイト以下の要素の数です。
+    pub count32: usize,
+    /// 64バイト以下の要素の数です。
+    pub count64: usize,
+    /// 128バイト以下の要素の数です。
+    pub count128: usize,
+    /// 256バイト以下の要素の数です。
+    pub count256: usize,
+}
+impl MemoryConfig {
+    /// 既定の設定です。
+    pub const DEFAULT: MemoryConfig = MemoryConfig {
+        count16: 32,
+        count32: 32,
+        count64: 32,
+        count128: 16,
+        count256: 16,
+    };
+}
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// 設定にメモリの項目を登録します。
+/// 
+/// `memory`の項目を宣言し、変更された際にアロケータへ適用します。
+/// 
+/// # 引数
+/// 
+/// * `config` - 登録する設定です。
+/// 
+/// # 戻り値
+/// 
+/// 既に登録されている場合、エラーを返します。
+/// 
+pub fn register_config(config: &mut Config) -> Result<()> {
+    register_config_with(config, |c| Allocator::new().configure(c))
+}
+
+// 設定にメモリの項目を登録し、変更された設定をfへ渡します。
+fn register_config_with(
+    config: &mut Config, 
+    f: impl Fn(&MemoryConfig) -> Result<()> + Send + 'static
+) -> Result<()> {
+    config.declare(CONFIG_KEY, &MemoryConfig::default())?;
+    config.subscribe(CONFIG_KEY, move |config| {
+        let result = config.get::<MemoryConfig>(CONFIG_KEY)
+            .and_then(|c| f(&c));
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+    });
+    Ok(())
+}
+
+/// メモリアロケータの統計です。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// 確保した回数です。
+    pub allocations: usize,
+    /// 解放した回数です。
+    pub deallocations: usize,
+    /// 使用中のバイト数です。
+    pub bytes: usize,
+    /// 使用中のバイト数の最大値です。
+    pub peak_bytes: usize,
 }
 
 /// メモリアロケータです。
 #[derive(Debug, Clone, Copy)]
 pub struct Allocator;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
 static mut DY_MEMORY: Option<dy::DyMemory> = None;
 static ONCE: Once = Once::new();
+static CONFIG: Mutex<MemoryConfig> = Mutex::new(MemoryConfig::DEFAULT);
 impl Allocator {
     /// 作成します。
     /// 
@@ -88,6 +296,181 @@ impl Allocator {
     pub const fn new() -> Allocator {
         Allocator {}
     }
+
+    /// 設定を適用します。
+    /// 
+    /// 初期化前に呼び出した場合は初期プールから、
+    /// 初期化後に呼び出した場合は以降に追加されるプールから適用されます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `config` - 設定です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 要素数が0の場合などに、エラーを返します。
+    /// 
+    pub fn configure(&self, config: &MemoryConfig) -> Result<()> {
+        let counts = [config.count16, config.count32, config.count64, config.count128, config.count256];
+        if let Some(size) = counts.iter().position(|c| *c == 0).map(|i| 16usize << i) {
+            return Err(CwagoError::new(ErrorKind::Config, msg!(POOL_INVALID, size = size, count = 0)));
+        }
+        *CONFIG.lock().unwrap_or_else(|e| e.into_inner()) = *config;
+        if ONCE.is_completed() {
+            Self::memory()?.configure(config)?;
+        }
+        Ok(())
+    }
+
+    /// メモリを確保します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layout` -  確保するメモリのレイアウトです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 確保したメモリのポインタ、または、エラーです。
+    /// 
+    pub fn try_alloc(&self, layout: Layout) -> Result<NonNull<u8>> {
+        Ok(Self::alloc_raw(layout)?)
+    }
+
+    /// メモリを解放します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `ptr` - 解放するメモリのポインタです。
+    /// * `layout` - 解放するメモリのレイアウトです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 失敗した際、エラーを返します。
+    /// 
+    /// # Safety
+    /// 
+    /// `ptr`はこのアロケータが`layout`で確保した、未解放のメモリである必要があります。
+    /// 
+    pub unsafe fn try_dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> Result<()> {
+        Ok(Self::dealloc_raw(ptr, layout)?)
+    }
+
+    /// グローバルアロケータとして失敗した際のエラーを取り出します。
+    /// 
+    /// `GlobalAlloc`の中ではメモリを確保できないため、エラーは記録のみ行われます。
+    /// 記録は最後の1件のみ保持され、取り出すと消去されます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 記録されたエラー、または、Noneです。
+    /// 
+    pub fn take_error() -> Option<CwagoError> {
+        error::take().map(CwagoError::from)
+    }
+
+    /// 統計を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// すべてのインスタンスで共有する統計です。
+    /// 
+    pub fn stats() -> MemoryStats {
+        MemoryStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+            bytes: BYTES.load(Ordering::Relaxed),
+            peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 統計を計測のカウンタに記録します。
+    /// 
+    /// 確保の度に記録すると計測自体が確保を行うため、フレーム毎などに呼び出します。
+    /// `profile`フィーチャーが無効な場合は何もしません。
+    /// 
+    pub fn record_profile() {
+        profile_counter!("memory.bytes", BYTES.load(Ordering::Relaxed));
+        profile_counter!(
+            "memory.live_allocations", 
+            ALLOCATIONS.load(Ordering::Relaxed).saturating_sub(DEALLOCATIONS.load(Ordering::Relaxed))
+        );
+    }
+
+    /// メモリを確保します。失敗してもエラーの作成でメモリを確保しません。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layout` -  確保するメモリのレイアウトです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 確保したメモリのポインタ、または、エラーです。
+    /// 
+    fn alloc_raw(layout: Layout) -> AllocResult<NonNull<u8>> {
+        // サイズ0の確保は要求として不正です。
+        if layout.size() == 0 {
+            return Err(AllocError::ZeroSize);
+        }
+        let ptr = Self::memory()?.try_alloc(layout)?;
+        let ptr = NonNull::new(ptr).ok_or(AllocError::Failed)?;
+
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let bytes = BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK_BYTES.fetch_max(bytes, Ordering::Relaxed);
+        Ok(ptr)
+    }
+
+    /// メモリを解放します。失敗してもエラーの作成でメモリを確保しません。
+    /// 
+    /// # 引数
+    /// 
+    /// * `ptr` - 解放するメモリのポインタです。
+    /// * `layout` - 解放するメモリのレイアウトです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 失敗した際、エラーを返します。
+    /// 
+    /// # Safety
+    /// 
+    /// `ptr`はこのアロケータが`layout`で確保した、未解放のメモリである必要があります。
+    /// 
+    unsafe fn dealloc_raw(ptr: NonNull<u8>, layout: Layout) -> AllocResult<()> {
+        Self::memory()?.try_dealloc(ptr.as_ptr(), layout)?;
+
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 可変長メモリを取得します。
+    /// 
+    /// 初期化に失敗した原因は記録され、`take_error`で取り出せます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 初期化済みの可変長メモリ、または、初期化に失敗した際のエラーです。
+    /// 
+    fn memory() -> AllocResult<&'static dy::DyMemory> {
+        // 初期化します。
+        ONCE.call_once(|| {
+            let config = *CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+            match dy::DyMemory::try_new(&config) {
+                Ok(mem) => unsafe { DY_MEMORY = Some(mem) },
+                Err(e) => error::record(e),
+            }
+        });
+
+        match unsafe { (*addr_of!(DY_MEMORY)).as_ref() } {
+            Some(mem) => Ok(mem),
+            None => Err(AllocError::InitFailed),
+        }
+    }
+}
+impl Default for Allocator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 unsafe impl Send for Allocator {}
 unsafe impl Sync for Allocator {}
@@ -100,22 +483,17 @@ unsafe impl GlobalAlloc for Allocator {
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリのポインタです。
+    /// 確保したメモリのポインタ、失敗した際はヌルポインタです。
     /// 
-    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
-        // 初期化します。
-        ONCE.call_once(|| unsafe {
-            DY_MEMORY = Some(dy::DyMemory::new());
-        });
-        
-        // メモリを確保します。
-        match unsafe { DY_MEMORY.as_mut() } {
-            Some(mem) => mem,
-            None => {
-                error!("メモリシステムの初期化に失敗しました。");
-                panic!()
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // 再入を避けるため、エラーは記録のみ行います。
+        match Self::alloc_raw(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(e) => {
+                error::record(e);
+                null_mut()
             },
-        }.alloc(layout)
+        }
     }
 
     /// メモリを解放します。
@@ -125,19 +503,12 @@ unsafe impl GlobalAlloc for Allocator {
     /// * `ptr` - 解放するメモリのポインタです。
     /// * `layout` - 解放するメモリのレイアウトです。
     /// 
-    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
-        // 初期化します。
-        ONCE.call_once(|| unsafe {
-            DY_MEMORY = Some(dy::DyMemory::new());
-        });
-        
-        // メモリを解放します。
-        match unsafe { DY_MEMORY.as_mut() }{
-            Some(mem) => mem,
-            None => {
-                error!("メモリシステムの初期化に失敗しました。");
-                panic!()
-            },
-        }.dealloc(ptr, layout)
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // 再入を避けるため、エラーは記録のみ行います。
+        if let Some(ptr) = NonNull::new(ptr) {
+            if let Err(e) = Self::dealloc_raw(ptr, layout) {
+                error::record(e);
+            }
+        }
     }
 }
\ No newline at end of file
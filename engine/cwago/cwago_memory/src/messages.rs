@@ -0,0 +1,74 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_memory/src/messages.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_memoryの診断メッセージです。
+// =========================
+
+use cwago_utility::msg::Message;
+
+pub(crate) const ZERO_SIZE: Message = Message::new(
+    "memory.zero_size",
+    "確保しようとしたメモリサイズが0でした。",
+    "The requested memory size was 0."
+);
+pub(crate) const ZERO_ALIGN: Message = Message::new(
+    "memory.zero_align",
+    "確保しようとしたメモリサイズのアラインメントが0でした。",
+    "The requested memory alignment was 0."
+);
+pub(crate) const ALLOC_FAILED: Message = Message::new(
+    "memory.alloc_failed",
+    "メモリ確保に失敗しました。",
+    "Failed to allocate memory."
+);
+pub(crate) const ALLOC_LAYOUT_FAILED: Message = Message::new(
+    "memory.alloc_layout_failed",
+    "メモリ要求サイズ:{size}, 整列長:{align} でメモリの確保に失敗しました。",
+    "Failed to allocate memory of size {size} with alignment {align}."
+);
+pub(crate) const POOL_INVALID: Message = Message::new(
+    "memory.pool_invalid",
+    "メモリ要素サイズ:{size}, 要素数:{count} でメモリプールの作成に失敗しました。",
+    "Failed to create a memory pool with element size {size} and count {count}."
+);
+pub(crate) const ALLOC_POISONED: Message = Message::new(
+    "memory.alloc_poisoned",
+    "メモリ確保中に他スレッドが異常終了しました。",
+    "Another thread panicked while allocating memory."
+);
+pub(crate) const DEALLOC_POISONED: Message = Message::new(
+    "memory.dealloc_poisoned",
+    "メモリ解放中に他スレッドが異常終了しました。",
+    "Another thread panicked while freeing memory."
+);
+pub(crate) const INIT_FAILED: Message = Message::new(
+    "memory.init_failed",
+    "メモリシステムの初期化に失敗しました。",
+    "Failed to initialize the memory system."
+);
+
+/// cwago_memoryのすべての診断メッセージです。
+pub const MESSAGES: &[&Message] = &[
+    &ZERO_SIZE,
+    &ZERO_ALIGN,
+    &ALLOC_FAILED,
+    &ALLOC_LAYOUT_FAILED,
+    &POOL_INVALID,
+    &ALLOC_POISONED,
+    &DEALLOC_POISONED,
+    &INIT_FAILED,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        cwago_utility::msg::check_catalog_source(MESSAGES, include_str!("messages.rs"));
+    }
+}
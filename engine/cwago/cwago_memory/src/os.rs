@@ -8,22 +8,20 @@
 //! OSメモリのシングルトンを提供します。
 // =========================
 
-use std::{
-    alloc::{
-        Layout, 
-        System, 
-        GlobalAlloc
-    }, 
-    sync::Once, ptr::null_mut
+use std::alloc::{
+    Layout, 
+    System, 
+    GlobalAlloc
 };
 
-use cwago_utility::log::error;
-
+use super::error::{
+    self, 
+    AllocError, 
+    AllocResult
+};
 
 // OSが提供するメモリのシングルトンです。
 pub(super) struct OSMemory;
-static mut SYSTEM: Option<System> = None;
-static ONCE: Once = Once::new();
 impl OSMemory {
 
     /// メモリを確保します。
@@ -34,28 +32,21 @@ impl OSMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリです。
+    /// 確保したメモリ、または、エラーです。
     /// 
-    pub(super) fn alloc(layout: Layout) -> *mut u8 {
-        // OSメモリを初期化します。
-        ONCE.call_once(|| unsafe{
-            SYSTEM = Some(System::default())
-        });
+    pub(super) fn try_alloc(layout: Layout) -> AllocResult<*mut u8> {
+        // 0サイズの確保は未定義動作のため、事前に弾きます。
+        if layout.size() == 0 {
+            return Err(AllocError::ZeroSize);
+        }
 
         // メモリを確保します。
-        let ptr = unsafe { SYSTEM.unwrap().alloc(layout) };
-        if ptr == null_mut() {
-            if layout.size() == 0 {
-                error!("確保しようとしたメモリサイズが0でした。");
-            } else if layout.align() == 0 {
-                error!("確保しようとしたメモリサイズのアラインメントが0でした。");
-            } else {
-                error!("メモリ確保に失敗しました。");
-            }
-            panic!();
+        let ptr = unsafe { System.alloc(layout) };
+        if ptr.is_null() {
+            return Err(error::os(layout));
         }
 
-        ptr
+        Ok(ptr)
     }
 
     /// 0初期化したメモリを確保します。
@@ -66,17 +57,15 @@ impl OSMemory {
     /// 
     /// # 戻り値
     /// 
-    /// 確保したメモリです。
+    /// 確保したメモリ、または、エラーです。
     /// 
-    pub(super) fn alloc_zeroed(layout: Layout) -> *mut u8 {
-        let ptr = Self::alloc(layout);
+    pub(super) fn try_alloc_zeroed(layout: Layout) -> AllocResult<*mut u8> {
+        let ptr = Self::try_alloc(layout)?;
 
         // 0初期化します。
-        for i in 0..layout.align() {
-            unsafe { *ptr.add(i) = 0u8 };
-        }
+        unsafe { ptr.write_bytes(0u8, layout.size()) };
 
-        ptr
+        Ok(ptr)
     }
 
     /// メモリを解放します。
@@ -87,11 +76,6 @@ impl OSMemory {
     /// * layout - 解放するメモリレイアウトです。
     /// 
     pub(super) fn dealloc(pointer: *mut u8, layout: Layout) {
-
-        if let Some(system) = unsafe { &SYSTEM } {
-            unsafe { system.dealloc(pointer, layout) };
-        } else {
-            error!("メモリが確保される前に解放しようとしました。");
-        }
+        unsafe { System.dealloc(pointer, layout) };
     }
 }
\ No newline at end of file
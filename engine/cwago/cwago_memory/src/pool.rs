@@ -10,16 +10,19 @@
 
 use std::{
     alloc::Layout,
-    mem::{
-        size_of, 
-        transmute
-    },
+    mem::size_of,
     ptr::null_mut
 };
-use cwago_utility::log::error;
-use super::os::OSMemory;
+use super::{
+    error::{
+        AllocError, 
+        AllocResult
+    }, 
+    os::OSMemory
+};
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
     use super::*;
 
@@ -29,8 +32,7 @@ mod tests {
     #[test]
     fn test_pool() {
 
-        std::env::set_var("RUST_LOG", "error");
-        env_logger::init();
+        cwago_utility::logging::init();
 
         // サイズが1~256までで作成可能かテストします。
         for size in 1..SIZE_MAX {
@@ -42,7 +44,7 @@ mod tests {
     }
     fn test_pool_one(size: usize, count: usize) {
         // 作成します。
-        let mut  pool = Pool::new(size, count);
+        let mut pool = Pool::try_new(size, count).expect("プールの作成に失敗しました。");
 
         // 使いまわしが可能かテストします。
         for _lap in 0..3usize {
@@ -119,12 +121,11 @@ impl Pool {
     /// 
     /// # 戻り値
     /// 
-    /// インスタンスが返ります。
-    pub(super) fn new(size: usize, count: usize) -> Pool {
+    /// インスタンス、または、エラーが返ります。
+    pub(super) fn try_new(size: usize, count: usize) -> AllocResult<Pool> {
         // サイズ、または、要素数0の場合作成されません。
         if size == 0 || count == 0 {
-            error!("メモリ要素サイズ:{}, 要素数:{} でメモリプールの作成に失敗しました。", size, count);
-            panic!()
+            return Err(AllocError::PoolInvalid { size, count });
         }
 
         // 1要素のサイズと整列長です。
@@ -137,11 +138,7 @@ impl Pool {
         
         // 領域を確保します。
         let layout = unsafe { Layout::from_size_align_unchecked(buf_size, buf_align) };
-        let buffer = OSMemory::alloc(layout);
-        if buffer == null_mut() {
-            error!("メモリの確保に失敗しました。");
-            panic!()        
-        }
+        let buffer = OSMemory::try_alloc(layout)?;
         
         // 連結リストを作成します。
         // 
@@ -152,8 +149,8 @@ impl Pool {
         let mut top = null_mut();
         for i in 0..count {
             let lp = unsafe { buffer.add(i * align) }; 
-            let lpp = unsafe { transmute::<*mut u8, *mut *mut u8>(lp) };
-            let rp = unsafe { transmute::<*mut *mut u8, *mut u8>(top) };
+            let lpp = lp as *mut *mut u8;
+            let rp = top as *mut u8;
             unsafe { *lpp = rp };
             top = lpp; 
         }
@@ -162,7 +159,7 @@ impl Pool {
         let min_address = unsafe { buffer.add(0) } as usize;
         let max_address = unsafe { buffer.add(align * (count - 1)) } as usize;
 
-        Pool{ 
+        Ok(Pool{ 
             all_count: count, 
             free_count: count, 
             layout, 
@@ -170,7 +167,7 @@ impl Pool {
             top, 
             min_address, 
             max_address 
-        }
+        })
     }
 
     /// 要素を確保します。
@@ -181,14 +178,14 @@ impl Pool {
     /// 
     pub(super) fn alloc(&mut self) -> *mut u8 {
         // 要素リストが空の場合、ヌルポインタを返します。
-        if self.top == null_mut() {
+        if self.top.is_null() {
             return null_mut();
         }
         // リストから要素を1つ取り出し返します。
         self.free_count -= 1;
         let ptr = self.top;
-        unsafe { self.top = transmute::<*mut u8, *mut *mut u8>(*ptr) };
-        unsafe { transmute::<*mut *mut u8, *mut u8>(ptr) }
+        unsafe { self.top = *ptr as *mut *mut u8 };
+        ptr as *mut u8
     }
 
     /// 要素を解放します。
@@ -208,8 +205,8 @@ impl Pool {
         }
         // リストに要素を挿入して、真を返します。
         self.free_count += 1;
-        let ptr = unsafe { transmute::<*mut u8, *mut *mut u8>(pointer) };
-        unsafe { *ptr = transmute::<*mut *mut u8, *mut u8>(self.top) };
+        let ptr = pointer as *mut *mut u8;
+        unsafe { *ptr = self.top as *mut u8 };
         self.top = ptr;
         true
     }
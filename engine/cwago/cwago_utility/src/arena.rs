@@ -0,0 +1,997 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/arena.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 世代付きのキーで値を管理するアリーナを提供します。
+//! 
+//! キーは添え字と世代値の組です。値を削除するとスロットの世代値が進むため、
+//! 削除済みの値を指す古いキーは、スロットが再利用された後も無効のままです。
+//! 世代値が一巡するスロットは退役させ、二度と再利用しません。
+//! 
+//! `GenArena`はスロットに値を直接格納し、`DenseArena`は値を連続した配列に詰めて格納します。
+//! 
+//! ```
+//! use cwago_utility::{arena::GenArena, new_key_type};
+//! 
+//! new_key_type! {
+//!     /// 音のキーです。
+//!     pub struct SoundKey;
+//! }
+//! 
+//! let mut sounds = GenArena::<&str, SoundKey>::new();
+//! let jump = sounds.insert("jump.ogg");
+//! assert_eq!(sounds[jump], "jump.ogg");
+//! sounds.remove(jump);
+//! let land = sounds.insert("land.ogg");
+//! assert_eq!(sounds.get(jump), None);
+//! assert_eq!(sounds.get(land), Some(&"land.ogg"));
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Debug,
+        Display
+    },
+    hash::Hash,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ops::{
+        Index,
+        IndexMut
+    },
+    slice
+};
+
+use serde::{
+    de::Error as _,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+
+use crate::{
+    messages::{
+        ARENA_FULL,
+        ARENA_INVALID_ENTRY,
+        ARENA_INVALID_KEY,
+        ARENA_INVALID_SLOT
+    },
+    msg
+};
+
+#[doc(hidden)]
+pub use serde as __serde;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::new_key_type! {
+        struct TestKey;
+    }
+
+    #[test]
+    fn test_gen_arena() {
+        let mut arena = GenArena::<String, TestKey>::new();
+        let a = arena.insert("a".to_string());
+        let b = arena.insert_with_key(|k| format!("b{}", k.raw().index()));
+        assert_eq!(arena.len(), 2);
+        assert!(arena.can_insert());
+        assert_eq!(arena[a], "a");
+        assert_eq!(arena[b], "b1");
+
+        // 削除したキーは、スロットが再利用されても無効です。
+        assert_eq!(arena.remove(a).as_deref(), Some("a"));
+        assert_eq!(arena.remove(a), None);
+        let c = arena.insert("c".to_string());
+        assert_eq!(c.raw().index(), a.raw().index(), "空きスロットを再利用する必要があります。");
+        assert_ne!(c, a);
+        assert!(!arena.contains_key(a));
+        assert_eq!(arena.get(c).map(String::as_str), Some("c"));
+
+        arena.get_mut(b).unwrap().push('!');
+        let items: Vec<_> = arena.iter().map(|(k, v)| (k, v.clone())).collect();
+        assert_eq!(items, [(c, "c".to_string()), (b, "b1!".to_string())]);
+
+        arena.retain(|k, _| k == b);
+        assert_eq!(arena.keys().collect::<Vec<_>>(), [b]);
+        arena.clear();
+        assert!(arena.is_empty());
+        assert!(!arena.contains_key(b));
+    }
+
+    #[test]
+    fn test_gen_arena_retire() {
+        let mut arena = GenArena::<u32>::new();
+        let a = arena.insert(1);
+        // 世代値が一巡する直前まで進めます。
+        arena.slots[0].version = u32::MAX - 1;
+        let a = DefaultKey::from(RawKey::new(a.raw().index(), u32::MAX - 1));
+        assert_eq!(arena.remove(a), Some(1));
+
+        // 退役したスロットは再利用されません。
+        let b = arena.insert(2);
+        assert_eq!(b.raw().index(), 1);
+        assert!(!arena.contains_key(DefaultKey::from(RawKey::new(0, u32::MAX))));
+        assert_eq!(arena.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_gen_arena_serde() {
+        let mut arena = GenArena::<u32>::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        arena.remove(b);
+
+        let json = serde_json::to_string(&arena).unwrap();
+        let mut de: GenArena<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(de.len(), 2);
+        assert_eq!(de[a], 1);
+        assert_eq!(de[c], 3);
+        assert!(!de.contains_key(b));
+        // 空きスロットも復元されます。
+        let d = de.insert(4);
+        assert_eq!(d.raw().index(), b.raw().index());
+        assert_ne!(d, b);
+
+        // キーも文字列化できます。
+        let key: DefaultKey = serde_json::from_str(&serde_json::to_string(&c).unwrap()).unwrap();
+        assert_eq!(key, c);
+
+        assert!(serde_json::from_str::<GenArena<u32>>(&format!("[[{}, 1]]", u32::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_dense_arena() {
+        let mut arena = DenseArena::<&str, TestKey>::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        let c = arena.insert("c");
+        assert_eq!(arena.values(), ["a", "b", "c"]);
+
+        // 末尾の値を詰めても、キーは同じ値を指します。
+        assert_eq!(arena.remove(a), Some("a"));
+        assert_eq!(arena.values(), ["c", "b"]);
+        assert_eq!(arena[c], "c");
+        assert_eq!(arena[b], "b");
+        assert_eq!(arena.keys(), [c, b]);
+        assert_eq!(arena.get(a), None);
+
+        let d = arena.insert("d");
+        assert_eq!(d.raw().index(), a.raw().index());
+        arena.values_mut()[0] = "C";
+        assert_eq!(arena.iter().collect::<Vec<_>>(), [(c, &"C"), (b, &"b"), (d, &"d")]);
+
+        arena.retain(|_, v| *v != "b");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena[d], "d");
+        assert!(!arena.contains_key(b));
+    }
+
+    #[test]
+    fn test_dense_arena_serde() {
+        let mut arena = DenseArena::<u32>::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        arena.remove(a);
+
+        let json = serde_json::to_string(&arena).unwrap();
+        let mut de: DenseArena<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(de.values(), arena.values());
+        assert_eq!(de[b], 2);
+        assert_eq!(de[c], 3);
+        assert!(!de.contains_key(a));
+        let d = de.insert(4);
+        assert_eq!(d.raw().index(), a.raw().index());
+
+        // 世代値の合わない値は拒否します。
+        let bad = r#"{"versions":[0],"entries":[[{"index":0,"version":1},5]]}"#;
+        assert!(serde_json::from_str::<DenseArena<u32>>(bad).is_err());
+        let bad = r#"{"versions":[0],"entries":[[{"index":0,"version":0},5],[{"index":0,"version":0},6]]}"#;
+        assert!(serde_json::from_str::<DenseArena<u32>>(bad).is_err());
+    }
+}
+
+/// 型を区別しないキーです。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RawKey {
+    index: u32,   // スロットの添え字です。
+    version: u32, // スロットの世代値です。
+}
+impl RawKey {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `index` - スロットの添え字です。
+    /// * `version` - スロットの世代値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// キーです。
+    /// 
+    #[inline]
+    pub const fn new(index: u32, version: u32) -> RawKey {
+        RawKey { index, version }
+    }
+
+    /// スロットの添え字を取得します。
+    #[inline]
+    pub const fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// スロットの世代値を取得します。
+    #[inline]
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+}
+impl Display for RawKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.version)
+    }
+}
+
+/// アリーナのキーです。
+/// 
+/// `new_key_type!`で用途毎に型を分けて定義します。
+/// 
+pub trait Key: Copy + Eq + Hash + Debug + From<RawKey> {
+    /// 型を区別しないキーを取得します。
+    fn raw(&self) -> RawKey;
+}
+
+/// 用途毎のキー型を定義します。
+/// 
+/// 定義した型は`Key`、`Serialize`、`Deserialize`を実装します。
+/// 
+/// ```
+/// cwago_utility::new_key_type! {
+///     /// UIノードのキーです。
+///     pub struct NodeKey;
+/// }
+/// ```
+#[macro_export]
+macro_rules! new_key_type {
+    ($($(#[$meta:meta])* $vis:vis struct $name:ident;)*) => {$(
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[repr(transparent)]
+        $vis struct $name($crate::arena::RawKey);
+        impl ::std::convert::From<$crate::arena::RawKey> for $name {
+            #[inline]
+            fn from(raw: $crate::arena::RawKey) -> Self {
+                $name(raw)
+            }
+        }
+        impl $crate::arena::Key for $name {
+            #[inline]
+            fn raw(&self) -> $crate::arena::RawKey {
+                self.0
+            }
+        }
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                ::std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+        impl $crate::arena::__serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: $crate::arena::__serde::Serializer
+            {
+                $crate::arena::__serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+        impl<'de> $crate::arena::__serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where D: $crate::arena::__serde::Deserializer<'de>
+            {
+                <$crate::arena::RawKey as $crate::arena::__serde::Deserialize>::deserialize(deserializer).map($name)
+            }
+        }
+    )*};
+}
+
+new_key_type! {
+    /// 型を指定しない場合のキーです。
+    pub struct DefaultKey;
+}
+
+// 空きリストの終端です。
+const NONE: u32 = u32::MAX;
+
+// 退役したスロットの世代値です。
+const RETIRED: u32 = u32::MAX;
+
+// 削除したスロットの次の世代値を取得します。
+// 使用中のスロットは退役していないため溢れず、一巡する直前で`RETIRED`になります。
+#[inline]
+fn next_version(version: u32) -> u32 {
+    debug_assert!(version != RETIRED);
+    version + 1
+}
+
+// 次に追加するスロットの添え字を取得します。
+#[inline]
+fn slot_index(len: usize) -> u32 {
+    assert!(len < NONE as usize, "{}", msg!(ARENA_FULL, max = NONE));
+    len as u32
+}
+
+/// スロットに値を直接格納するアリーナです。
+/// 
+/// 削除しても他の値は移動しません。
+/// 
+#[derive(Clone)]
+pub struct GenArena<T, K: Key = DefaultKey> {
+    slots: Vec<Slot<T>>,    // スロットです。
+    free: u32,              // 空きスロットの先頭です。
+    len: usize,             // 値の数です。
+    _key: PhantomData<K>,   // キーの型です。
+}
+
+// スロットです。
+#[derive(Clone)]
+struct Slot<T> {
+    version: u32,      // 世代値です。
+    entry: Entry<T>,   // 内容です。
+}
+
+// スロットの内容です。
+#[derive(Clone)]
+enum Entry<T> {
+    Occupied(T),   // 値です。
+    Vacant(u32),   // 次の空きスロットです。
+}
+
+impl<T, K: Key> GenArena<T, K> {
+    /// 作成します。
+    pub const fn new() -> Self {
+        GenArena { slots: Vec::new(), free: NONE, len: 0, _key: PhantomData }
+    }
+
+    /// 容量を指定して作成します。
+    pub fn with_capacity(capacity: usize) -> Self {
+        GenArena { slots: Vec::with_capacity(capacity), free: NONE, len: 0, _key: PhantomData }
+    }
+
+    /// 値の数を取得します。
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 空かを取得します。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 値を追加できるかを取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 空きスロットが無く、スロット数が`u32::MAX`に達している場合はfalseです。
+    /// 
+    #[inline]
+    pub fn can_insert(&self) -> bool {
+        self.free != NONE || self.slots.len() < NONE as usize
+    }
+
+    /// 値を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `value` - 値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値を指すキーです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// スロット数が`u32::MAX`に達した場合に異常終了します。
+    /// 
+    pub fn insert(&mut self, value: T) -> K {
+        self.insert_with_key(|_| value)
+    }
+
+    /// キーを使って作成した値を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 値を指すことになるキーから値を作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値を指すキーです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// スロット数が`u32::MAX`に達した場合に異常終了します。
+    /// 
+    pub fn insert_with_key(&mut self, f: impl FnOnce(K) -> T) -> K {
+        if self.free != NONE {
+            let index = self.free;
+            let slot = &mut self.slots[index as usize];
+            let key = K::from(RawKey::new(index, slot.version));
+            if let Entry::Vacant(next) = slot.entry {
+                self.free = next;
+            }
+            slot.entry = Entry::Occupied(f(key));
+            self.len += 1;
+            return key;
+        }
+
+        let index = slot_index(self.slots.len());
+        let key = K::from(RawKey::new(index, 0));
+        self.slots.push(Slot { version: 0, entry: Entry::Occupied(f(key)) });
+        self.len += 1;
+        key
+    }
+
+    /// 値を削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した値、キーが無効な場合はNoneです。
+    /// 
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        let raw = key.raw();
+        let slot = self.slots.get_mut(raw.index as usize)?;
+        if slot.version != raw.version || !matches!(slot.entry, Entry::Occupied(_)) {
+            return None;
+        }
+
+        slot.version = next_version(slot.version);
+        // 退役したスロットは空きリストに繋ぎません。
+        let entry = if slot.version == RETIRED {
+            mem::replace(&mut slot.entry, Entry::Vacant(NONE))
+        } else {
+            let entry = mem::replace(&mut slot.entry, Entry::Vacant(self.free));
+            self.free = raw.index;
+            entry
+        };
+        self.len -= 1;
+        match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// キーが有効かを取得します。
+    #[inline]
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// 値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、キーが無効な場合はNoneです。
+    /// 
+    pub fn get(&self, key: K) -> Option<&T> {
+        let raw = key.raw();
+        match self.slots.get(raw.index as usize) {
+            Some(Slot { version, entry: Entry::Occupied(value) }) if *version == raw.version => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 可変な値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、キーが無効な場合はNoneです。
+    /// 
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        let raw = key.raw();
+        match self.slots.get_mut(raw.index as usize) {
+            Some(Slot { version, entry: Entry::Occupied(value) }) if *version == raw.version => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 条件を満たさない値を削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 残す場合にtrueを返す条件です。
+    /// 
+    pub fn retain(&mut self, mut f: impl FnMut(K, &mut T) -> bool) {
+        let keys: Vec<K> = self.iter_mut()
+            .filter_map(|(k, v)| (!f(k, v)).then_some(k))
+            .collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// すべての値を削除します。
+    /// 
+    /// スロットは空きとして残すため、既存のキーは無効になります。
+    /// 
+    pub fn clear(&mut self) {
+        let keys: Vec<K> = self.keys().collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// キーと値を添え字の順に走査します。
+    pub fn iter(&self) -> Iter<'_, T, K> {
+        Iter { slots: self.slots.iter().enumerate(), remaining: self.len, _key: PhantomData }
+    }
+
+    /// キーと可変な値を添え字の順に走査します。
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, K> {
+        IterMut { slots: self.slots.iter_mut().enumerate(), remaining: self.len, _key: PhantomData }
+    }
+
+    /// キーを添え字の順に走査します。
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// 値を添え字の順に走査します。
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// 可変な値を添え字の順に走査します。
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.iter_mut().map(|(_, v)| v)
+    }
+}
+impl<T, K: Key> Default for GenArena<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: Debug, K: Key> Debug for GenArena<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+impl<T, K: Key> Index<K> for GenArena<T, K> {
+    type Output = T;
+
+    fn index(&self, key: K) -> &T {
+        self.get(key).unwrap_or_else(|| panic!("{}", msg!(ARENA_INVALID_KEY, key = format!("{:?}", key))))
+    }
+}
+impl<T, K: Key> IndexMut<K> for GenArena<T, K> {
+    fn index_mut(&mut self, key: K) -> &mut T {
+        self.get_mut(key).unwrap_or_else(|| panic!("{}", msg!(ARENA_INVALID_KEY, key = format!("{:?}", key))))
+    }
+}
+impl<'a, T, K: Key> IntoIterator for &'a GenArena<T, K> {
+    type Item = (K, &'a T);
+    type IntoIter = Iter<'a, T, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, T, K: Key> IntoIterator for &'a mut GenArena<T, K> {
+    type Item = (K, &'a mut T);
+    type IntoIter = IterMut<'a, T, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// スロット毎に`(世代値, 値)`の列として保存するため、読み込み後もキーはそのまま使えます。
+impl<T: Serialize, K: Key> Serialize for GenArena<T, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.slots.iter().map(|slot| {
+            let value = match &slot.entry {
+                Entry::Occupied(value) => Some(value),
+                Entry::Vacant(_) => None,
+            };
+            (slot.version, value)
+        }))
+    }
+}
+impl<'de, T: Deserialize<'de>, K: Key> Deserialize<'de> for GenArena<T, K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let slots = Vec::<(u32, Option<T>)>::deserialize(deserializer)?;
+        if slots.len() >= NONE as usize {
+            return Err(D::Error::custom(msg!(ARENA_INVALID_SLOT, index = NONE)));
+        }
+
+        let mut arena = GenArena::with_capacity(slots.len());
+        // 空きリストは添え字の小さい順に再利用されるよう、後ろから積みます。
+        let mut free = Vec::new();
+        for (index, (version, value)) in slots.into_iter().enumerate() {
+            let entry = match value {
+                Some(_) if version == RETIRED => {
+                    return Err(D::Error::custom(msg!(ARENA_INVALID_SLOT, index = index)));
+                },
+                Some(value) => {
+                    arena.len += 1;
+                    Entry::Occupied(value)
+                },
+                None => {
+                    if version != RETIRED {
+                        free.push(index as u32);
+                    }
+                    Entry::Vacant(NONE)
+                },
+            };
+            arena.slots.push(Slot { version, entry });
+        }
+        for index in free.into_iter().rev() {
+            arena.slots[index as usize].entry = Entry::Vacant(arena.free);
+            arena.free = index;
+        }
+        Ok(arena)
+    }
+}
+
+/// `GenArena`のキーと値のイテレータです。
+pub struct Iter<'a, T, K> {
+    slots: std::iter::Enumerate<slice::Iter<'a, Slot<T>>>, // 走査中のスロットです。
+    remaining: usize,                                       // 残りの値の数です。
+    _key: PhantomData<K>,                                   // キーの型です。
+}
+impl<'a, T, K: Key> Iterator for Iter<'a, T, K> {
+    type Item = (K, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, slot) in self.slots.by_ref() {
+            if let Entry::Occupied(value) = &slot.entry {
+                self.remaining -= 1;
+                return Some((K::from(RawKey::new(index as u32, slot.version)), value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<T, K: Key> ExactSizeIterator for Iter<'_, T, K> {}
+impl<T, K: Key> FusedIterator for Iter<'_, T, K> {}
+
+/// `GenArena`のキーと可変な値のイテレータです。
+pub struct IterMut<'a, T, K> {
+    slots: std::iter::Enumerate<slice::IterMut<'a, Slot<T>>>, // 走査中のスロットです。
+    remaining: usize,                                          // 残りの値の数です。
+    _key: PhantomData<K>,                                      // キーの型です。
+}
+impl<'a, T, K: Key> Iterator for IterMut<'a, T, K> {
+    type Item = (K, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, slot) in self.slots.by_ref() {
+            if let Entry::Occupied(value) = &mut slot.entry {
+                self.remaining -= 1;
+                return Some((K::from(RawKey::new(index as u32, slot.version)), value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<T, K: Key> ExactSizeIterator for IterMut<'_, T, K> {}
+impl<T, K: Key> FusedIterator for IterMut<'_, T, K> {}
+
+/// 値を連続した配列に詰めて格納するアリーナです。
+/// 
+/// 走査はスライスと同じ速さですが、削除すると末尾の値が削除した位置へ移動します。
+/// 
+#[derive(Clone)]
+pub struct DenseArena<T, K: Key = DefaultKey> {
+    slots: Vec<DenseSlot>,   // スロットです。
+    keys: Vec<K>,            // 値と同じ順のキーです。
+    values: Vec<T>,          // 値です。
+    free: u32,               // 空きスロットの先頭です。
+}
+
+// 値の位置を指すスロットです。
+#[derive(Debug, Clone, Copy)]
+struct DenseSlot {
+    version: u32,    // 世代値です。
+    index: u32,      // 使用中は値の添え字、空きの場合は次の空きスロットです。
+    occupied: bool,  // 使用中かです。
+}
+
+impl<T, K: Key> DenseArena<T, K> {
+    /// 作成します。
+    pub const fn new() -> Self {
+        DenseArena { slots: Vec::new(), keys: Vec::new(), values: Vec::new(), free: NONE }
+    }
+
+    /// 容量を指定して作成します。
+    pub fn with_capacity(capacity: usize) -> Self {
+        DenseArena {
+            slots: Vec::with_capacity(capacity),
+            keys: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            free: NONE,
+        }
+    }
+
+    /// 値の数を取得します。
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// 空かを取得します。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// 値を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `value` - 値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値を指すキーです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// スロット数が`u32::MAX`に達した場合に異常終了します。
+    /// 
+    pub fn insert(&mut self, value: T) -> K {
+        let dense = self.values.len() as u32;
+        let key = if self.free != NONE {
+            let index = self.free;
+            let slot = &mut self.slots[index as usize];
+            self.free = slot.index;
+            slot.index = dense;
+            slot.occupied = true;
+            K::from(RawKey::new(index, slot.version))
+        } else {
+            let index = slot_index(self.slots.len());
+            self.slots.push(DenseSlot { version: 0, index: dense, occupied: true });
+            K::from(RawKey::new(index, 0))
+        };
+        self.keys.push(key);
+        self.values.push(value);
+        key
+    }
+
+    /// 値を削除します。
+    /// 
+    /// 末尾の値が削除した位置へ移動します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 削除した値、キーが無効な場合はNoneです。
+    /// 
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        let raw = key.raw();
+        let dense = self.dense_index(raw)?;
+
+        let slot = &mut self.slots[raw.index as usize];
+        slot.version = next_version(slot.version);
+        slot.occupied = false;
+        if slot.version != RETIRED {
+            slot.index = self.free;
+            self.free = raw.index;
+        }
+
+        self.keys.swap_remove(dense);
+        let value = self.values.swap_remove(dense);
+        if let Some(moved) = self.keys.get(dense) {
+            self.slots[moved.raw().index as usize].index = dense as u32;
+        }
+        Some(value)
+    }
+
+    // 値の添え字を取得します。
+    #[inline]
+    fn dense_index(&self, raw: RawKey) -> Option<usize> {
+        match self.slots.get(raw.index as usize) {
+            Some(slot) if slot.occupied && slot.version == raw.version => Some(slot.index as usize),
+            _ => None,
+        }
+    }
+
+    /// キーが有効かを取得します。
+    #[inline]
+    pub fn contains_key(&self, key: K) -> bool {
+        self.dense_index(key.raw()).is_some()
+    }
+
+    /// 値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、キーが無効な場合はNoneです。
+    /// 
+    #[inline]
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.dense_index(key.raw()).map(|i| &self.values[i])
+    }
+
+    /// 可変な値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 値を指すキーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値、キーが無効な場合はNoneです。
+    /// 
+    #[inline]
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.dense_index(key.raw()).map(|i| &mut self.values[i])
+    }
+
+    /// 条件を満たさない値を削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 残す場合にtrueを返す条件です。
+    /// 
+    pub fn retain(&mut self, mut f: impl FnMut(K, &mut T) -> bool) {
+        let mut i = 0;
+        while i < self.values.len() {
+            let key = self.keys[i];
+            if f(key, &mut self.values[i]) {
+                i += 1;
+            } else {
+                // 末尾の値が移動してくるため、添え字は進めません。
+                self.remove(key);
+            }
+        }
+    }
+
+    /// すべての値を削除します。
+    /// 
+    /// スロットは空きとして残すため、既存のキーは無効になります。
+    /// 
+    pub fn clear(&mut self) {
+        while let Some(key) = self.keys.last().copied() {
+            self.remove(key);
+        }
+    }
+
+    /// 値と同じ順のキーを取得します。
+    #[inline]
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// 値を取得します。
+    #[inline]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// 可変な値を取得します。
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// キーと値を格納順に走査します。
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (K, &T)> + '_ {
+        self.keys.iter().copied().zip(self.values.iter())
+    }
+
+    /// キーと可変な値を格納順に走査します。
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = (K, &mut T)> + '_ {
+        self.keys.iter().copied().zip(self.values.iter_mut())
+    }
+}
+impl<T, K: Key> Default for DenseArena<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: Debug, K: Key> Debug for DenseArena<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+impl<T, K: Key> Index<K> for DenseArena<T, K> {
+    type Output = T;
+
+    fn index(&self, key: K) -> &T {
+        self.get(key).unwrap_or_else(|| panic!("{}", msg!(ARENA_INVALID_KEY, key = format!("{:?}", key))))
+    }
+}
+impl<T, K: Key> IndexMut<K> for DenseArena<T, K> {
+    fn index_mut(&mut self, key: K) -> &mut T {
+        self.get_mut(key).unwrap_or_else(|| panic!("{}", msg!(ARENA_INVALID_KEY, key = format!("{:?}", key))))
+    }
+}
+
+// DenseArenaの保存形式です。
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "V: Serialize, K: Serialize", deserialize = "V: Deserialize<'de>, K: Deserialize<'de>"))]
+struct DenseRepr<K, V> {
+    versions: Vec<u32>,     // スロットの世代値です。
+    entries: Vec<(K, V)>,   // 格納順のキーと値です。
+}
+
+/// スロットの世代値とキー付きの値を保存するため、読み込み後もキーと格納順はそのまま使えます。
+impl<T: Serialize, K: Key> Serialize for DenseArena<T, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DenseRepr {
+            versions: self.slots.iter().map(|slot| slot.version).collect(),
+            entries: self.keys.iter().map(|k| k.raw()).zip(self.values.iter()).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+impl<'de, T: Deserialize<'de>, K: Key> Deserialize<'de> for DenseArena<T, K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DenseRepr::<RawKey, T>::deserialize(deserializer)?;
+        if repr.versions.len() >= NONE as usize {
+            return Err(D::Error::custom(msg!(ARENA_INVALID_SLOT, index = NONE)));
+        }
+
+        let mut arena = DenseArena::with_capacity(repr.versions.len());
+        arena.slots = repr.versions.into_iter()
+            .map(|version| DenseSlot { version, index: NONE, occupied: false })
+            .collect();
+        for (dense, (raw, value)) in repr.entries.into_iter().enumerate() {
+            match arena.slots.get_mut(raw.index as usize) {
+                Some(slot) if !slot.occupied && slot.version == raw.version && slot.version != RETIRED => {
+                    slot.occupied = true;
+                    slot.index = dense as u32;
+                },
+                _ => return Err(D::Error::custom(msg!(ARENA_INVALID_ENTRY, key = raw))),
+            }
+            arena.keys.push(K::from(raw));
+            arena.values.push(value);
+        }
+
+        // 空きリストは添え字の小さい順に再利用されるよう、後ろから積みます。
+        for index in (0..arena.slots.len()).rev() {
+            let slot = &mut arena.slots[index];
+            if !slot.occupied && slot.version != RETIRED {
+                slot.index = arena.free;
+                arena.free = index as u32;
+            }
+        }
+        Ok(arena)
+    }
+}
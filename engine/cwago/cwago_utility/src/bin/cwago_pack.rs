@@ -0,0 +1,138 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/bin/cwago_pack.rs
+// (C) 2023 CwagoCommunity.
+//
+//! ディレクトリからパックを作成するコマンドです。
+//! 
+//! ```text
+//! cwago_pack <入力ディレクトリ> <出力ファイル> [--align <バイト数>] [--no-compress]
+//! cwago_pack --list <パック>
+//! ```
+// =========================
+
+use std::{
+    env,
+    fs::{
+        self,
+        File
+    },
+    io::BufWriter,
+    path::{
+        Path,
+        PathBuf
+    },
+    process::ExitCode
+};
+
+use cwago_utility::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result
+    },
+    messages::{
+        PACK_CLI_NOT_UTF8,
+        PACK_CLI_PACKED,
+        PACK_CLI_USAGE
+    },
+    msg,
+    pack::{
+        Compression,
+        PackReader,
+        PackWriter
+    }
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+// 引数に従って実行します。
+fn run(args: &[String]) -> Result<()> {
+    if let [flag, pack] = args {
+        if flag == "--list" {
+            return list(Path::new(pack));
+        }
+    }
+
+    let mut paths = Vec::new();
+    let mut alignment = None;
+    let mut compression = Compression::Lz4;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--align" => {
+                let value = iter.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?;
+                alignment = Some(value);
+            },
+            "--no-compress" => compression = Compression::None,
+            _ if arg.starts_with("--") => return Err(usage()),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = paths.as_slice() else {
+        return Err(usage());
+    };
+    pack(input, output, alignment, compression)
+}
+
+// パックを作成します。
+fn pack(input: &Path, output: &Path, alignment: Option<u32>, compression: Compression) -> Result<()> {
+    let mut files = Vec::new();
+    collect(input, String::new(), &mut files)?;
+    files.sort();
+
+    let mut writer = PackWriter::new(BufWriter::new(File::create(output)?))?;
+    if let Some(alignment) = alignment {
+        writer.set_alignment(alignment)?;
+    }
+    for path in &files {
+        let data = fs::read(input.join(path))?;
+        writer.add(path, &data, compression)?;
+    }
+    writer.finish()?;
+    println!("{}", msg!(PACK_CLI_PACKED, count = files.len(), output = output.display()));
+    Ok(())
+}
+
+// ディレクトリ内のファイルを'/'区切りの相対パスで集めます。
+fn collect(dir: &Path, prefix: String, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string()
+            .map_err(|name| CwagoError::new(ErrorKind::InvalidArgument, msg!(PACK_CLI_NOT_UTF8, name = format!("{:?}", name))))?;
+        let path = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            collect(&entry.path(), format!("{}/", path), files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// パックの項目を表示します。
+fn list(pack: &Path) -> Result<()> {
+    let reader = PackReader::open(pack)?;
+    let mut entries: Vec<_> = reader.entries().collect();
+    entries.sort_by_key(|e| e.path());
+    for entry in entries {
+        println!("{:>10} {:>10} {:?} {:016x} {}", entry.size(), entry.stored_size(), entry.compression(), entry.hash(), entry.path());
+    }
+    Ok(())
+}
+
+// 使い方のエラーを作成します。
+fn usage() -> CwagoError {
+    CwagoError::new(ErrorKind::InvalidArgument, msg!(PACK_CLI_USAGE))
+}
@@ -0,0 +1,856 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/bitset.rs
+// (C) 2023 CwagoCommunity.
+//
+//! ビット集合を提供します。
+//! 
+//! * `FixedBitSet` - 固定長でインラインに持つ集合です。アーキタイプのマスクに使います。
+//! * `BitSet` - 必要に応じて伸びる集合です。
+//! * `HierBitSet` - 空のワードを上位の階層で読み飛ばす、疎な集合向けの集合です。
+//! 
+//! いずれも末尾の0のワードを除いたワード列でシリアライズするため、互いに読み替えられます。
+//! 等価性とハッシュも末尾の0のワードを無視します。
+//! 
+//! ```
+//! use cwago_utility::bitset::FixedBitSet;
+//! 
+//! let archetype: FixedBitSet<2> = [1, 3, 70].into_iter().collect();
+//! let query: FixedBitSet<2> = [3, 70].into_iter().collect();
+//! assert!(query.is_subset(&archetype));
+//! assert_eq!(archetype.iter().collect::<Vec<_>>(), [1, 3, 70]);
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Debug
+    },
+    hash::{
+        Hash,
+        Hasher
+    }
+};
+
+use serde::{
+    de::{
+        self,
+        SeqAccess,
+        Visitor
+    },
+    ser::SerializeSeq,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+
+use crate::{
+    messages::BITSET_OUT_OF_RANGE,
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{
+        FxHashSet,
+        FxHasher
+    };
+
+    fn fx<T: Hash>(value: &T) -> u64 {
+        let mut hasher = FxHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_fixed() {
+        let mut a = FixedBitSet::<2>::new();
+        assert!(a.is_empty());
+        assert!(a.insert(0));
+        assert!(!a.insert(0), "2回目の追加はfalseを返す必要があります。");
+        a.insert(63);
+        a.insert(64);
+        a.insert(127);
+        assert_eq!(a.len(), 4);
+        assert!(a.contains(64));
+        assert!(!a.contains(65));
+        assert!(!a.contains(1000));
+        assert_eq!(a.iter().collect::<Vec<_>>(), [0, 63, 64, 127]);
+        assert!(a.remove(63));
+        assert!(!a.remove(63));
+
+        let b: FixedBitSet<2> = [0, 64].into_iter().collect();
+        assert!(b.is_subset(&a));
+        assert!(a.is_superset(&b));
+        assert!(!a.is_subset(&b));
+        assert!(a.intersects(&b));
+        assert!(b.difference(&a).is_empty());
+        assert_eq!(a.difference(&b).iter().collect::<Vec<_>>(), [127]);
+        assert_eq!(a.intersection(&b), b);
+        assert!(a.difference(&b).is_disjoint(&b));
+        assert_eq!(b.union(&[5].into_iter().collect()).iter().collect::<Vec<_>>(), [0, 5, 64]);
+        assert_eq!(FixedBitSet::<2>::CAPACITY, 128);
+    }
+
+    #[test]
+    fn test_growable() {
+        let mut a = BitSet::new();
+        a.insert(1000);
+        a.insert(3);
+        assert_eq!(a.iter().collect::<Vec<_>>(), [3, 1000]);
+        assert!(!a.contains(5000));
+        assert!(!a.remove(5000));
+
+        let mut b = BitSet::with_capacity(64);
+        b.insert(3);
+        assert!(b.is_subset(&a));
+        assert!(!a.is_subset(&b));
+        b.union_with(&a);
+        assert_eq!(b, a);
+
+        // 末尾の0のワードは比較とハッシュに影響しません。
+        a.remove(1000);
+        b.intersect_with(&[3, 4].into_iter().collect());
+        assert_eq!(a, b);
+        assert_eq!(fx(&a), fx(&[3].into_iter().collect::<BitSet>()));
+        let set: FxHashSet<BitSet> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_hier() {
+        let mut a = HierBitSet::new();
+        let bits = [0, 1, 64, 4095, 4096, 300_000];
+        for bit in bits {
+            assert!(a.insert(bit));
+        }
+        assert_eq!(a.iter().collect::<Vec<_>>(), bits);
+        assert_eq!(a.len(), bits.len());
+        assert!(a.remove(4096));
+        assert!(a.remove(300_000));
+        assert!(!a.remove(300_000));
+        assert_eq!(a.iter().collect::<Vec<_>>(), [0, 1, 64, 4095]);
+        assert!(a.contains(4095));
+        assert!(!a.contains(4096));
+
+        let b: HierBitSet = [1, 4095].into_iter().collect();
+        assert!(b.is_subset(&a));
+        assert!(a.intersects(&b));
+        assert!(!b.is_disjoint(&a));
+        assert_eq!(a.intersection(&b).collect::<Vec<_>>(), [1, 4095]);
+        a.clear();
+        assert!(a.is_empty());
+        assert_eq!(a.iter().next(), None);
+    }
+
+    #[test]
+    fn test_serde() {
+        let fixed: FixedBitSet<4> = [1, 65].into_iter().collect();
+        let json = serde_json::to_string(&fixed).unwrap();
+        assert_eq!(json, "[2,2]");
+        let growable: BitSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(growable.iter().collect::<Vec<_>>(), [1, 65]);
+        let hier: HierBitSet = serde_json::from_str(&serde_json::to_string(&growable).unwrap()).unwrap();
+        assert_eq!(hier.iter().collect::<Vec<_>>(), [1, 65]);
+        assert_eq!(serde_json::from_str::<FixedBitSet<4>>(&serde_json::to_string(&hier).unwrap()).unwrap(), fixed);
+
+        assert!(serde_json::from_str::<FixedBitSet<1>>("[1,1]").is_err(), "容量を超えるビットは拒否する必要があります。");
+        assert_eq!(serde_json::from_str::<FixedBitSet<1>>("[1,0]").unwrap().iter().collect::<Vec<_>>(), [0]);
+    }
+}
+
+// ワードのビット数です。
+const BITS: usize = u64::BITS as usize;
+
+// ワードの位置とマスクです。
+#[inline]
+fn locate(bit: usize) -> (usize, u64) {
+    (bit / BITS, 1 << (bit % BITS))
+}
+
+// 末尾の0のワードを除いたワード列です。
+fn trimmed(words: &[u64]) -> &[u64] {
+    let len = words.iter().rposition(|w| *w != 0).map_or(0, |i| i + 1);
+    &words[..len]
+}
+
+// aがbの部分集合かを取得します。
+fn is_subset(a: &[u64], b: &[u64]) -> bool {
+    a.iter().enumerate().all(|(i, w)| w & !b.get(i).copied().unwrap_or(0) == 0)
+}
+
+// aとbに共通の要素があるかを取得します。
+fn intersects(a: &[u64], b: &[u64]) -> bool {
+    a.iter().zip(b).any(|(a, b)| a & b != 0)
+}
+
+// 要素数を取得します。
+fn count(words: &[u64]) -> usize {
+    words.iter().map(|w| w.count_ones() as usize).sum()
+}
+
+// 位置`index`のワードの立っているビットを走査します。
+fn word_bits(index: usize, mut word: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (word != 0).then(|| {
+            let bit = word.trailing_zeros() as usize;
+            word &= word - 1;
+            index * BITS + bit
+        })
+    })
+}
+
+// ワード列をシリアライズします。
+fn serialize_words<S: Serializer>(words: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+    let words = trimmed(words);
+    let mut seq = serializer.serialize_seq(Some(words.len()))?;
+    for word in words {
+        seq.serialize_element(word)?;
+    }
+    seq.end()
+}
+
+// ワード列をデシリアライズします。
+fn deserialize_words<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+    struct WordsVisitor;
+    impl<'de> Visitor<'de> for WordsVisitor {
+        type Value = Vec<u64>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a sequence of u64 words")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u64>, A::Error> {
+            let mut words = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(word) = seq.next_element()? {
+                words.push(word);
+            }
+            Ok(words)
+        }
+    }
+    deserializer.deserialize_seq(WordsVisitor)
+}
+
+/// 立っているビットの位置を昇順に走査します。
+#[derive(Clone)]
+pub struct Ones<'a> {
+    words: &'a [u64], // ワード列です。
+    next: usize,      // 次に読み込むワードの位置です。
+    base: usize,      // currentの先頭のビットの位置です。
+    current: u64,     // 走査中のワードの残りです。
+}
+impl<'a> Ones<'a> {
+    fn new(words: &'a [u64]) -> Ones<'a> {
+        Ones { words, next: 0, base: 0, current: 0 }
+    }
+}
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.current = *self.words.get(self.next)?;
+            self.base = self.next * BITS;
+            self.next += 1;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.base + bit)
+    }
+}
+impl Debug for Ones<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// 固定長のビット集合です。
+/// 
+/// `WORDS * 64`個のビットをインラインに持ち、確保を行いません。
+/// 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedBitSet<const WORDS: usize> {
+    words: [u64; WORDS], // ビットです。
+}
+impl<const WORDS: usize> FixedBitSet<WORDS> {
+    /// 格納できるビット数です。
+    pub const CAPACITY: usize = WORDS * BITS;
+
+    /// 空の集合を作成します。
+    pub const fn new() -> Self {
+        FixedBitSet { words: [0; WORDS] }
+    }
+
+    /// ビットを立てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 新たに立てた場合はtrueです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `bit`が`CAPACITY`以上の場合に異常終了します。
+    /// 
+    #[inline]
+    pub fn insert(&mut self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        let word = &mut self.words[word];
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    /// ビットを下ろします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 立っていた場合はtrueです。
+    /// 
+    #[inline]
+    pub fn remove(&mut self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        match self.words.get_mut(word) {
+            Some(word) => {
+                let removed = *word & mask != 0;
+                *word &= !mask;
+                removed
+            },
+            None => false,
+        }
+    }
+
+    /// ビットが立っているかを取得します。
+    #[inline]
+    pub fn contains(&self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        self.words.get(word).is_some_and(|w| w & mask != 0)
+    }
+
+    /// すべてのビットを下ろします。
+    pub fn clear(&mut self) {
+        self.words = [0; WORDS];
+    }
+
+    /// 立っているビットの数を取得します。
+    pub fn len(&self) -> usize {
+        count(&self.words)
+    }
+
+    /// 空かを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    /// 他の集合の部分集合かを取得します。
+    #[inline]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.words.iter().zip(&other.words).all(|(a, b)| a & !b == 0)
+    }
+
+    /// 他の集合を含むかを取得します。
+    #[inline]
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// 他の集合と共通の要素があるかを取得します。
+    #[inline]
+    pub fn intersects(&self, other: &Self) -> bool {
+        intersects(&self.words, &other.words)
+    }
+
+    /// 他の集合と共通の要素が無いかを取得します。
+    #[inline]
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        !self.intersects(other)
+    }
+
+    /// 和集合を取得します。
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+
+    /// 積集合を取得します。
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// 差集合を取得します。
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    // ワード毎に演算します。
+    #[inline]
+    fn zip(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        FixedBitSet { words: std::array::from_fn(|i| f(self.words[i], other.words[i])) }
+    }
+
+    /// 立っているビットを昇順に走査します。
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.words)
+    }
+
+    /// ワード列を取得します。
+    pub fn as_words(&self) -> &[u64; WORDS] {
+        &self.words
+    }
+}
+impl<const WORDS: usize> Default for FixedBitSet<WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<const WORDS: usize> FromIterator<usize> for FixedBitSet<WORDS> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        iter.into_iter().for_each(|bit| { set.insert(bit); });
+        set
+    }
+}
+impl<'a, const WORDS: usize> IntoIterator for &'a FixedBitSet<WORDS> {
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+
+    fn into_iter(self) -> Ones<'a> {
+        self.iter()
+    }
+}
+impl<const WORDS: usize> Debug for FixedBitSet<WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+impl<const WORDS: usize> Serialize for FixedBitSet<WORDS> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_words(&self.words, serializer)
+    }
+}
+impl<'de, const WORDS: usize> Deserialize<'de> for FixedBitSet<WORDS> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let words = deserialize_words(deserializer)?;
+        let words = trimmed(&words);
+        if words.len() > WORDS {
+            return Err(de::Error::custom(msg!(BITSET_OUT_OF_RANGE, capacity = Self::CAPACITY)));
+        }
+        let mut set = Self::new();
+        set.words[..words.len()].copy_from_slice(words);
+        Ok(set)
+    }
+}
+
+/// 必要に応じて伸びるビット集合です。
+#[derive(Clone, Default)]
+pub struct BitSet {
+    words: Vec<u64>, // ビットです。末尾に0のワードを含む場合があります。
+}
+impl BitSet {
+    /// 空の集合を作成します。
+    pub const fn new() -> BitSet {
+        BitSet { words: Vec::new() }
+    }
+
+    /// 指定のビット数を確保した空の集合を作成します。
+    pub fn with_capacity(bits: usize) -> BitSet {
+        BitSet { words: vec![0; bits.div_ceil(BITS)] }
+    }
+
+    /// ビットを立てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 新たに立てた場合はtrueです。
+    /// 
+    pub fn insert(&mut self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let word = &mut self.words[word];
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    /// ビットを下ろします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 立っていた場合はtrueです。
+    /// 
+    pub fn remove(&mut self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        match self.words.get_mut(word) {
+            Some(word) => {
+                let removed = *word & mask != 0;
+                *word &= !mask;
+                removed
+            },
+            None => false,
+        }
+    }
+
+    /// ビットが立っているかを取得します。
+    #[inline]
+    pub fn contains(&self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        self.words.get(word).is_some_and(|w| w & mask != 0)
+    }
+
+    /// すべてのビットを下ろします。確保した領域は保持します。
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// 立っているビットの数を取得します。
+    pub fn len(&self) -> usize {
+        count(&self.words)
+    }
+
+    /// 空かを取得します。
+    pub fn is_empty(&self) -> bool {
+        trimmed(&self.words).is_empty()
+    }
+
+    /// 他の集合の部分集合かを取得します。
+    pub fn is_subset(&self, other: &BitSet) -> bool {
+        is_subset(&self.words, &other.words)
+    }
+
+    /// 他の集合を含むかを取得します。
+    pub fn is_superset(&self, other: &BitSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// 他の集合と共通の要素があるかを取得します。
+    pub fn intersects(&self, other: &BitSet) -> bool {
+        intersects(&self.words, &other.words)
+    }
+
+    /// 他の集合と共通の要素が無いかを取得します。
+    pub fn is_disjoint(&self, other: &BitSet) -> bool {
+        !self.intersects(other)
+    }
+
+    /// 他の集合の要素を加えます。
+    pub fn union_with(&mut self, other: &BitSet) {
+        let other = trimmed(&other.words);
+        if other.len() > self.words.len() {
+            self.words.resize(other.len(), 0);
+        }
+        self.words.iter_mut().zip(other).for_each(|(a, b)| *a |= b);
+    }
+
+    /// 他の集合に無い要素を取り除きます。
+    pub fn intersect_with(&mut self, other: &BitSet) {
+        let len = other.words.len().min(self.words.len());
+        self.words.truncate(len);
+        self.words.iter_mut().zip(&other.words).for_each(|(a, b)| *a &= b);
+    }
+
+    /// 他の集合にある要素を取り除きます。
+    pub fn difference_with(&mut self, other: &BitSet) {
+        self.words.iter_mut().zip(&other.words).for_each(|(a, b)| *a &= !b);
+    }
+
+    /// 立っているビットを昇順に走査します。
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.words)
+    }
+
+    /// ワード列を取得します。末尾に0のワードを含む場合があります。
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+}
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        trimmed(&self.words) == trimmed(&other.words)
+    }
+}
+impl Eq for BitSet {}
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        trimmed(&self.words).hash(state)
+    }
+}
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        iter.into_iter().for_each(|bit| { set.insert(bit); });
+        set
+    }
+}
+impl<'a> IntoIterator for &'a BitSet {
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+
+    fn into_iter(self) -> Ones<'a> {
+        self.iter()
+    }
+}
+impl Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+impl Serialize for BitSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_words(&self.words, serializer)
+    }
+}
+impl<'de> Deserialize<'de> for BitSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_words(deserializer).map(|words| BitSet { words })
+    }
+}
+
+/// 階層を持つビット集合です。
+/// 
+/// 上位の階層の各ビットは、下位の階層のワードが0でないかを表します。
+/// 走査や探索で空のワードを読み飛ばすため、大きく疎な集合に向きます。
+/// 
+#[derive(Clone, Default)]
+pub struct HierBitSet {
+    layers: Vec<Vec<u64>>, // 0番目がビット、以降が下位のワードの要約です。最上位は1ワード以下です。
+}
+impl HierBitSet {
+    /// 空の集合を作成します。
+    pub const fn new() -> HierBitSet {
+        HierBitSet { layers: Vec::new() }
+    }
+
+    /// ビットを立てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 新たに立てた場合はtrueです。
+    /// 
+    pub fn insert(&mut self, bit: usize) -> bool {
+        self.reserve(bit);
+        let mut bit = bit;
+        for (level, layer) in self.layers.iter_mut().enumerate() {
+            let (word, mask) = locate(bit);
+            let was = layer[word];
+            layer[word] |= mask;
+            if level == 0 && was & mask != 0 {
+                return false;
+            }
+            // 既にワードが0でなければ上位は立っています。
+            if was != 0 {
+                break;
+            }
+            bit = word;
+        }
+        true
+    }
+
+    // ビットを格納できるよう階層を伸ばします。
+    fn reserve(&mut self, bit: usize) {
+        let mut words = bit / BITS + 1;
+        let mut level = 0;
+        loop {
+            if level == self.layers.len() {
+                // 新たな最上位は下位の要約を引き継ぎます。
+                let summary = self.layers.last().map(|below| {
+                    let mut summary = vec![0; below.len().div_ceil(BITS)];
+                    for (i, w) in below.iter().enumerate() {
+                        if *w != 0 {
+                            summary[i / BITS] |= 1 << (i % BITS);
+                        }
+                    }
+                    summary
+                });
+                self.layers.push(summary.unwrap_or_default());
+            }
+            let layer = &mut self.layers[level];
+            if layer.len() < words {
+                layer.resize(words, 0);
+            }
+            if words <= 1 && level + 1 == self.layers.len() {
+                return;
+            }
+            words = words.div_ceil(BITS);
+            level += 1;
+        }
+    }
+
+    /// ビットを下ろします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `bit` - ビットの位置です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 立っていた場合はtrueです。
+    /// 
+    pub fn remove(&mut self, bit: usize) -> bool {
+        if !self.contains(bit) {
+            return false;
+        }
+        let mut bit = bit;
+        for layer in &mut self.layers {
+            let (word, mask) = locate(bit);
+            layer[word] &= !mask;
+            // ワードが空になった場合だけ上位を下ろします。
+            if layer[word] != 0 {
+                break;
+            }
+            bit = word;
+        }
+        true
+    }
+
+    /// ビットが立っているかを取得します。
+    #[inline]
+    pub fn contains(&self, bit: usize) -> bool {
+        let (word, mask) = locate(bit);
+        self.bits().get(word).is_some_and(|w| w & mask != 0)
+    }
+
+    /// すべてのビットを下ろします。
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// 立っているビットの数を取得します。
+    pub fn len(&self) -> usize {
+        self.iter_words().map(|(_, w)| w.count_ones() as usize).sum()
+    }
+
+    /// 空かを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.layers.last().is_none_or(|top| top.iter().all(|w| *w == 0))
+    }
+
+    /// 他の集合の部分集合かを取得します。
+    pub fn is_subset(&self, other: &HierBitSet) -> bool {
+        let other = other.bits();
+        self.iter_words().all(|(i, w)| w & !other.get(i).copied().unwrap_or(0) == 0)
+    }
+
+    /// 他の集合を含むかを取得します。
+    pub fn is_superset(&self, other: &HierBitSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// 他の集合と共通の要素があるかを取得します。
+    pub fn intersects(&self, other: &HierBitSet) -> bool {
+        let other = other.bits();
+        self.iter_words().any(|(i, w)| w & other.get(i).copied().unwrap_or(0) != 0)
+    }
+
+    /// 他の集合と共通の要素が無いかを取得します。
+    pub fn is_disjoint(&self, other: &HierBitSet) -> bool {
+        !self.intersects(other)
+    }
+
+    /// 他の集合と共通の要素を昇順に走査します。
+    pub fn intersection<'a>(&'a self, other: &'a HierBitSet) -> impl Iterator<Item = usize> + 'a {
+        let other = other.bits();
+        self.iter_words()
+            .flat_map(move |(i, w)| word_bits(i, w & other.get(i).copied().unwrap_or(0)))
+    }
+
+    /// 立っているビットを昇順に走査します。
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter_words().flat_map(|(i, w)| word_bits(i, w))
+    }
+
+    // 最下位のワード列を取得します。
+    fn bits(&self) -> &[u64] {
+        self.layers.first().map_or(&[], |l| l.as_slice())
+    }
+
+    // 0でないワードを位置と共に昇順に走査します。
+    fn iter_words(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let mut next = 0;
+        std::iter::from_fn(move || {
+            let word = self.next_word(0, next)?;
+            next = word + 1;
+            Some((word, self.layers[0][word]))
+        })
+    }
+
+    // 指定の階層で、位置`from`以降の0でないワードの位置を探します。
+    fn next_word(&self, level: usize, from: usize) -> Option<usize> {
+        let layer = self.layers.get(level)?;
+        if level + 1 == self.layers.len() {
+            return (from..layer.len()).find(|i| layer[*i] != 0);
+        }
+        // 上位の階層で、位置`from`以降の立っているビットを探します。
+        let above = &self.layers[level + 1];
+        let (word, _) = locate(from);
+        let masked = above.get(word)? & (!0u64 << (from % BITS));
+        if masked != 0 {
+            return Some(word * BITS + masked.trailing_zeros() as usize);
+        }
+        let word = self.next_word(level + 1, word + 1)?;
+        Some(word * BITS + above[word].trailing_zeros() as usize)
+    }
+}
+impl PartialEq for HierBitSet {
+    fn eq(&self, other: &Self) -> bool {
+        trimmed(self.bits()) == trimmed(other.bits())
+    }
+}
+impl Eq for HierBitSet {}
+impl Hash for HierBitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        trimmed(self.bits()).hash(state)
+    }
+}
+impl FromIterator<usize> for HierBitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = HierBitSet::new();
+        iter.into_iter().for_each(|bit| { set.insert(bit); });
+        set
+    }
+}
+impl Debug for HierBitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+impl Serialize for HierBitSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_words(self.bits(), serializer)
+    }
+}
+impl<'de> Deserialize<'de> for HierBitSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let words = deserialize_words(deserializer)?;
+        let mut set = HierBitSet::new();
+        for bit in Ones::new(&words) {
+            set.insert(bit);
+        }
+        Ok(set)
+    }
+}
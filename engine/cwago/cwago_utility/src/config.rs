@@ -0,0 +1,665 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/config.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 層で上書きされる設定を提供します。
+//! 
+//! 設定は既定値 < プロジェクトファイル < ユーザーファイル < 環境変数 < コマンドライン
+//! の順に上書きされます。
+//! 各モジュールは使う項目を既定値と共に宣言し、宣言されていない項目や型の異なる値は拒否されます。
+//! 
+//! ```
+//! use cwago_utility::config::{Config, Layer};
+//! 
+//! let mut config = Config::new();
+//! config.declare("window.width", &1280).unwrap();
+//! config.load_str(Layer::Project, "[window]\nwidth = 1920\n").unwrap();
+//! config.load_args(vec!["--window.width=800".to_string()]).unwrap();
+//! assert_eq!(config.get::<u32>("window.width").unwrap(), 800);
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Debug
+    },
+    fs,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize
+};
+use toml::{
+    Table,
+    Value
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result,
+        ResultExt
+    },
+    messages::*,
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            Mutex
+        }
+    };
+
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.declare("window.width", &1280).unwrap();
+        config.declare("window.title", &"cwago").unwrap();
+        config.declare("window.scale", &1.0).unwrap();
+        config.declare("log.categories", &Table::new()).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut config = config();
+        assert_eq!(config.get::<i64>("window.width").unwrap(), 1280);
+
+        config.load_str(Layer::User, "window.width = 1600\n").unwrap();
+        config.load_str(Layer::Project, "[window]\nwidth = 1920\ntitle = \"game\"\n").unwrap();
+        assert_eq!(config.get::<i64>("window.width").unwrap(), 1600, "ユーザーファイルがプロジェクトファイルより優先されていません。");
+        assert_eq!(config.get::<String>("window.title").unwrap(), "game");
+
+        config.load_env_from(vec![
+            ("CWAGO_WINDOW__WIDTH".to_string(), "1024".to_string()),
+            ("CWAGO_WINDOW__TITLE".to_string(), "from env".to_string()),
+            ("CWAGO_LANG".to_string(), "en".to_string()),
+        ]).unwrap();
+        assert_eq!(config.get::<i64>("window.width").unwrap(), 1024);
+        assert_eq!(config.get::<String>("window.title").unwrap(), "from env");
+
+        let rest = config.load_args(vec![
+            "--window.width=800".to_string(),
+            "--unknown=1".to_string(),
+            "scene.toml".to_string(),
+        ]).unwrap();
+        assert_eq!(rest, vec!["--unknown=1", "scene.toml"]);
+        assert_eq!(config.get::<i64>("window.width").unwrap(), 800);
+
+        // 整数は浮動小数点数の項目にも指定できます。
+        config.load_str(Layer::Project, "window.scale = 2\n").unwrap();
+        assert_eq!(config.get::<f64>("window.scale").unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = config();
+
+        let e = config.load_str(Layer::Project, "window.widht = 1\n").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Config);
+        let e = config.load_str(Layer::Project, "window.width = \"wide\"\n").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Config);
+        let e = config.load_str(Layer::Project, "window = [").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        let e = config.load_args(vec!["--window.width=abc".to_string()]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Config);
+        let e = config.declare("window.width", &1).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Config);
+        assert_eq!(config.get::<i64>("window.height").unwrap_err().kind(), ErrorKind::NotFound);
+
+        // 空の表として宣言した項目は任意の項目を持てます。
+        config.load_str(Layer::Project, "[log.categories]\ncwago_memory = \"trace\"\n").unwrap();
+        assert_eq!(config.get::<String>("log.categories.cwago_memory").unwrap(), "trace");
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mut config = config();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let s = seen.clone();
+        let id = config.subscribe("window", move |config| {
+            s.lock().unwrap().push(config.get::<i64>("window.width").unwrap());
+        });
+
+        config.load_str(Layer::Project, "window.width = 1920\n").unwrap();
+        config.load_str(Layer::Project, "window.width = 1920\n").unwrap();
+        config.load_str(Layer::Project, "log.categories.a = \"info\"\n").unwrap();
+        config.set(Layer::CommandLine, "window.width", &640).unwrap();
+        config.unsubscribe(id);
+        config.set(Layer::CommandLine, "window.width", &320).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![1920, 1280, 640]);
+    }
+
+    #[test]
+    fn test_load_file() {
+        let dir = std::env::temp_dir().join(format!("cwago_config_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("user")).unwrap();
+        fs::write(dir.join(PROJECT_FILE), "[window]\nwidth = 1920\ntitle = \"project\"\n").unwrap();
+        let user = dir.join("user").join(PROJECT_FILE);
+        fs::write(&user, "[window]\ntitle = \"user\"\nscale = 2.0\n").unwrap();
+        let vars = HashMap::from([("CWAGO_WINDOW__SCALE".to_string(), "1.5".to_string())]);
+
+        // 実行環境の環境変数とユーザーファイルには依存しません。
+        let mut loaded = config();
+        let rest = loaded.load_standard_from(&dir, Some(&user), vars, vec!["--window.title=t".to_string()]).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(loaded.get::<i64>("window.width").unwrap(), 1920);
+        assert_eq!(loaded.get::<f64>("window.scale").unwrap(), 1.5);
+        assert_eq!(loaded.get::<String>("window.title").unwrap(), "t");
+        assert_eq!(lookup(loaded.layer(Layer::User), "window.title"), Some(&Value::from("user")));
+        assert_eq!(lookup(loaded.layer(Layer::Project), "window.title"), Some(&Value::from("project")));
+
+        // 存在しないユーザーファイルは読み込みません。
+        let mut missing = config();
+        missing.load_standard_from(&dir, Some(&dir.join("missing.toml")), HashMap::new(), Vec::new()).unwrap();
+        assert!(missing.layer(Layer::User).is_empty());
+        assert_eq!(missing.get::<String>("window.title").unwrap(), "project");
+
+        let e = missing.load_file(Layer::User, &dir.join("missing.toml")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Io);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+/// プロジェクトの設定ファイル名です。
+pub const PROJECT_FILE: &str = "cwago.toml";
+
+/// 設定を上書きする環境変数の接頭辞です。
+/// 
+/// `CWAGO_MEMORY__COUNT16`は`memory.count16`を上書きします。
+pub const ENV_PREFIX: &str = "CWAGO_";
+
+/// 環境変数名で項目の階層を区切る文字列です。
+pub const ENV_SEPARATOR: &str = "__";
+
+/// 設定の層です。
+/// 
+/// 後の層ほど優先されます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// 宣言された既定値です。
+    Default,
+    /// プロジェクトの設定ファイルです。
+    Project,
+    /// ユーザーの設定ファイルです。
+    User,
+    /// 環境変数です。
+    Env,
+    /// コマンドライン引数です。
+    CommandLine,
+}
+impl Layer {
+    /// すべての層の数です。
+    pub const COUNT: usize = 5;
+}
+
+/// 変更の購読を識別します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+/// 変更を通知する関数です。
+type Subscriber = Box<dyn FnMut(&Config) + Send>;
+
+/// 層で上書きされる設定です。
+pub struct Config {
+    layers: [Table; Layer::COUNT],                        // 層毎の値です。既定値の層が宣言を兼ねます。
+    merged: Table,                                        // すべての層を重ねた値です。
+    subscribers: Vec<(Subscription, String, Subscriber)>, // 購読する項目と購読者です。
+    next_subscription: u64,                               // 次に発行する購読のIDです。
+}
+impl Config {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 何も宣言されていないインスタンスです。
+    /// 
+    pub fn new() -> Config {
+        Config {
+            layers: Default::default(),
+            merged: Table::new(),
+            subscribers: Vec::new(),
+            next_subscription: 0,
+        }
+    }
+
+    /// 項目を宣言します。
+    /// 
+    /// 表になる値を宣言した場合、その中の項目がすべて宣言されます。
+    /// 空の表を宣言した場合、その中には任意の項目を設定できます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - `memory.count16`のような`.`区切りの項目名です。
+    /// * `default` - 既定値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 既に宣言されている場合、エラーを返します。
+    /// 
+    pub fn declare<T: Serialize + ?Sized>(&mut self, key: &str, default: &T) -> Result<()> {
+        let value = Value::try_from(default)
+            .map_err(|e| CwagoError::from_source(ErrorKind::Serialize, e))
+            .with_context(|| msg!(CONFIG_SERIALIZE_FAILED, key = key))?;
+        if lookup(&self.layers[Layer::Default as usize], key).is_some() {
+            return Err(CwagoError::new(ErrorKind::Config, msg!(CONFIG_DUPLICATE, key = key)));
+        }
+        insert(&mut self.layers[Layer::Default as usize], key, value);
+        self.update();
+        Ok(())
+    }
+
+    /// 層の値を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layer` - 置き換える層です。既定値の層は指定できません。
+    /// * `table` - 新しい値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 宣言されていない項目や型の異なる値を含む場合、層を変更せずにエラーを返します。
+    /// 
+    pub fn set_layer(&mut self, layer: Layer, table: Table) -> Result<()> {
+        if layer == Layer::Default {
+            return Err(CwagoError::new(ErrorKind::InvalidArgument, msg!(CONFIG_DEFAULT_LAYER)));
+        }
+        let mut problems = Vec::new();
+        validate(&self.layers[Layer::Default as usize], &table, "", &mut problems);
+        if !problems.is_empty() {
+            return Err(CwagoError::new(ErrorKind::Config, problems.join(" ")));
+        }
+        self.layers[layer as usize] = table;
+        self.update();
+        Ok(())
+    }
+
+    /// 1つの項目を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layer` - 設定する層です。既定値の層は指定できません。
+    /// * `key` - `.`区切りの項目名です。
+    /// * `value` - 値です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 宣言されていない項目や型の異なる値の場合、エラーを返します。
+    /// 
+    pub fn set<T: Serialize + ?Sized>(&mut self, layer: Layer, key: &str, value: &T) -> Result<()> {
+        let value = Value::try_from(value)
+            .map_err(|e| CwagoError::from_source(ErrorKind::Serialize, e))
+            .with_context(|| msg!(CONFIG_SERIALIZE_FAILED, key = key))?;
+        let mut table = self.layers[layer as usize].clone();
+        insert(&mut table, key, value);
+        self.set_layer(layer, table)
+    }
+
+    /// TOML文字列で層の値を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layer` - 置き換える層です。
+    /// * `src` - TOML文字列です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 解析や検証に失敗した際、エラーを返します。
+    /// 
+    pub fn load_str(&mut self, layer: Layer, src: &str) -> Result<()> {
+        let table = src.parse::<Table>()
+            .map_err(|e| CwagoError::from_source(ErrorKind::Parse, e))?;
+        self.set_layer(layer, table)
+    }
+
+    /// TOMLファイルで層の値を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layer` - 置き換える層です。
+    /// * `path` - TOMLファイルのパスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 読み込み、解析、検証に失敗した際、エラーを返します。
+    /// 
+    pub fn load_file(&mut self, layer: Layer, path: &Path) -> Result<()> {
+        fs::read_to_string(path)
+            .map_err(CwagoError::from)
+            .and_then(|src| self.load_str(layer, &src))
+            .with_context(|| msg!(CONFIG_FILE, path = path.display()))
+    }
+
+    /// 環境変数で環境変数の層を置き換えます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値を解釈できない際、エラーを返します。
+    /// 
+    pub fn load_env(&mut self) -> Result<()> {
+        self.load_env_from(std::env::vars())
+    }
+
+    /// 名前と値の組で環境変数の層を置き換えます。
+    /// 
+    /// `CWAGO_`で始まり、宣言された項目に対応する変数だけを使います。
+    /// `CWAGO_LANG`のような他の用途の変数は無視します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `vars` - 環境変数の名前と値の組です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 値を解釈できない際、エラーを返します。
+    /// 
+    pub fn load_env_from(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let mut table = Table::new();
+        for (name, raw) in vars {
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(key) => key.split(ENV_SEPARATOR).collect::<Vec<_>>().join(".").to_lowercase(),
+                None => continue,
+            };
+            if let Some(value) = self.parse_leaf(&key, &raw)? {
+                insert(&mut table, &key, value);
+            }
+        }
+        self.set_layer(Layer::Env, table)
+    }
+
+    /// `--項目名=値`形式の引数でコマンドラインの層を置き換えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `args` - プログラム名を除いたコマンドライン引数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 設定に使わなかった引数、または、値を解釈できない際のエラーです。
+    /// 
+    pub fn load_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<Vec<String>> {
+        let mut table = Table::new();
+        let mut rest = Vec::new();
+        for arg in args {
+            let parsed = match arg.strip_prefix("--").and_then(|a| a.split_once('=')) {
+                Some((key, raw)) => self.parse_leaf(key, raw)?.map(|v| (key.to_string(), v)),
+                None => None,
+            };
+            match parsed {
+                Some((key, value)) => insert(&mut table, &key, value),
+                None => rest.push(arg),
+            }
+        }
+        self.set_layer(Layer::CommandLine, table)?;
+        Ok(rest)
+    }
+
+    /// 標準の場所からすべての層を読み込みます。
+    /// 
+    /// プロジェクトファイルとユーザーファイルは存在する場合のみ読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `project_dir` - `cwago.toml`を置くプロジェクトのディレクトリです。
+    /// * `args` - プログラム名を除いたコマンドライン引数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 設定に使わなかった引数、または、エラーです。
+    /// 
+    pub fn load_standard(&mut self, project_dir: &Path, args: impl IntoIterator<Item = String>) -> Result<Vec<String>> {
+        self.load_standard_from(project_dir, user_file().as_deref(), std::env::vars(), args)
+    }
+
+    /// ユーザーファイルと環境変数を指定してすべての層を読み込みます。
+    /// 
+    /// プロジェクトファイルとユーザーファイルは存在する場合のみ読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `project_dir` - `cwago.toml`を置くプロジェクトのディレクトリです。
+    /// * `user` - ユーザーファイルのパスです。Noneの場合は読み込みません。
+    /// * `vars` - 環境変数の名前と値の組です。
+    /// * `args` - プログラム名を除いたコマンドライン引数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 設定に使わなかった引数、または、エラーです。
+    /// 
+    pub fn load_standard_from(
+        &mut self,
+        project_dir: &Path,
+        user: Option<&Path>,
+        vars: impl IntoIterator<Item = (String, String)>,
+        args: impl IntoIterator<Item = String>
+    ) -> Result<Vec<String>> {
+        let project = project_dir.join(PROJECT_FILE);
+        if project.is_file() {
+            self.load_file(Layer::Project, &project)?;
+        }
+        if let Some(user) = user.filter(|p| p.is_file()) {
+            self.load_file(Layer::User, user)?;
+        }
+        self.load_env_from(vars)?;
+        self.load_args(args)
+    }
+
+    /// 値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - `.`区切りの項目名です。表の項目名の場合は表全体を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// すべての層を重ねた値、または、エラーです。
+    /// 
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T> {
+        let value = self.value(key)
+            .ok_or_else(|| CwagoError::new(ErrorKind::NotFound, msg!(CONFIG_NOT_FOUND, key = key)))?;
+        value.clone().try_into()
+            .map_err(|e| CwagoError::from_source(ErrorKind::Deserialize, e))
+            .with_context(|| msg!(CONFIG_GET_FAILED, key = key))
+    }
+
+    /// 型に変換せずに値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - `.`区切りの項目名です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// すべての層を重ねた値、または、無い場合はNoneです。
+    /// 
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        lookup(&self.merged, key)
+    }
+
+    /// 層の値を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `layer` - 層です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 層に設定された値です。
+    /// 
+    pub fn layer(&self, layer: Layer) -> &Table {
+        &self.layers[layer as usize]
+    }
+
+    /// 値の変更を購読します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `key` - 購読する項目名です。表の項目名の場合は中のいずれかの変更で通知されます。
+    /// * `f` - 変更後の設定を受け取る関数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 購読を解除するためのIDです。
+    /// 
+    pub fn subscribe(&mut self, key: &str, f: impl FnMut(&Config) + Send + 'static) -> Subscription {
+        let id = Subscription(self.next_subscription);
+        self.next_subscription += 1;
+        self.subscribers.push((id, key.to_string(), Box::new(f)));
+        id
+    }
+
+    /// 購読を解除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - 購読した際のIDです。
+    /// 
+    pub fn unsubscribe(&mut self, id: Subscription) {
+        self.subscribers.retain(|(s, _, _)| *s != id);
+    }
+
+    // 宣言された項目の文字列を、宣言の型で解釈します。
+    // 表以外の宣言された項目で無い場合はNoneです。
+    fn parse_leaf(&self, key: &str, raw: &str) -> Result<Option<Value>> {
+        let declared = match lookup(&self.layers[Layer::Default as usize], key) {
+            Some(Value::Table(_)) | None => return Ok(None),
+            Some(declared) => declared,
+        };
+        if declared.is_str() {
+            return Ok(Some(Value::String(raw.to_string())));
+        }
+        let value = format!("v = {}", raw).parse::<Table>()
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .filter(|v| compatible(declared, v))
+            .ok_or_else(|| CwagoError::new(ErrorKind::Config, msg!(CONFIG_VALUE_PARSE, key = key, value = raw)))?;
+        Ok(Some(value))
+    }
+
+    // 層を重ね直し、変更された項目の購読者に通知します。
+    fn update(&mut self) {
+        let mut merged = Table::new();
+        for layer in &self.layers {
+            merge(&mut merged, layer);
+        }
+        let old = std::mem::replace(&mut self.merged, merged);
+
+        let mut subscribers = std::mem::take(&mut self.subscribers);
+        for (_, key, f) in subscribers.iter_mut() {
+            if lookup(&old, key) != lookup(&self.merged, key) {
+                f(self);
+            }
+        }
+        self.subscribers = subscribers;
+    }
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("merged", &self.merged)
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+/// ユーザーの設定ファイルのパスを取得します。
+/// 
+/// # 戻り値
+/// 
+/// Windowsでは`%APPDATA%\cwago\cwago.toml`、
+/// それ以外では`$XDG_CONFIG_HOME/cwago/cwago.toml`または`~/.config/cwago/cwago.toml`です。
+/// 
+pub fn user_file() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let dir = if cfg!(windows) {
+        var("APPDATA")
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|h| h.join(".config")))
+    };
+    dir.map(|d| d.join("cwago").join(PROJECT_FILE))
+}
+
+// `.`区切りの項目名で値を探します。
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (head, tail) = match key.split_once('.') {
+        Some((head, tail)) => (head, Some(tail)),
+        None => (key, None),
+    };
+    match (table.get(head), tail) {
+        (Some(Value::Table(t)), Some(tail)) => lookup(t, tail),
+        (value, None) => value,
+        _ => None,
+    }
+}
+
+// `.`区切りの項目名で値を挿入します。途中の表は作成します。
+fn insert(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, tail)) => {
+            let entry = table.entry(head).or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+            if let Value::Table(t) = entry {
+                insert(t, tail, value);
+            }
+        },
+        None => { table.insert(key.to_string(), value); },
+    }
+}
+
+// 表を再帰的に上書きします。
+fn merge(base: &mut Table, over: &Table) {
+    for (key, value) in over {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(b)), Value::Table(o)) => merge(b, o),
+            _ => { base.insert(key.clone(), value.clone()); },
+        }
+    }
+}
+
+// 宣言された値の型に、値を設定できるかを判定します。
+fn compatible(declared: &Value, value: &Value) -> bool {
+    declared.same_type(value) || (declared.is_float() && value.is_integer())
+}
+
+// 宣言と照合し、問題を収集します。
+fn validate(declared: &Table, table: &Table, prefix: &str, problems: &mut Vec<String>) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match (declared.get(name), value) {
+            (None, _) => problems.push(msg!(CONFIG_UNKNOWN_KEY, key = key)),
+            // 空の表は任意の項目を持てます。
+            (Some(Value::Table(d)), Value::Table(_)) if d.is_empty() => {},
+            (Some(Value::Table(d)), Value::Table(t)) => validate(d, t, &key, problems),
+            (Some(d), v) if !compatible(d, v) => problems.push(msg!(
+                CONFIG_TYPE_MISMATCH,
+                key = key,
+                expected = d.type_str(),
+                found = v.type_str()
+            )),
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,312 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/error.rs
+// (C) 2023 CwagoCommunity.
+//
+//! エンジン共通のエラー型を提供します。
+// =========================
+
+use std::{
+    backtrace::{
+        Backtrace,
+        BacktraceStatus
+    },
+    error::Error,
+    fmt::{
+        self,
+        Debug,
+        Display
+    },
+    io
+};
+
+use crate::{
+    log::error,
+    plugin::{
+        Diagnostics,
+        PluginError
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_save() -> Result<()> {
+        let e = io::Error::new(io::ErrorKind::NotFound, "save.dat");
+        Err(e).context("セーブデータを読み込めませんでした。")
+    }
+
+    #[test]
+    fn test_context_and_source() {
+        let e = read_save()
+            .with_context(|| "スロット1".to_string())
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Io);
+        assert_eq!(e.contexts(), &["セーブデータを読み込めませんでした。", "スロット1"]);
+        assert_eq!(e.to_string(), "スロット1: セーブデータを読み込めませんでした。: save.dat");
+        let source = e.source().expect("原因のエラーが保持されていません。");
+        assert_eq!(source.to_string(), "save.dat");
+        assert!(source.downcast_ref::<io::Error>().is_some());
+    }
+
+    #[test]
+    fn test_new() {
+        let e = CwagoError::new(ErrorKind::InvalidArgument, "サイズが0です。");
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+        assert_eq!(e.message(), "サイズが0です。");
+        assert!(e.source().is_none());
+        assert_eq!(e.to_string(), "サイズが0です。");
+        assert_eq!(
+            CwagoError::from(PluginError::Duplicate { name: "a".to_string() }).kind(),
+            ErrorKind::Plugin
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_or_abort() {
+        let r: Result<()> = Err(CwagoError::new(ErrorKind::Other, "異常終了します。"));
+        r.or_abort();
+    }
+}
+
+/// エラーの種類です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// メモリの確保、解放に失敗しました。
+    Memory,
+    /// 入出力に失敗しました。
+    Io,
+    /// データの解析に失敗しました。
+    Parse,
+    /// シリアライズに失敗しました。
+    Serialize,
+    /// デシリアライズに失敗しました。
+    Deserialize,
+    /// 型情報が不正です。
+    Type,
+    /// プラグインの読み込みに失敗しました。
+    Plugin,
+    /// 設定が不正です。
+    Config,
+    /// 引数が不正です。
+    InvalidArgument,
+    /// 対象が見つかりません。
+    NotFound,
+    /// 上限に達しました。
+    Exhausted,
+    /// その他のエラーです。
+    Other,
+}
+
+/// エンジン共通のエラーです。
+/// 
+/// 種類、メッセージ、原因のエラー、呼び出し側で追加した文脈を持ちます。
+/// バックトレースは`RUST_BACKTRACE`が有効な場合のみ記録します。
+pub struct CwagoError {
+    kind: ErrorKind,                              // 種類です。
+    message: String,                              // メッセージです。
+    context: Vec<String>,                         // 内側から順の文脈です。
+    source: Option<Box<dyn Error + Send + Sync>>, // 原因のエラーです。
+    backtrace: Option<Backtrace>,                 // 作成時のバックトレースです。
+}
+
+/// CwagoErrorを返すResult型です。
+pub type Result<T> = std::result::Result<T, CwagoError>;
+
+impl CwagoError {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `kind` - 種類です。
+    /// * `message` - メッセージです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> CwagoError {
+        let backtrace = Backtrace::capture();
+        CwagoError {
+            kind,
+            message: message.into(),
+            context: Vec::new(),
+            source: None,
+            backtrace: match backtrace.status() {
+                BacktraceStatus::Captured => Some(backtrace),
+                _ => None,
+            },
+        }
+    }
+
+    /// 原因のエラーから作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `kind` - 種類です。
+    /// * `source` - 原因のエラーです。メッセージには原因のエラーの文面を使います。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn from_source(kind: ErrorKind, source: impl Error + Send + Sync + 'static) -> CwagoError {
+        Self::new(kind, source.to_string()).with_source(source)
+    }
+
+    /// 原因のエラーを設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `source` - 原因のエラーです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 原因を設定したインスタンスです。
+    /// 
+    pub fn with_source(mut self, source: impl Error + Send + Sync + 'static) -> CwagoError {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// 文脈を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `context` - エラーが起きた時に何をしていたかの説明です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 文脈を追加したインスタンスです。
+    /// 
+    pub fn context(mut self, context: impl Into<String>) -> CwagoError {
+        self.context.push(context.into());
+        self
+    }
+
+    /// 種類を取得します。
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// メッセージを取得します。
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// 内側から順に追加された文脈を取得します。
+    pub fn contexts(&self) -> &[String] {
+        &self.context
+    }
+
+    /// 作成時のバックトレースを取得します。
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+}
+impl Display for CwagoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.context.iter().rev() {
+            write!(f, "{}: ", c)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+impl Debug for CwagoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self)?;
+        let mut source = self.source();
+        while let Some(e) = source {
+            write!(f, "\n  caused by: {}", e)?;
+            source = e.source();
+        }
+        if let Some(backtrace) = &self.backtrace {
+            write!(f, "\n{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+impl Error for CwagoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+impl From<io::Error> for CwagoError {
+    fn from(e: io::Error) -> Self {
+        Self::from_source(ErrorKind::Io, e)
+    }
+}
+impl From<PluginError> for CwagoError {
+    fn from(e: PluginError) -> Self {
+        Self::from_source(ErrorKind::Plugin, e)
+    }
+}
+impl From<Diagnostics> for CwagoError {
+    fn from(e: Diagnostics) -> Self {
+        Self::from_source(ErrorKind::Plugin, e)
+    }
+}
+
+/// Resultに文脈を追加する機能です。
+pub trait ResultExt<T> {
+    /// 失敗していた場合、文脈を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `context` - エラーが起きた時に何をしていたかの説明です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// CwagoErrorに変換したResultです。
+    /// 
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// 失敗していた場合、関数で作成した文脈を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 文脈を作成する関数です。失敗した場合のみ呼び出されます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// CwagoErrorに変換したResultです。
+    /// 
+    fn with_context(self, f: impl FnOnce() -> String) -> Result<T>;
+
+    /// 失敗していた場合、エラーログを残して異常終了します。
+    /// 
+    /// 回復できない箇所でResultを返すAPIを使うためのものです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 成功した際の値です。
+    /// 
+    fn or_abort(self) -> T;
+}
+impl<T, E> ResultExt<T> for std::result::Result<T, E>
+where E: Into<CwagoError>
+{
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context(self, f: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|e| e.into().context(f()))
+    }
+
+    #[track_caller]
+    fn or_abort(self) -> T {
+        match self {
+            Ok(v) => v,
+            Err(e) => {
+                let e = e.into();
+                error!("{}", e);
+                panic!("{}", e)
+            },
+        }
+    }
+}
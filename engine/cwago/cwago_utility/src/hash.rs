@@ -6,6 +6,9 @@
 // (C) 2023 CwagoCommunity.
 //
 //! 高速なハッシュ機能を提供します。
+//! 
+//! `FxHasher`は実行中のハッシュテーブル用です。
+//! アセットIDやセーブデータなど、環境をまたいで同じ値が必要な場合は`StableHasher`を使用します。
 // =========================
 
 use std::{
@@ -18,6 +21,103 @@ use std::{
     collections
 };
 
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+
+    use super::*;
+
+    fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = StableHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_stable_hash_known_answers() {
+        // FNV-1a 64bitの公開されているテストベクタです。
+        assert_eq!(stable_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(stable_hash(b"foobar"), 0x85944171f73967e8);
+
+        // 文字列は終端の0xffを加えたバイト列のFNV-1a 64bitです。
+        // パックの目次に保存されるため、値を変えてはいけません。
+        assert_eq!(stable_hash_str(""), 0xaf64724c8602eb6e);
+        assert_eq!(stable_hash_str("a"), 0x089bc907b544c769);
+        assert_eq!(stable_hash_str("foobar"), 0x34524ba7168a2c15);
+
+        // コンパイル時に計算できるかテストします。
+        const FOOBAR: u64 = stable_hash_str("foobar");
+        assert_eq!(FOOBAR, 0x34524ba7168a2c15);
+    }
+
+    #[test]
+    fn test_stable_hasher_endianness() {
+        // 整数はリトルエンディアンのバイト列として計算されます。
+        assert_eq!(hash_of(&0x04030201u32), stable_hash(&[1, 2, 3, 4]));
+        assert_eq!(hash_of(&0x0201u16), stable_hash(&[1, 2]));
+        assert_eq!(hash_of(&-2i64), stable_hash(&[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(hash_of(&1u128), stable_hash(&1u128.to_le_bytes()));
+
+        // usizeはポインタ幅に関わらず64bitとして計算されます。
+        assert_eq!(hash_of(&7usize), hash_of(&7u64));
+        assert_eq!(hash_of(&-7isize), hash_of(&-7i64));
+
+        // 文字列は`write_str`と`Hash`トレイトのどちらでも同じ値です。
+        let mut hasher = StableHasher::default();
+        hasher.write_str("foobar");
+        assert_eq!(hasher.finish(), 0x34524ba7168a2c15);
+        assert_eq!(hash_of("foobar"), 0x34524ba7168a2c15);
+        assert_eq!(hash_of(&String::from("foobar")), 0x34524ba7168a2c15);
+        assert_eq!(hash_of(&(1u8, 2u32, "x")), stable_hash(&[1, 2, 0, 0, 0, b'x', 0xff]));
+    }
+
+    #[test]
+    fn test_stable_hasher_write() {
+        // FNV-1a 64bitの公開されているテストベクタです。
+        for (bytes, expected) in [
+            (&b""[..], 0xcbf29ce484222325),
+            (b"a", 0xaf63dc4c8601ec8c),
+            (b"foobar", 0x85944171f73967e8),
+        ] {
+            let mut hasher = StableHasher::default();
+            hasher.write(bytes);
+            assert_eq!(hasher.finish(), expected);
+        }
+
+        // 分割して書き込んでも同じ値です。
+        let mut hasher = StableHasher::default();
+        hasher.write(b"foo");
+        hasher.write(b"bar");
+        assert_eq!(hasher.finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_crc32() {
+        // CRC-32の公開されているチェック値です。
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_stable_hash_map() {
+        let mut map = StableHashMap::default();
+        map.insert("transform", 1);
+        map.insert("physics", 2);
+        assert_eq!(map.get("transform"), Some(&1));
+        assert_eq!(map.get("physics"), Some(&2));
+
+        let mut set = StableHashSet::default();
+        assert!(set.insert(10u64));
+        assert!(!set.insert(10u64));
+    }
+}
+
 /// FxHasherを使用した集合型です。
 pub type FxHashSet<T> = collections::HashSet<T, BuildFxHasher>;
 
@@ -76,7 +176,7 @@ impl Hasher for FxHasher {
             hash.add_to_hash(u16::from_ne_bytes(bytes[..2].try_into().unwrap()) as usize);
             bytes = &bytes[2..];
         }
-        if (size_of::<usize>() > 1) && bytes.len() >= 1 {
+        if (size_of::<usize>() > 1) && !bytes.is_empty() {
             hash.add_to_hash(bytes[0] as usize);
         }
         self.hash = hash.hash;
@@ -115,3 +215,242 @@ impl Hasher for FxHasher {
         self.add_to_hash(i);
     }
 }
+
+
+/// StableHasherを使用した集合型です。
+pub type StableHashSet<T> = collections::HashSet<T, BuildStableHasher>;
+
+/// StableHasherを使用した連想配列型です。
+pub type StableHashMap<K, V> = collections::HashMap<K, V, BuildStableHasher>;
+
+/// StableHasherを作成するビルダーです。
+pub type BuildStableHasher = BuildHasherDefault<StableHasher>;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x00000100000001b3;
+
+/// 文字列の終端に加えるバイトです。
+/// 
+/// UTF-8に現れないため、連続した文字列の境界が曖昧になりません。
+pub const STR_TERMINATOR: u8 = 0xff;
+
+/// 環境に依存しないハッシュ計算機能です。
+/// 
+/// FNV-1a 64bitで計算します。
+/// 整数はリトルエンディアン、`usize`と`isize`は64bitとして扱うため、
+/// ポインタ幅やエンディアンが異なる環境でも同じ値になります。
+/// 文字列は`write_str`の通り、UTF-8のバイト列に`STR_TERMINATOR`を加えて計算します。
+#[derive(Clone, Copy)]
+pub struct StableHasher {
+    hash: u64,
+}
+impl StableHasher {
+    #[inline]
+    fn add_bytes(&mut self, bytes: &[u8]) {
+        self.hash = fnv1a(self.hash, bytes);
+    }
+
+    /// 文字列を加えます。
+    /// 
+    /// UTF-8のバイト列に続けて`STR_TERMINATOR`を加えます。
+    /// 標準ライブラリの`str`の`Hash`実装と同じ値ですが、こちらの定義を正とします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `s` - 対象の文字列です。
+    /// 
+    #[inline]
+    pub fn write_str(&mut self, s: &str) {
+        self.hash = fnv1a_str(self.hash, s);
+    }
+}
+impl Default for StableHasher {
+    #[inline]
+    fn default() -> StableHasher {
+        StableHasher { hash: FNV_OFFSET }
+    }
+}
+impl Hasher for StableHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.add_bytes(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add_bytes(&[i]);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.add_bytes(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add_bytes(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add_bytes(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.add_bytes(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(i as u8);
+    }
+
+    #[inline]
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    #[inline]
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    #[inline]
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    #[inline]
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// バイト列のハッシュ値をコンパイル時にも計算可能な形で求めます。
+/// 
+/// # 引数
+/// 
+/// * `bytes` - 対象のバイト列です。
+/// 
+/// # 戻り値
+/// 
+/// FNV-1a 64bitのハッシュ値です。
+/// 
+pub const fn stable_hash(bytes: &[u8]) -> u64 {
+    fnv1a(FNV_OFFSET, bytes)
+}
+
+/// 文字列のハッシュ値をコンパイル時にも計算可能な形で求めます。
+/// 
+/// `StableHasher::write_str`で文字列だけを加えた値と一致します。
+/// 
+/// # 引数
+/// 
+/// * `s` - 対象の文字列です。
+/// 
+/// # 戻り値
+/// 
+/// UTF-8バイト列と`STR_TERMINATOR`のFNV-1a 64bitのハッシュ値です。
+/// 
+pub const fn stable_hash_str(s: &str) -> u64 {
+    fnv1a_str(FNV_OFFSET, s)
+}
+
+// FNV-1aでハッシュ値にバイト列を加えます。
+const fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    hash
+}
+
+// FNV-1aでハッシュ値に終端を加えた文字列を加えます。
+const fn fnv1a_str(hash: u64, s: &str) -> u64 {
+    fnv1a(fnv1a(hash, s.as_bytes()), &[STR_TERMINATOR])
+}
+
+// CRC-32(IEEE)の多項式を反転した値です。
+const CRC32_POLY: u32 = 0xedb88320;
+
+// CRC-32の1バイト毎の表です。
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { CRC32_POLY ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// バイト列のCRC-32を求めます。
+/// 
+/// # 引数
+/// 
+/// * `bytes` - 対象のバイト列です。
+/// 
+/// # 戻り値
+/// 
+/// CRC-32(IEEE 802.3)の値です。
+/// 
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+/// 分割されたバイト列のCRC-32を求めます。
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32, // 反転した途中の値です。
+}
+impl Crc32 {
+    /// 作成します。
+    pub const fn new() -> Crc32 {
+        Crc32 { state: !0 }
+    }
+
+    /// バイト列を加えます。
+    pub fn update(&mut self, bytes: &[u8]) {
+        let mut c = self.state;
+        for b in bytes {
+            c = CRC32_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8);
+        }
+        self.state = c;
+    }
+
+    /// 値を取得します。
+    pub const fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
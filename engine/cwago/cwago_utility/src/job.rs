@@ -0,0 +1,860 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/job.rs
+// (C) 2023 CwagoCommunity.
+//
+//! ワークスティーリングによるジョブシステムを提供します。
+//! 
+//! 各ワーカーは自身の両端キューからジョブを取り出し、空になると共有キューや他のワーカーから盗みます。
+//! ジョブの完了は`Counter`で待ち合わせ、依存先として後続のジョブに渡せます。
+//! `scope`内のジョブはスタック上のデータを借用でき、`scope`はすべてのジョブの完了を待ってから戻ります。
+//! 
+//! ジョブはグローバルアロケータで確保されます。
+//! cwago_memoryはこのクレートに依存するため直接は使えませんが、
+//! cwago_memoryの`Allocator`を`#[global_allocator]`に設定すれば、その上で確保されます。
+//! 
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use cwago_utility::job::JobSystem;
+//! 
+//! let jobs = JobSystem::new(2);
+//! let mut values = vec![1, 2, 3, 4];
+//! jobs.parallel_for_mut(&mut values, 2, |_, v| *v *= 10);
+//! assert_eq!(values, [10, 20, 30, 40]);
+//! 
+//! let sum = AtomicUsize::new(0);
+//! jobs.scope(|s| {
+//!     for v in &values {
+//!         let sum = &sum;
+//!         s.spawn(move || { sum.fetch_add(*v, Ordering::Relaxed); });
+//!     }
+//! });
+//! assert_eq!(sum.into_inner(), 100);
+//! ```
+// =========================
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{
+        self,
+        Debug
+    },
+    marker::PhantomData,
+    mem,
+    panic::{
+        self,
+        AssertUnwindSafe
+    },
+    ptr,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            AtomicUsize,
+            Ordering
+        },
+        Arc,
+        Condvar,
+        Mutex,
+        MutexGuard,
+        OnceLock
+    },
+    thread::{
+        self,
+        JoinHandle,
+        ThreadId
+    },
+    time::Duration
+};
+
+use crossbeam_deque::{
+    Injector,
+    Steal,
+    Stealer,
+    Worker
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result,
+        ResultExt
+    },
+    messages::{
+        JOB_PANICKED,
+        JOB_SPAWN_FAILED
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn() {
+        crate::logging::init();
+        let jobs = JobSystem::new(3);
+        let count = Arc::new(AtomicUsize::new(0));
+        let counters: Vec<_> = (0..64)
+            .map(|_| {
+                let count = count.clone();
+                jobs.spawn(move || { count.fetch_add(1, Ordering::Relaxed); })
+            })
+            .collect();
+        for counter in &counters {
+            jobs.wait(counter).unwrap();
+        }
+        assert_eq!(count.load(Ordering::Relaxed), 64);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        crate::logging::init();
+        let jobs = JobSystem::new(2);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let push = |n: u32| {
+            let order = order.clone();
+            move || {
+                thread::sleep(Duration::from_millis(5));
+                lock(&order).push(n);
+            }
+        };
+        let a = jobs.spawn(push(1));
+        let b = jobs.spawn(push(2));
+        let c = jobs.spawn_with(Affinity::Any, &[&a, &b], push(3));
+        let d = jobs.spawn_with(Affinity::Any, &[&c], push(4));
+        jobs.wait(&d).unwrap();
+        let order = lock(&order).clone();
+        assert_eq!(order[2..], [3, 4], "依存先の後に実行される必要があります。");
+
+        // 完了済みの依存先はすぐに満たされます。
+        let e = jobs.spawn_with(Affinity::Any, &[&a, &Counter::new()], || {});
+        jobs.wait(&e).unwrap();
+    }
+
+    #[test]
+    fn test_scope() {
+        crate::logging::init();
+        let jobs = JobSystem::new(2);
+        let mut values = vec![0usize; 100];
+        jobs.scope(|s| {
+            for (i, chunk) in values.chunks_mut(10).enumerate() {
+                s.spawn(move || chunk.fill(i));
+                // スコープを入れ子にできます。
+                s.spawn(|| jobs.scope(|s| s.spawn(|| {})));
+            }
+        });
+        assert!(values.iter().enumerate().all(|(i, v)| *v == i / 10));
+    }
+
+    #[test]
+    fn test_parallel_for() {
+        crate::logging::init();
+        let jobs = JobSystem::new(3);
+        let mut values: Vec<usize> = (0..1000).collect();
+        jobs.parallel_for_mut(&mut values, 64, |i, v| *v += i);
+        assert!(values.iter().enumerate().all(|(i, v)| *v == i * 2));
+
+        let sum = AtomicUsize::new(0);
+        jobs.parallel_for(&values, 0, |_, v| { sum.fetch_add(*v, Ordering::Relaxed); });
+        assert_eq!(sum.into_inner(), 999 * 1000);
+
+        // ワーカーが無くても呼び出し元で実行されます。
+        let inline = JobSystem::new(0);
+        inline.parallel_for_mut(&mut values, 7, |_, v| *v = 1);
+        assert!(values.iter().all(|v| *v == 1));
+    }
+
+    #[test]
+    fn test_main_affinity() {
+        crate::logging::init();
+        let jobs = JobSystem::new(2);
+        let main = thread::current().id();
+        let ran = Arc::new(Mutex::new(Vec::new()));
+
+        let r = ran.clone();
+        let first = jobs.spawn_main(move || lock(&r).push(thread::current().id()));
+        let r = ran.clone();
+        let second = jobs.spawn_with(Affinity::Main, &[&first], move || lock(&r).push(thread::current().id()));
+
+        thread::sleep(Duration::from_millis(10));
+        assert!(!first.is_done(), "メインスレッドで実行されるまで完了しない必要があります。");
+        assert_eq!(thread::scope(|s| s.spawn(|| jobs.run_main_thread_jobs()).join().unwrap()), 0);
+        assert_eq!(jobs.run_main_thread_jobs(), 1);
+        // 待機中のメインスレッドは、メインスレッドのジョブを実行します。
+        jobs.wait(&second).unwrap();
+
+        // ワーカーから投入したジョブもメインスレッドで実行されます。
+        let jobs = Arc::new(jobs);
+        let (j, r) = (jobs.clone(), ran.clone());
+        let relay = jobs.spawn(move || { j.spawn_main(move || lock(&r).push(thread::current().id())); });
+        jobs.wait(&relay).unwrap();
+        while jobs.run_main_thread_jobs() == 0 {
+            thread::yield_now();
+        }
+        assert_eq!(*lock(&ran), [main, main, main]);
+    }
+
+    #[test]
+    fn test_panic() {
+        crate::logging::init();
+        let jobs = JobSystem::new(1);
+        let counter = jobs.spawn(|| panic!("test"));
+        let e = jobs.wait(&counter).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Other);
+        assert!(counter.is_panicked());
+
+        // スコープ内のパニックは、すべての完了を待ってから伝播します。
+        let done = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            jobs.scope(|s| {
+                s.spawn(|| panic!("test"));
+                s.spawn(|| {
+                    thread::sleep(Duration::from_millis(10));
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(done.into_inner(), 1);
+
+        // パニックしてもワーカーは動き続けます。
+        let counter = jobs.spawn(|| {});
+        jobs.wait(&counter).unwrap();
+    }
+}
+
+/// ジョブを実行できるスレッドです。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Affinity {
+    /// いずれかのワーカー、または、待機中のスレッドで実行します。
+    #[default]
+    Any,
+    /// メインスレッドでのみ実行します。
+    /// 
+    /// `JobSystem::run_main_thread_jobs`、または、メインスレッドの`JobSystem::wait`で実行されます。
+    /// 
+    Main,
+}
+
+/// ジョブの完了を待ち合わせるカウンタです。
+/// 
+/// 登録されたジョブがすべて終わると完了します。
+/// 複製したカウンタは同じ状態を共有します。
+/// 
+#[derive(Clone, Default)]
+pub struct Counter(Arc<CounterInner>);
+impl Counter {
+    /// 完了済みのカウンタを作成します。
+    pub fn new() -> Counter {
+        Counter::default()
+    }
+
+    /// 完了したかを取得します。
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.0.remaining.load(Ordering::Acquire) == 0
+    }
+
+    /// 登録されたジョブのいずれかがパニックしたかを取得します。
+    #[inline]
+    pub fn is_panicked(&self) -> bool {
+        self.0.panicked.load(Ordering::Acquire)
+    }
+
+    // 未完了のジョブを1つ増やします。
+    fn add(&self) {
+        self.0.remaining.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // ジョブを1つ完了し、すべて終わった場合は後続を実行します。
+    fn complete(&self, shared: &Shared) {
+        if self.0.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let continuations = {
+                let mut continuations = lock(&self.0.continuations);
+                self.0.done.notify_all();
+                mem::take(&mut *continuations)
+            };
+            for continuation in continuations {
+                continuation(shared);
+            }
+        }
+    }
+
+    // 完了時に実行する後続を登録します。完了済みの場合はすぐに実行します。
+    fn on_done(&self, shared: &Shared, continuation: Continuation) {
+        let mut continuations = lock(&self.0.continuations);
+        if self.is_done() {
+            drop(continuations);
+            continuation(shared);
+        } else {
+            continuations.push(continuation);
+        }
+    }
+
+    // 完了するか、時間が経過するまで待ちます。
+    fn park(&self, timeout: Duration) {
+        let continuations = lock(&self.0.continuations);
+        if !self.is_done() {
+            drop(self.0.done.wait_timeout(continuations, timeout));
+        }
+    }
+}
+impl Debug for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Counter")
+            .field("remaining", &self.0.remaining.load(Ordering::Acquire))
+            .field("panicked", &self.is_panicked())
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct CounterInner {
+    remaining: AtomicUsize,                    // 未完了のジョブ数です。
+    panicked: AtomicBool,                      // パニックしたジョブがあるかです。
+    continuations: Mutex<Vec<Continuation>>,   // 完了時に実行する後続です。
+    done: Condvar,                             // 完了を通知します。
+}
+
+// ジョブの処理です。
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+// カウンタの完了時に実行する処理です。
+type Continuation = Box<dyn FnOnce(&Shared) + Send + 'static>;
+
+// 実行待ちのジョブです。
+struct Job {
+    task: Task,       // 処理です。
+    counter: Counter, // 完了を通知するカウンタです。
+}
+impl Job {
+    // 実行し、カウンタに完了を通知します。
+    fn run(self, shared: &Shared) {
+        {
+            crate::profile_scope!("job");
+            if panic::catch_unwind(AssertUnwindSafe(self.task)).is_err() {
+                self.counter.0.panicked.store(true, Ordering::Release);
+            }
+        }
+        self.counter.complete(shared);
+    }
+}
+
+// スレッド間で共有する状態です。
+struct Shared {
+    injector: Injector<Job>,     // ワーカー外から投入されたジョブです。
+    stealers: Vec<Stealer<Job>>, // 各ワーカーの両端キューから盗む口です。
+    main: Mutex<VecDeque<Job>>,  // メインスレッドで実行するジョブです。
+    main_thread: ThreadId,       // メインスレッドです。
+    epoch: AtomicU64,            // ジョブが投入される度に進みます。
+    sleepers: AtomicUsize,       // 休止中のワーカー数です。
+    sleep: Mutex<()>,            // 休止を保護します。
+    wake: Condvar,               // 休止中のワーカーを起こします。
+    shutdown: AtomicBool,        // 終了を要求されたかです。
+}
+impl Shared {
+    // ジョブを投入します。
+    fn push(&self, job: Job, affinity: Affinity) {
+        match affinity {
+            Affinity::Any => {
+                // 自身のワーカーからは、自身の両端キューに積みます。
+                let job = LOCAL.with(|local| match &*local.borrow() {
+                    Some(local) if ptr::eq(local.shared, self) => {
+                        local.worker.push(job);
+                        None
+                    },
+                    _ => Some(job),
+                });
+                if let Some(job) = job {
+                    self.injector.push(job);
+                }
+                self.notify();
+            },
+            Affinity::Main => lock(&self.main).push_back(job),
+        }
+    }
+
+    // 休止中のワーカーを1つ起こします。
+    fn notify(&self) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _sleep = lock(&self.sleep);
+            self.wake.notify_one();
+        }
+    }
+
+    // 実行できるジョブを探します。
+    fn find(&self) -> Option<Job> {
+        let index = LOCAL.with(|local| match &*local.borrow() {
+            Some(local) if ptr::eq(local.shared, self) => {
+                let job = local.worker.pop()
+                    .or_else(|| steal(|| self.injector.steal_batch_and_pop(&local.worker)));
+                Some((local.index, job))
+            },
+            _ => None,
+        });
+        let index = match index {
+            Some((_, Some(job))) => return Some(job),
+            Some((index, None)) => Some(index),
+            None => {
+                if let Some(job) = steal(|| self.injector.steal()) {
+                    return Some(job);
+                }
+                None
+            },
+        };
+
+        // 他のワーカーから盗みます。偏らないよう自身の次から順に試します。
+        let count = self.stealers.len();
+        let start = index.map_or(0, |i| i + 1);
+        (0..count)
+            .map(|i| (start + i) % count)
+            .filter(|i| Some(*i) != index)
+            .find_map(|i| steal(|| self.stealers[i].steal()))
+    }
+
+    // メインスレッドのジョブを取り出します。
+    fn pop_main(&self) -> Option<Job> {
+        lock(&self.main).pop_front()
+    }
+}
+
+// ワーカースレッドの状態です。
+struct Local {
+    shared: *const Shared, // 所属するジョブシステムです。
+    index: usize,          // ワーカー番号です。
+    worker: Worker<Job>,   // 自身の両端キューです。
+}
+
+thread_local! {
+    static LOCAL: RefCell<Option<Local>> = const { RefCell::new(None) };
+}
+
+/// スレッドプールです。
+/// 
+/// 作成したスレッドをメインスレッドとして扱います。
+/// 破棄するとワーカーは残りのジョブを実行してから終了します。
+/// メインスレッドのジョブは実行されずに破棄されるため、そのカウンタは完了しません。
+/// 
+pub struct JobSystem {
+    shared: Arc<Shared>,             // 共有する状態です。
+    threads: Vec<JoinHandle<()>>,    // ワーカースレッドです。
+}
+impl JobSystem {
+    /// 待機中に新しいジョブを確認する間隔です。
+    const PARK_TIMEOUT: Duration = Duration::from_millis(1);
+
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `threads` - ワーカースレッド数です。0の場合は待機するスレッドだけでジョブを実行します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、スレッドを作成できなかった場合はエラーです。
+    /// 
+    pub fn try_new(threads: usize) -> Result<JobSystem> {
+        let workers: Vec<_> = (0..threads).map(|_| Worker::new_lifo()).collect();
+        let shared = Arc::new(Shared {
+            injector: Injector::new(),
+            stealers: workers.iter().map(Worker::stealer).collect(),
+            main: Mutex::new(VecDeque::new()),
+            main_thread: thread::current().id(),
+            epoch: AtomicU64::new(0),
+            sleepers: AtomicUsize::new(0),
+            sleep: Mutex::new(()),
+            wake: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let mut system = JobSystem { shared, threads: Vec::with_capacity(threads) };
+        for (index, worker) in workers.into_iter().enumerate() {
+            let shared = system.shared.clone();
+            let handle = thread::Builder::new()
+                .name(format!("cwago-job-{}", index))
+                .spawn(move || work(shared, index, worker))
+                .map_err(|e| CwagoError::new(ErrorKind::Other, msg!(JOB_SPAWN_FAILED, reason = e)))?;
+            system.threads.push(handle);
+        }
+        Ok(system)
+    }
+
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `threads` - ワーカースレッド数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// スレッドを作成できなかった場合に異常終了します。
+    /// 
+    pub fn new(threads: usize) -> JobSystem {
+        Self::try_new(threads).or_abort()
+    }
+
+    /// 共有のジョブシステムを取得します。
+    /// 
+    /// 初めて呼び出したスレッドがメインスレッドになります。
+    /// 
+    pub fn global() -> &'static JobSystem {
+        static GLOBAL: OnceLock<JobSystem> = OnceLock::new();
+        GLOBAL.get_or_init(|| JobSystem::new(Self::default_threads()))
+    }
+
+    /// 既定のワーカースレッド数を取得します。
+    /// 
+    /// 論理コア数からメインスレッドの分を除いた数で、最低1です。
+    /// 
+    pub fn default_threads() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1).max(1))
+    }
+
+    /// ワーカースレッド数を取得します。
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// ジョブを投入します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 処理です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 完了を待ち合わせるカウンタです。
+    /// 
+    pub fn spawn<F>(&self, f: F) -> Counter
+    where F: FnOnce() + Send + 'static
+    {
+        self.spawn_with(Affinity::Any, &[], f)
+    }
+
+    /// メインスレッドで実行するジョブを投入します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - 処理です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 完了を待ち合わせるカウンタです。
+    /// 
+    pub fn spawn_main<F>(&self, f: F) -> Counter
+    where F: FnOnce() + Send + 'static
+    {
+        self.spawn_with(Affinity::Main, &[], f)
+    }
+
+    /// 依存先の完了後に実行するジョブを投入します。
+    /// 
+    /// 依存先がパニックしても実行されます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `affinity` - 実行するスレッドです。
+    /// * `dependencies` - 先に完了する必要があるカウンタです。
+    /// * `f` - 処理です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 完了を待ち合わせるカウンタです。
+    /// 
+    pub fn spawn_with<F>(&self, affinity: Affinity, dependencies: &[&Counter], f: F) -> Counter
+    where F: FnOnce() + Send + 'static
+    {
+        let counter = Counter::new();
+        self.submit(affinity, dependencies, Box::new(f), &counter);
+        counter
+    }
+
+    // カウンタに登録してジョブを投入します。
+    fn submit(&self, affinity: Affinity, dependencies: &[&Counter], task: Task, counter: &Counter) {
+        counter.add();
+        let job = Job { task, counter: counter.clone() };
+        if dependencies.is_empty() {
+            self.shared.push(job, affinity);
+            return;
+        }
+
+        // 最後に完了した依存先がジョブを投入します。
+        let pending = Arc::new(AtomicUsize::new(dependencies.len()));
+        let job = Arc::new(Mutex::new(Some(job)));
+        for dependency in dependencies {
+            let pending = pending.clone();
+            let job = job.clone();
+            dependency.on_done(&self.shared, Box::new(move |shared| {
+                if pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    if let Some(job) = lock(&job).take() {
+                        shared.push(job, affinity);
+                    }
+                }
+            }));
+        }
+    }
+
+    /// カウンタの完了を待ちます。
+    /// 
+    /// 待つ間は他のジョブを実行します。
+    /// メインスレッドから呼び出した場合は、メインスレッドのジョブも実行します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `counter` - 待つカウンタです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 登録されたジョブのいずれかがパニックした場合はエラーです。
+    /// 
+    pub fn wait(&self, counter: &Counter) -> Result<()> {
+        crate::profile_scope!("job.wait");
+        let main = self.is_main_thread();
+        while !counter.is_done() {
+            let job = if main { self.shared.pop_main() } else { None };
+            match job.or_else(|| self.shared.find()) {
+                Some(job) => job.run(&self.shared),
+                None => counter.park(Self::PARK_TIMEOUT),
+            }
+        }
+        if counter.is_panicked() {
+            return Err(CwagoError::new(ErrorKind::Other, msg!(JOB_PANICKED)));
+        }
+        Ok(())
+    }
+
+    /// メインスレッドのジョブを実行します。
+    /// 
+    /// 呼び出し時点で積まれていたジョブだけを実行します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 実行したジョブ数です。メインスレッド以外から呼び出した場合は何もせず0です。
+    /// 
+    pub fn run_main_thread_jobs(&self) -> usize {
+        if !self.is_main_thread() {
+            return 0;
+        }
+        let count = lock(&self.shared.main).len();
+        for _ in 0..count {
+            match self.shared.pop_main() {
+                Some(job) => job.run(&self.shared),
+                None => break,
+            }
+        }
+        count
+    }
+
+    /// 現在のスレッドがメインスレッドかを取得します。
+    #[inline]
+    pub fn is_main_thread(&self) -> bool {
+        thread::current().id() == self.shared.main_thread
+    }
+
+    /// スタック上のデータを借用するジョブのスコープを作成します。
+    /// 
+    /// スコープ内で投入したジョブがすべて完了してから戻ります。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - スコープで実行する処理です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// `f`の戻り値です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `f`、または、投入したジョブがパニックした場合に、すべての完了を待ってから異常終了します。
+    /// 
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R
+    {
+        let scope = Scope {
+            system: self,
+            counter: Counter::new(),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        // パニックしても、借用が切れる前にすべての完了を待ちます。
+        let waited = self.wait(&scope.counter);
+        match result {
+            Ok(r) => {
+                waited.or_abort();
+                r
+            },
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// スライスの要素を並列に処理します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `items` - 処理する要素です。
+    /// * `chunk_size` - 1つのジョブで処理する要素数です。0は1として扱います。
+    /// * `f` - 要素の添字と要素を受け取る処理です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `f`がパニックした場合に異常終了します。
+    /// 
+    pub fn parallel_for<T, F>(&self, items: &[T], chunk_size: usize, f: F)
+    where T: Sync, F: Fn(usize, &T) + Sync
+    {
+        let chunk_size = chunk_size.max(1);
+        let f = &f;
+        self.scope(|s| {
+            for (c, chunk) in items.chunks(chunk_size).enumerate() {
+                s.spawn(move || {
+                    crate::profile_scope!("job.parallel_for");
+                    for (i, item) in chunk.iter().enumerate() {
+                        f(c * chunk_size + i, item);
+                    }
+                });
+            }
+        });
+    }
+
+    /// スライスの要素を並列に変更します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `items` - 処理する要素です。
+    /// * `chunk_size` - 1つのジョブで処理する要素数です。0は1として扱います。
+    /// * `f` - 要素の添字と要素を受け取る処理です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `f`がパニックした場合に異常終了します。
+    /// 
+    pub fn parallel_for_mut<T, F>(&self, items: &mut [T], chunk_size: usize, f: F)
+    where T: Send, F: Fn(usize, &mut T) + Sync
+    {
+        let chunk_size = chunk_size.max(1);
+        let f = &f;
+        self.scope(|s| {
+            for (c, chunk) in items.chunks_mut(chunk_size).enumerate() {
+                s.spawn(move || {
+                    crate::profile_scope!("job.parallel_for");
+                    for (i, item) in chunk.iter_mut().enumerate() {
+                        f(c * chunk_size + i, item);
+                    }
+                });
+            }
+        });
+    }
+}
+impl Debug for JobSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobSystem")
+            .field("threads", &self.threads.len())
+            .field("main_thread", &self.shared.main_thread)
+            .finish()
+    }
+}
+impl Drop for JobSystem {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        {
+            let _sleep = lock(&self.shared.sleep);
+            self.shared.wake.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// スタック上のデータを借用するジョブのスコープです。
+/// 
+/// `JobSystem::scope`で作成します。
+/// 
+pub struct Scope<'scope, 'env: 'scope> {
+    system: &'scope JobSystem,                  // 投入先です。
+    counter: Counter,                           // スコープ内のジョブのカウンタです。
+    scope: PhantomData<&'scope mut &'scope ()>, // スコープの寿命です。
+    env: PhantomData<&'env mut &'env ()>,       // 借用元の寿命です。
+}
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// ジョブを投入します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `f` - スコープより長く生存するデータを借用できる処理です。
+    /// 
+    pub fn spawn<F>(&'scope self, f: F)
+    where F: FnOnce() + Send + 'scope
+    {
+        let task: Box<dyn FnOnce() + Send + 'scope> = Box::new(f);
+        // Safety: スコープは戻る前にカウンタの完了を待つため、借用はジョブの実行より長く生存します。
+        let task: Task = unsafe { mem::transmute(task) };
+        self.system.submit(Affinity::Any, &[], task, &self.counter);
+    }
+}
+impl Debug for Scope<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+// ワーカースレッドの処理です。
+fn work(shared: Arc<Shared>, index: usize, worker: Worker<Job>) {
+    LOCAL.with(|local| {
+        *local.borrow_mut() = Some(Local { shared: Arc::as_ptr(&shared), index, worker });
+    });
+
+    loop {
+        let epoch = shared.epoch.load(Ordering::SeqCst);
+        if let Some(job) = shared.find() {
+            job.run(&shared);
+            continue;
+        }
+        if shared.shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // 探した後にジョブが投入されていなければ休止します。
+        let sleep = lock(&shared.sleep);
+        shared.sleepers.fetch_add(1, Ordering::SeqCst);
+        if shared.epoch.load(Ordering::SeqCst) == epoch && !shared.shutdown.load(Ordering::SeqCst) {
+            drop(shared.wake.wait(sleep));
+        } else {
+            drop(sleep);
+        }
+        shared.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    LOCAL.with(|local| *local.borrow_mut() = None);
+}
+
+// 再試行しながら盗みます。
+fn steal(mut f: impl FnMut() -> Steal<Job>) -> Option<Job> {
+    loop {
+        match f() {
+            Steal::Success(job) => return Some(job),
+            Steal::Empty => return None,
+            Steal::Retry => continue,
+        }
+    }
+}
+
+// パニックで汚染されていてもロックします。
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -9,4 +9,20 @@
 // =========================
 
 pub use log;
-pub mod hash;
\ No newline at end of file
+pub mod arena;
+pub mod bitset;
+pub mod config;
+pub mod error;
+pub mod hash;
+pub mod job;
+pub mod logging;
+pub mod msg;
+pub mod name;
+pub mod pack;
+pub mod messages;
+pub mod plugin;
+pub mod profile;
+pub mod rand;
+pub mod time;
+pub mod vfs;
+pub mod watch;
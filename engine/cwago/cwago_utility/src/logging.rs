@@ -0,0 +1,771 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/logging.rs
+// (C) 2023 CwagoCommunity.
+//
+//! エンジンのログ機能を提供します。
+//! 
+//! `log`クレートのマクロで出力したログをカテゴリ毎のレベルで絞り込み、
+//! 登録された出力先へフレーム番号付きで書き出します。
+//! 
+//! ```
+//! cwago_utility::logging::init();
+//! cwago_utility::log::error!(size = 16, align = 8; "メモリ確保に失敗しました。");
+//! ```
+// =========================
+
+use std::{
+    collections::{
+        BTreeMap,
+        VecDeque
+    },
+    fmt::{
+        self,
+        Display
+    },
+    fs::{
+        self,
+        File,
+        OpenOptions
+    },
+    io::{
+        self,
+        Write
+    },
+    path::{
+        Path,
+        PathBuf
+    },
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            Ordering
+        },
+        Arc,
+        Mutex,
+        Once,
+        RwLock
+    },
+    thread,
+    time::{
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+use log::{
+    kv::{
+        self,
+        VisitSource
+    },
+    Level,
+    LevelFilter,
+    Log,
+    Metadata,
+    Record
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    config::Config,
+    messages::{
+        LOG_LEVEL_INVALID,
+        LOG_ROTATE_FAILED
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_to(logger: &Logger, level: Level, target: &str, message: fmt::Arguments) {
+        logger.log(&Record::builder()
+            .level(level)
+            .target(target)
+            .args(message)
+            .build());
+    }
+
+    #[test]
+    fn test_category_levels() {
+        let logger = Logger::new();
+        let ring = Arc::new(RingBufferSink::new(16));
+        logger.add_sink(ring.clone());
+        logger.set_default_level(LevelFilter::Warn);
+        logger.set_level("cwago_memory", LevelFilter::Trace);
+        logger.set_level("cwago_memory::pool", LevelFilter::Off);
+
+        log_to(&logger, Level::Info, "cwago_comp::ty", format_args!("a"));
+        log_to(&logger, Level::Error, "cwago_comp::ty", format_args!("b"));
+        log_to(&logger, Level::Debug, "cwago_memory::fix", format_args!("c"));
+        log_to(&logger, Level::Error, "cwago_memory::pool", format_args!("d"));
+        log_to(&logger, Level::Debug, "cwago_memory_extra", format_args!("e"));
+
+        let messages = ring.snapshot().into_iter().map(|e| e.message).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["b", "c"]);
+        assert_eq!(logger.level("cwago_memory::pool::inner"), LevelFilter::Off);
+        assert_eq!(logger.level("cwago_memory"), LevelFilter::Trace);
+        assert_eq!(logger.level("other"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_fields_and_frame() {
+        let logger = Logger::new();
+        let ring = Arc::new(RingBufferSink::new(2));
+        logger.add_sink(ring.clone());
+        logger.set_default_level(LevelFilter::Trace);
+
+        let fields = [("size", 16), ("align", 8)];
+        set_frame(42);
+        logger.log(&Record::builder()
+            .level(Level::Error)
+            .target("cwago_memory::os")
+            .args(format_args!("失敗"))
+            .key_values(&fields)
+            .build());
+        let entry = ring.snapshot().pop().expect("ログが記録されませんでした。");
+        assert_eq!(entry.frame, 42);
+        assert_eq!(entry.fields, vec![
+            ("size".to_string(), "16".to_string()),
+            ("align".to_string(), "8".to_string()),
+        ]);
+        assert!(entry.to_string().ends_with("cwago_memory::os: 失敗 size=16 align=8"));
+
+        // 容量を超えた場合は古いものから捨てられます。
+        for i in 0..3 {
+            log_to(&logger, Level::Info, "t", format_args!("{}", i));
+        }
+        let messages = ring.snapshot().into_iter().map(|e| e.message).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let logger = Logger::new();
+        logger.apply_spec("error, cwago_comp=debug ,cwago_memory::fix=off");
+        assert_eq!(logger.level("x"), LevelFilter::Error);
+        assert_eq!(logger.level("cwago_comp::ty"), LevelFilter::Debug);
+        assert_eq!(logger.level("cwago_memory::fix"), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_configure() {
+        let logger = Logger::new();
+        logger.set_level("cwago_ecs", LevelFilter::Trace);
+        logger.configure(&LogConfig {
+            level: "error".to_string(),
+            categories: BTreeMap::from([
+                ("cwago_memory".to_string(), "debug".to_string()),
+                ("cwago_comp".to_string(), "loud".to_string()),
+            ]),
+        });
+        assert_eq!(logger.level("cwago_utility"), LevelFilter::Error);
+        assert_eq!(logger.level("cwago_memory::dy"), LevelFilter::Debug);
+        assert_eq!(logger.level("cwago_ecs"), LevelFilter::Error, "以前のカテゴリ設定が残っています。");
+        assert_eq!(logger.level("cwago_comp"), LevelFilter::Error, "不正なレベルが適用されました。");
+
+        // 共有ロガーを変えないよう、変更はこのテストのロガーへ適用します。
+        let logger = Arc::new(Logger::new());
+        let target = logger.clone();
+        let mut config = Config::new();
+        register_config_with(&mut config, move |c| target.configure(c)).unwrap();
+        assert_eq!(config.get::<LogConfig>(CONFIG_KEY).unwrap(), LogConfig::default());
+        config.load_str(crate::config::Layer::Project, "[log.categories]\ncwago_memory = \"trace\"\n").unwrap();
+        assert_eq!(config.get::<String>("log.categories.cwago_memory").unwrap(), "trace");
+        assert_eq!(logger.level("cwago_memory::fix"), LevelFilter::Trace, "変更が適用されていません。");
+        assert_eq!(logger.level("cwago_utility"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("cwago_logging_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("engine.log");
+
+        let sink = FileSink::new(&path, 64, 2).expect("ログファイルを作成できませんでした。");
+        let entry = Entry {
+            level: Level::Info,
+            target: "t".to_string(),
+            message: "0123456789012345678901234567890123456789".to_string(),
+            fields: Vec::new(),
+            frame: 0,
+            time: UNIX_EPOCH,
+            thread: None,
+        };
+        for _ in 0..5 {
+            sink.write(&entry);
+        }
+        sink.flush();
+        assert!(path.exists());
+        assert!(dir.join("engine.log.1").exists());
+        assert!(dir.join("engine.log.2").exists());
+        assert!(!dir.join("engine.log.3").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_init_twice() {
+        init();
+        init();
+        log::error!(target: "cwago_utility::logging::tests", "初期化後のログです。");
+    }
+}
+
+/// ログのレベルを決める環境変数名です。
+/// 
+/// `warn,cwago_memory=trace`の形式で、既定レベルとカテゴリ毎のレベルを指定します。
+pub const ENV_VAR: &str = "CWAGO_LOG";
+
+/// 設定のログの項目名です。
+pub const CONFIG_KEY: &str = "log";
+
+/// 現在のフレーム番号です。
+static FRAME: AtomicU64 = AtomicU64::new(0);
+
+/// エンジン全体で共有するロガーです。
+static LOGGER: Logger = Logger::new();
+static ONCE: Once = Once::new();
+
+/// 共有ロガーがlogクレートに設定されたかです。
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// ログ機能を初期化します。
+/// 
+/// 標準エラー出力を出力先に登録し、環境変数`CWAGO_LOG`のレベル指定を適用します。
+/// 何度呼び出しても初期化は1度だけ行われます。
+pub fn init() {
+    ONCE.call_once(|| {
+        LOGGER.add_sink(Arc::new(StderrSink));
+        if let Ok(spec) = std::env::var(ENV_VAR) {
+            LOGGER.apply_spec(&spec);
+        }
+        // 他のロガーが設定済みの場合は、そちらを優先します。
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(LOGGER.max_level());
+            INSTALLED.store(true, Ordering::Release);
+        }
+    });
+}
+
+/// 設定にログの項目を登録します。
+/// 
+/// `log.level`と`log.categories`を宣言し、変更された際に共有ロガーへ適用します。
+/// 
+/// ```toml
+/// [log]
+/// level = "info"
+/// 
+/// [log.categories]
+/// cwago_memory = "trace"
+/// ```
+/// 
+/// # 引数
+/// 
+/// * `config` - 登録する設定です。
+/// 
+/// # 戻り値
+/// 
+/// 既に登録されている場合、エラーを返します。
+/// 
+pub fn register_config(config: &mut Config) -> crate::error::Result<()> {
+    register_config_with(config, |c| LOGGER.configure(c))
+}
+
+// 設定にログの項目を登録し、変更された設定をfへ渡します。
+fn register_config_with(config: &mut Config, f: impl Fn(&LogConfig) + Send + 'static) -> crate::error::Result<()> {
+    config.declare(CONFIG_KEY, &LogConfig::default())?;
+    config.subscribe(CONFIG_KEY, move |config| match config.get::<LogConfig>(CONFIG_KEY) {
+        Ok(c) => f(&c),
+        Err(e) => log::warn!("{}", e),
+    });
+    Ok(())
+}
+
+/// エンジン全体で共有するロガーを取得します。
+pub fn logger() -> &'static Logger {
+    &LOGGER
+}
+
+/// 現在のフレーム番号を設定します。
+/// 
+/// # 引数
+/// 
+/// * `frame` - 以降のログに記録するフレーム番号です。
+/// 
+pub fn set_frame(frame: u64) {
+    FRAME.store(frame, Ordering::Relaxed);
+}
+
+/// 現在のフレーム番号を取得します。
+pub fn frame() -> u64 {
+    FRAME.load(Ordering::Relaxed)
+}
+
+/// 1件のログです。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// レベルです。
+    pub level: Level,
+    /// カテゴリです。
+    pub target: String,
+    /// 本文です。
+    pub message: String,
+    /// 構造化された値です。
+    pub fields: Vec<(String, String)>,
+    /// 記録時のフレーム番号です。
+    pub frame: u64,
+    /// 記録時の時刻です。
+    pub time: SystemTime,
+    /// 記録したスレッド名です。
+    pub thread: Option<String>,
+}
+impl Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        write!(
+            f,
+            "{}.{:03} [{}] {:<5} {}: {}",
+            time.as_secs(),
+            time.subsec_millis(),
+            self.frame,
+            self.level,
+            self.target,
+            self.message
+        )?;
+        for (key, value) in &self.fields {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+// 構造化された値を収集します。
+struct FieldCollector(Vec<(String, String)>);
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// ログの出力先トレイトです。
+pub trait Sink: Send + Sync {
+    /// ログを書き出します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `entry` - 書き出すログです。
+    /// 
+    fn write(&self, entry: &Entry);
+
+    /// バッファを書き出します。
+    fn flush(&self) {}
+}
+
+/// 標準エラー出力へ書き出します。
+pub struct StderrSink;
+impl Sink for StderrSink {
+    fn write(&self, entry: &Entry) {
+        let _ = writeln!(io::stderr().lock(), "{}", entry);
+    }
+}
+
+/// ファイルへ書き出し、一定サイズを超えると世代を繰り上げます。
+/// 
+/// `engine.log`が上限を超えると`engine.log.1`へ、`engine.log.1`は`engine.log.2`へ移動します。
+pub struct FileSink {
+    path: PathBuf,            // 書き出し先のパスです。
+    max_bytes: u64,           // 1ファイルの上限サイズです。
+    max_files: usize,         // 残す過去の世代数です。
+    state: Mutex<FileState>,  // 書き出し中のファイルです。
+}
+struct FileState {
+    file: File,
+    written: u64,
+}
+impl FileSink {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - 書き出し先のパスです。
+    /// * `max_bytes` - 1ファイルの上限サイズです。
+    /// * `max_files` - 残す過去の世代数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、または、ファイルを開けなかった際のエラーです。
+    /// 
+    pub fn new(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<FileSink> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(FileSink {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            state: Mutex::new(FileState { file, written })
+        })
+    }
+
+    // 世代を繰り上げて新しいファイルを開きます。
+    fn rotate(&self, state: &mut FileState) -> io::Result<()> {
+        state.file.flush()?;
+        let numbered = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(numbered(self.max_files));
+            for n in (1..self.max_files).rev() {
+                let from = numbered(n);
+                if from.exists() {
+                    fs::rename(from, numbered(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, numbered(1))?;
+        }
+        state.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        state.written = 0;
+        Ok(())
+    }
+}
+impl Sink for FileSink {
+    fn write(&self, entry: &Entry) {
+        let line = format!("{}\n", entry);
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if state.written > 0 && state.written + line.len() as u64 > self.max_bytes {
+            if let Err(e) = self.rotate(&mut state) {
+                let _ = writeln!(io::stderr(), "{}", msg!(LOG_ROTATE_FAILED, path = self.path.display(), reason = e));
+            }
+        }
+        if state.file.write_all(line.as_bytes()).is_ok() {
+            state.written += line.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            let _ = state.file.flush();
+        }
+    }
+}
+
+/// 直近のログをメモリ上に保持します。
+/// 
+/// クラッシュレポートに直前の状況を添付する用途を想定しています。
+pub struct RingBufferSink {
+    capacity: usize,                 // 保持する最大件数です。
+    entries: Mutex<VecDeque<Entry>>, // 保持しているログです。
+}
+impl RingBufferSink {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `capacity` - 保持する最大件数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn new(capacity: usize) -> RingBufferSink {
+        RingBufferSink {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity))
+        }
+    }
+
+    /// 保持しているログを古い順に複製します。
+    pub fn snapshot(&self) -> Vec<Entry> {
+        match self.entries.lock() {
+            Ok(entries) => entries.iter().cloned().collect(),
+            Err(poisoned) => poisoned.into_inner().iter().cloned().collect(),
+        }
+    }
+
+    /// 保持しているログを古い順に書き出します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `out` - 書き出し先です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 失敗した際、エラーを返します。
+    /// 
+    pub fn dump(&self, out: &mut dyn Write) -> io::Result<()> {
+        for entry in self.snapshot() {
+            writeln!(out, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+impl Sink for RingBufferSink {
+    fn write(&self, entry: &Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry.clone());
+    }
+}
+
+/// ログの設定です。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// 既定のレベルです。
+    pub level: String,
+    /// カテゴリ毎のレベルです。
+    pub categories: BTreeMap<String, String>,
+}
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: LevelFilter::Warn.to_string().to_lowercase(),
+            categories: BTreeMap::new(),
+        }
+    }
+}
+
+/// カテゴリ毎のレベルと出力先を管理するロガーです。
+/// 
+/// カテゴリはログのターゲット(既定ではモジュールパス)で、
+/// `::`区切りで最も長く一致する設定のレベルが使われます。
+pub struct Logger {
+    state: RwLock<LoggerState>,
+}
+struct LoggerState {
+    default: LevelFilter,
+    categories: BTreeMap<String, LevelFilter>,
+    sinks: Vec<Arc<dyn Sink>>,
+}
+impl Logger {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 既定レベルがWarnで出力先の無いインスタンスです。
+    /// 
+    pub const fn new() -> Logger {
+        Logger {
+            state: RwLock::new(LoggerState {
+                default: LevelFilter::Warn,
+                categories: BTreeMap::new(),
+                sinks: Vec::new(),
+            })
+        }
+    }
+
+    /// 出力先を追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `sink` - 追加する出力先です。
+    /// 
+    pub fn add_sink(&self, sink: Arc<dyn Sink>) {
+        self.write_state().sinks.push(sink);
+    }
+
+    /// すべての出力先を削除します。
+    pub fn clear_sinks(&self) {
+        self.write_state().sinks.clear();
+    }
+
+    /// 既定のレベルを設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `level` - カテゴリの設定が無い場合のレベルです。
+    /// 
+    pub fn set_default_level(&self, level: LevelFilter) {
+        self.write_state().default = level;
+        self.update_max_level();
+    }
+
+    /// カテゴリのレベルを設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `category` - `cwago_memory`のようなターゲットの接頭辞です。
+    /// * `level` - レベルです。
+    /// 
+    pub fn set_level(&self, category: &str, level: LevelFilter) {
+        self.write_state().categories.insert(category.to_string(), level);
+        self.update_max_level();
+    }
+
+    /// カテゴリのレベル設定を削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `category` - 削除するカテゴリです。
+    /// 
+    pub fn reset_level(&self, category: &str) {
+        self.write_state().categories.remove(category);
+        self.update_max_level();
+    }
+
+    /// ターゲットに適用されるレベルを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `target` - ログのターゲットです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最も長く一致するカテゴリのレベル、または、既定のレベルです。
+    /// 
+    pub fn level(&self, target: &str) -> LevelFilter {
+        let state = self.read_state();
+        let mut category = target;
+        loop {
+            if let Some(level) = state.categories.get(category) {
+                return *level;
+            }
+            match category.rfind("::") {
+                Some(i) => category = &category[..i],
+                None => return state.default,
+            }
+        }
+    }
+
+    /// `warn,cwago_memory=trace`形式のレベル指定を適用します。
+    /// 
+    /// 解釈できない項目は無視します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `spec` - レベル指定です。
+    /// 
+    pub fn apply_spec(&self, spec: &str) {
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item.split_once('=') {
+                Some((category, level)) => if let Ok(level) = level.trim().parse() {
+                    self.set_level(category.trim(), level);
+                },
+                None => if let Ok(level) = item.parse() {
+                    self.set_default_level(level);
+                },
+            }
+        }
+    }
+
+    /// 設定を適用します。
+    /// 
+    /// 既存のカテゴリ設定は置き換えられ、解釈できないレベルは警告して無視します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `config` - ログの設定です。
+    /// 
+    pub fn configure(&self, config: &LogConfig) {
+        let parse = |level: &str| match level.trim().parse::<LevelFilter>() {
+            Ok(level) => Some(level),
+            Err(_) => {
+                log::warn!("{}", msg!(LOG_LEVEL_INVALID, level = level));
+                None
+            },
+        };
+        let default = parse(&config.level);
+        let categories = config.categories.iter()
+            .filter_map(|(category, level)| parse(level).map(|l| (category.clone(), l)))
+            .collect();
+        {
+            let mut state = self.write_state();
+            if let Some(default) = default {
+                state.default = default;
+            }
+            state.categories = categories;
+        }
+        self.update_max_level();
+    }
+
+    // 設定されたレベルの最大値です。
+    fn max_level(&self) -> LevelFilter {
+        let state = self.read_state();
+        state.categories.values().copied().fold(state.default, |a, b| a.max(b))
+    }
+
+    // 共有ロガーがlogクレートに設定されている場合、上限レベルを更新します。
+    // 他のロガーが設定されている場合は、その上限レベルを変更しません。
+    fn update_max_level(&self) {
+        if std::ptr::eq(self, &LOGGER) && INSTALLED.load(Ordering::Acquire) {
+            log::set_max_level(self.max_level());
+        }
+    }
+
+    fn read_state(&self) -> std::sync::RwLockReadGuard<'_, LoggerState> {
+        match self.state.read() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn write_state(&self) -> std::sync::RwLockWriteGuard<'_, LoggerState> {
+        match self.state.write() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut fields = FieldCollector(Vec::new());
+        let _ = record.key_values().visit(&mut fields);
+        let entry = Entry {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            fields: fields.0,
+            frame: frame(),
+            time: SystemTime::now(),
+            thread: thread::current().name().map(str::to_string),
+        };
+        // 書き出し中に出力先がログを出しても停止しないよう、複製してからロックを外します。
+        let sinks = self.read_state().sinks.clone();
+        for sink in sinks {
+            sink.write(&entry);
+        }
+    }
+
+    fn flush(&self) {
+        let sinks = self.read_state().sinks.clone();
+        for sink in sinks {
+            sink.flush();
+        }
+    }
+}
@@ -0,0 +1,359 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/messages.rs
+// (C) 2023 CwagoCommunity.
+//
+//! cwago_utilityの診断メッセージです。
+// =========================
+
+use crate::msg::Message;
+
+pub(crate) const PLUGIN_IO: Message = Message::new(
+    "utility.plugin.io",
+    "'{path}'の読み込みに失敗しました。({reason})",
+    "Failed to read '{path}'. ({reason})"
+);
+pub(crate) const PLUGIN_PARSE: Message = Message::new(
+    "utility.plugin.parse",
+    "'{path}'の解析に失敗しました。({reason})",
+    "Failed to parse '{path}'. ({reason})"
+);
+pub(crate) const PLUGIN_PARSE_SOURCE: Message = Message::new(
+    "utility.plugin.parse_source",
+    "マニフェストの解析に失敗しました。({reason})",
+    "Failed to parse plugin manifest. ({reason})"
+);
+pub(crate) const PLUGIN_DUPLICATE: Message = Message::new(
+    "utility.plugin.duplicate",
+    "プラグイン'{plugin}'が複数存在します。",
+    "Plugin '{plugin}' is defined more than once."
+);
+pub(crate) const PLUGIN_ENGINE: Message = Message::new(
+    "utility.plugin.engine",
+    "プラグイン'{plugin}'はエンジン{required}を要求していますが、エンジンは{engine}です。",
+    "Plugin '{plugin}' requires engine {required}, but the engine is {engine}."
+);
+pub(crate) const PLUGIN_MISSING: Message = Message::new(
+    "utility.plugin.missing",
+    "プラグイン'{plugin}'が依存する'{dependency} {required}'が見つかりません。",
+    "Plugin '{plugin}' depends on '{dependency} {required}', which was not found."
+);
+pub(crate) const PLUGIN_INCOMPATIBLE: Message = Message::new(
+    "utility.plugin.incompatible",
+    "プラグイン'{plugin}'は'{dependency} {required}'を要求していますが、見つかったのは{found}です。",
+    "Plugin '{plugin}' requires '{dependency} {required}', but found {found}."
+);
+pub(crate) const PLUGIN_CYCLE: Message = Message::new(
+    "utility.plugin.cycle",
+    "プラグインの依存関係が循環しています。({path})",
+    "Plugin dependencies form a cycle. ({path})"
+);
+pub(crate) const PLUGIN_LIBRARY: Message = Message::new(
+    "utility.plugin.library",
+    "プラグイン'{plugin}'のライブラリ'{path}'の読み込みに失敗しました。({reason})",
+    "Failed to load library '{path}' of plugin '{plugin}'. ({reason})"
+);
+pub(crate) const PLUGIN_DIAGNOSTICS: Message = Message::new(
+    "utility.plugin.diagnostics",
+    "プラグインの読み込みで{count}件のエラーが見つかりました。",
+    "Found {count} error(s) while loading plugins."
+);
+pub(crate) const LOG_ROTATE_FAILED: Message = Message::new(
+    "utility.logging.rotate_failed",
+    "ログファイル'{path}'の切り替えに失敗しました。({reason})",
+    "Failed to rotate log file '{path}'. ({reason})"
+);
+pub(crate) const LOG_LEVEL_INVALID: Message = Message::new(
+    "utility.logging.level_invalid",
+    "'{level}'はログのレベルとして解釈できません。",
+    "'{level}' is not a valid log level."
+);
+pub(crate) const CONFIG_SERIALIZE_FAILED: Message = Message::new(
+    "utility.config.serialize_failed",
+    "設定項目'{key}'の値を変換できません。",
+    "Failed to convert the value of config key '{key}'."
+);
+pub(crate) const CONFIG_DUPLICATE: Message = Message::new(
+    "utility.config.duplicate",
+    "設定項目'{key}'は既に宣言されています。",
+    "Config key '{key}' is already declared."
+);
+pub(crate) const CONFIG_DEFAULT_LAYER: Message = Message::new(
+    "utility.config.default_layer",
+    "既定値の層は宣言でのみ変更できます。",
+    "The default layer can only be changed by declarations."
+);
+pub(crate) const CONFIG_FILE: Message = Message::new(
+    "utility.config.file",
+    "設定ファイル'{path}'を読み込めません。",
+    "Failed to load config file '{path}'."
+);
+pub(crate) const CONFIG_NOT_FOUND: Message = Message::new(
+    "utility.config.not_found",
+    "設定項目'{key}'が見つかりません。",
+    "Config key '{key}' was not found."
+);
+pub(crate) const CONFIG_GET_FAILED: Message = Message::new(
+    "utility.config.get_failed",
+    "設定項目'{key}'を読み込めません。",
+    "Failed to read config key '{key}'."
+);
+pub(crate) const CONFIG_VALUE_PARSE: Message = Message::new(
+    "utility.config.value_parse",
+    "設定項目'{key}'の値'{value}'を解釈できません。",
+    "Cannot interpret '{value}' as the value of config key '{key}'."
+);
+pub(crate) const CONFIG_UNKNOWN_KEY: Message = Message::new(
+    "utility.config.unknown_key",
+    "設定項目'{key}'は宣言されていません。",
+    "Config key '{key}' is not declared."
+);
+pub(crate) const CONFIG_TYPE_MISMATCH: Message = Message::new(
+    "utility.config.type_mismatch",
+    "設定項目'{key}'は{expected}である必要がありますが、{found}が指定されました。",
+    "Config key '{key}' expects {expected}, but {found} was given."
+);
+pub(crate) const JOB_SPAWN_FAILED: Message = Message::new(
+    "utility.job.spawn_failed",
+    "ワーカースレッドの作成に失敗しました。({reason})",
+    "Failed to spawn a worker thread. ({reason})"
+);
+pub(crate) const JOB_PANICKED: Message = Message::new(
+    "utility.job.panicked",
+    "ジョブがパニックしました。",
+    "A job panicked."
+);
+pub(crate) const ARENA_INVALID_SLOT: Message = Message::new(
+    "utility.arena.invalid_slot",
+    "スロット{index}は退役していますが、値が格納されています。",
+    "Slot {index} is retired but holds a value."
+);
+pub(crate) const ARENA_INVALID_ENTRY: Message = Message::new(
+    "utility.arena.invalid_entry",
+    "キー{key}が指すスロットは存在しないか、世代値が一致しないか、既に使用されています。",
+    "The slot for key {key} does not exist, has a different version, or is already occupied."
+);
+pub(crate) const ARENA_FULL: Message = Message::new(
+    "utility.arena.full",
+    "スロット数が上限{max}を超えました。",
+    "The number of slots exceeds the limit {max}."
+);
+pub(crate) const ARENA_INVALID_KEY: Message = Message::new(
+    "utility.arena.invalid_key",
+    "無効なキー{key}です。",
+    "Invalid key {key}."
+);
+pub(crate) const RAND_ZERO_STATE: Message = Message::new(
+    "utility.rand.zero_state",
+    "乱数生成器の状態がすべて0です。",
+    "Random generator state is all zeros."
+);
+pub(crate) const RAND_EMPTY_RANGE: Message = Message::new(
+    "utility.rand.empty_range",
+    "空の範囲です。",
+    "The range is empty."
+);
+pub(crate) const TIME_INVALID_SCALE: Message = Message::new(
+    "utility.time.invalid_scale",
+    "時間の倍率{scale}は0以上の有限の値である必要があります。",
+    "Time scale {scale} must be finite and not negative."
+);
+pub(crate) const TIME_ZERO_STEP: Message = Message::new(
+    "utility.time.zero_step",
+    "固定時間刻みは0より大きい必要があります。",
+    "The fixed time step must be greater than zero."
+);
+
+pub(crate) const VFS_INVALID_PATH: Message = Message::new(
+    "utility.vfs.invalid_path",
+    "パス'{path}'はルートより上を指しています。",
+    "Path '{path}' points above the root."
+);
+pub(crate) const VFS_NOT_FOUND: Message = Message::new(
+    "utility.vfs.not_found",
+    "'{path}'が見つかりません。",
+    "'{path}' was not found."
+);
+pub(crate) const VFS_READ_ONLY: Message = Message::new(
+    "utility.vfs.read_only",
+    "'{path}'に書き込めるマウントがありません。",
+    "No writable mount contains '{path}'."
+);
+pub(crate) const VFS_IO: Message = Message::new(
+    "utility.vfs.io",
+    "'{path}'の入出力に失敗しました。",
+    "I/O failed for '{path}'."
+);
+pub(crate) const VFS_NOT_UTF8: Message = Message::new(
+    "utility.vfs.not_utf8",
+    "'{path}'はUTF-8ではありません。",
+    "'{path}' is not valid UTF-8."
+);
+
+pub(crate) const VFS_READ_PANICKED: Message = Message::new(
+    "utility.vfs.read_panicked",
+    "'{path}'の読み込み中に異常終了しました。",
+    "Reading '{path}' panicked."
+);
+pub(crate) const VFS_RESULT_TAKEN: Message = Message::new(
+    "utility.vfs.result_taken",
+    "読み込み結果は取り出し済みです。",
+    "The read result has already been taken."
+);
+
+pub(crate) const PACK_ALIGNMENT: Message = Message::new(
+    "utility.pack.alignment",
+    "境界{alignment}は2の累乗ではありません。",
+    "Alignment {alignment} is not a power of two."
+);
+pub(crate) const PACK_DUPLICATE: Message = Message::new(
+    "utility.pack.duplicate",
+    "'{path}'は既に追加されています。",
+    "'{path}' has already been added."
+);
+pub(crate) const PACK_HASH_COLLISION: Message = Message::new(
+    "utility.pack.hash_collision",
+    "'{path}'と'{other}'のハッシュ値が衝突しています。",
+    "Hash of '{path}' collides with '{other}'."
+);
+pub(crate) const PACK_CORRUPT: Message = Message::new(
+    "utility.pack.corrupt",
+    "パックの形式が不正です。",
+    "The pack file is malformed."
+);
+pub(crate) const PACK_CHECKSUM: Message = Message::new(
+    "utility.pack.checksum",
+    "'{path}'のチェックサムが一致しません。",
+    "Checksum mismatch for '{path}'."
+);
+pub(crate) const PACK_TOC_CHECKSUM: Message = Message::new(
+    "utility.pack.toc_checksum",
+    "パックの目次のチェックサムが一致しません。",
+    "Checksum mismatch for the pack's table of contents."
+);
+pub(crate) const PACK_NOT_FOUND: Message = Message::new(
+    "utility.pack.not_found",
+    "パックに'{path}'がありません。",
+    "'{path}' is not in the pack."
+);
+pub(crate) const PACK_FILE: Message = Message::new(
+    "utility.pack.file",
+    "パック'{path}'を開けません。",
+    "Failed to open pack '{path}'."
+);
+
+/// cwago_packコマンドの使い方です。
+pub const PACK_CLI_USAGE: Message = Message::new(
+    "utility.pack_cli.usage",
+    "使い方: cwago_pack <入力ディレクトリ> <出力ファイル> [--align <バイト数>] [--no-compress]\n        cwago_pack --list <パック>",
+    "usage: cwago_pack <input_dir> <output> [--align <bytes>] [--no-compress]\n       cwago_pack --list <pack>"
+);
+/// cwago_packコマンドで名前がUTF-8でない場合のメッセージです。
+pub const PACK_CLI_NOT_UTF8: Message = Message::new(
+    "utility.pack_cli.not_utf8",
+    "{name}はUTF-8ではありません。",
+    "{name} is not valid UTF-8."
+);
+/// cwago_packコマンドで作成した結果のメッセージです。
+pub const PACK_CLI_PACKED: Message = Message::new(
+    "utility.pack_cli.packed",
+    "{count}個のファイル -> {output}",
+    "{count} files -> {output}"
+);
+
+pub(crate) const WATCH_FAILED: Message = Message::new(
+    "utility.watch.failed",
+    "'{path}'を監視できません。",
+    "Failed to watch '{path}'."
+);
+pub(crate) const WATCH_FALLBACK: Message = Message::new(
+    "utility.watch.fallback",
+    "OSのファイル監視を使えないため、ポーリングで監視します。({reason})",
+    "OS file watching is unavailable; falling back to polling. ({reason})"
+);
+pub(crate) const WATCH_OVERFLOW: Message = Message::new(
+    "utility.watch.overflow",
+    "監視イベントが溢れたため、監視中のパスを走査し直します。",
+    "Watch event queue overflowed; rescanning the watched paths."
+);
+pub(crate) const WATCH_SPAWN_FAILED: Message = Message::new(
+    "utility.watch.spawn_failed",
+    "監視スレッドを作成できません。({reason})",
+    "Failed to spawn the watch thread. ({reason})"
+);
+
+pub(crate) const BITSET_OUT_OF_RANGE: Message = Message::new(
+    "utility.bitset.out_of_range",
+    "ビット集合の容量{capacity}を超えるビットがあります。",
+    "A bit exceeds the bit set capacity {capacity}."
+);
+
+/// cwago_utilityのすべての診断メッセージです。
+pub const MESSAGES: &[&Message] = &[
+    &PLUGIN_IO,
+    &PLUGIN_PARSE,
+    &PLUGIN_PARSE_SOURCE,
+    &PLUGIN_DUPLICATE,
+    &PLUGIN_ENGINE,
+    &PLUGIN_MISSING,
+    &PLUGIN_INCOMPATIBLE,
+    &PLUGIN_CYCLE,
+    &PLUGIN_LIBRARY,
+    &PLUGIN_DIAGNOSTICS,
+    &LOG_ROTATE_FAILED,
+    &LOG_LEVEL_INVALID,
+    &CONFIG_SERIALIZE_FAILED,
+    &CONFIG_DUPLICATE,
+    &CONFIG_DEFAULT_LAYER,
+    &CONFIG_FILE,
+    &CONFIG_NOT_FOUND,
+    &CONFIG_GET_FAILED,
+    &CONFIG_VALUE_PARSE,
+    &CONFIG_UNKNOWN_KEY,
+    &CONFIG_TYPE_MISMATCH,
+    &JOB_SPAWN_FAILED,
+    &JOB_PANICKED,
+    &ARENA_INVALID_SLOT,
+    &ARENA_INVALID_ENTRY,
+    &ARENA_FULL,
+    &ARENA_INVALID_KEY,
+    &RAND_ZERO_STATE,
+    &RAND_EMPTY_RANGE,
+    &TIME_INVALID_SCALE,
+    &TIME_ZERO_STEP,
+    &VFS_INVALID_PATH,
+    &VFS_NOT_FOUND,
+    &VFS_READ_ONLY,
+    &VFS_IO,
+    &VFS_NOT_UTF8,
+    &VFS_READ_PANICKED,
+    &VFS_RESULT_TAKEN,
+    &PACK_ALIGNMENT,
+    &PACK_DUPLICATE,
+    &PACK_HASH_COLLISION,
+    &PACK_CORRUPT,
+    &PACK_CHECKSUM,
+    &PACK_TOC_CHECKSUM,
+    &PACK_NOT_FOUND,
+    &PACK_FILE,
+    &PACK_CLI_USAGE,
+    &PACK_CLI_NOT_UTF8,
+    &PACK_CLI_PACKED,
+    &WATCH_FAILED,
+    &WATCH_FALLBACK,
+    &WATCH_OVERFLOW,
+    &WATCH_SPAWN_FAILED,
+    &BITSET_OUT_OF_RANGE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        crate::msg::check_catalog_source(MESSAGES, include_str!("messages.rs"));
+    }
+}
@@ -0,0 +1,361 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/msg.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 多言語対応の診断メッセージを提供します。
+//! 
+//! メッセージは安定したIDと日本語、英語の文面を持ち、
+//! 実行時に選択した言語で`{名前}`の引数を置換して取得します。
+//! 
+//! ```
+//! use cwago_utility::msg::{Lang, Message, set_lang};
+//! 
+//! const ALLOC_FAILED: Message = Message::new(
+//!     "memory.alloc_failed",
+//!     "{size}バイトのメモリ確保に失敗しました。",
+//!     "Failed to allocate {size} bytes of memory."
+//! );
+//! 
+//! set_lang(Lang::En);
+//! assert_eq!(cwago_utility::msg!(ALLOC_FAILED, size = 16), "Failed to allocate 16 bytes of memory.");
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Display,
+        Write
+    },
+    str::FromStr,
+    sync::atomic::{
+        AtomicU8,
+        Ordering
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREETING: Message = Message::new(
+        "test.greeting",
+        "{name}さん、{count}件です。{{括弧}}",
+        "{count} items for {name}. {{braces}}"
+    );
+
+    #[test]
+    fn test_format() {
+        let name = "cwago";
+        assert_eq!(
+            GREETING.format_in(Lang::Ja, &[("name", &name), ("count", &3)]),
+            "cwagoさん、3件です。{括弧}"
+        );
+        assert_eq!(
+            GREETING.format_in(Lang::En, &[("name", &name), ("count", &3)]),
+            "3 items for cwago. {braces}"
+        );
+
+        // 引数が無い場合は名前のまま残します。
+        assert_eq!(GREETING.format_in(Lang::En, &[]), "{count} items for {name}. {braces}");
+    }
+
+    #[test]
+    fn test_check_catalog_source() {
+        const OPENED: Message = Message::new("test.opened", "{path}を開きました。", "Opened {path}.");
+        const CLOSED: Message = Message::new("test.closed", "{path}を閉じました。", "Closed {path}.");
+        const SOURCE: &str = r#"
+            const OPENED: Message = Message::new(
+                "test.opened",
+                "", ""
+            );
+        "#;
+        check_catalog_source(&[&OPENED], SOURCE);
+        let missing = std::panic::catch_unwind(|| check_catalog_source(&[], SOURCE));
+        assert!(missing.is_err(), "登録し忘れを検出する必要があります。");
+        let undefined = std::panic::catch_unwind(|| check_catalog_source(&[&OPENED, &CLOSED], SOURCE));
+        assert!(undefined.is_err(), "ソースに無いメッセージを検出する必要があります。");
+        let duplicated = std::panic::catch_unwind(|| check_catalog_source(&[&OPENED, &OPENED], SOURCE));
+        assert!(duplicated.is_err(), "IDの重複を検出する必要があります。");
+    }
+
+    #[test]
+    fn test_lang() {
+        assert_eq!("en".parse::<Lang>(), Ok(Lang::En));
+        assert_eq!("ja_JP.UTF-8".parse::<Lang>(), Ok(Lang::Ja));
+        assert!("fr".parse::<Lang>().is_err());
+        assert_eq!(Lang::En.to_string(), "en");
+    }
+}
+
+/// 言語です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+    /// 日本語です。
+    Ja,
+    /// 英語です。
+    En,
+}
+impl Lang {
+    /// 言語コードを取得します。
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lang::Ja => "ja",
+            Lang::En => "en",
+        }
+    }
+}
+impl Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+impl FromStr for Lang {
+    type Err = String;
+
+    /// `ja`、`en_US.UTF-8`のような言語コードから変換します。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or("");
+        match code.to_ascii_lowercase().as_str() {
+            "ja" => Ok(Lang::Ja),
+            "en" => Ok(Lang::En),
+            _ => Err(format!("unknown language '{}'", s)),
+        }
+    }
+}
+
+/// 言語を決める環境変数名です。
+pub const ENV_VAR: &str = "CWAGO_LANG";
+
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+/// 表示する言語を設定します。
+/// 
+/// # 引数
+/// 
+/// * `lang` - 以降のメッセージの言語です。
+/// 
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8 + 1, Ordering::Relaxed);
+}
+
+/// 表示する言語を取得します。
+/// 
+/// # 戻り値
+/// 
+/// 設定された言語、未設定の場合は環境変数`CWAGO_LANG`の言語、どちらも無い場合は日本語です。
+/// 
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Ja,
+        2 => Lang::En,
+        _ => {
+            let lang = std::env::var(ENV_VAR)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Lang::Ja);
+            set_lang(lang);
+            lang
+        },
+    }
+}
+
+/// 診断メッセージです。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    id: &'static str, // 言語に依らない安定したIDです。
+    ja: &'static str, // 日本語の文面です。
+    en: &'static str, // 英語の文面です。
+}
+impl Message {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - `crate.name`形式の安定したIDです。
+    /// * `ja` - 日本語の文面です。
+    /// * `en` - 英語の文面です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub const fn new(id: &'static str, ja: &'static str, en: &'static str) -> Message {
+        Message { id, ja, en }
+    }
+
+    /// IDを取得します。
+    pub fn id(&self) -> &'static str {
+        self.id
+    }
+
+    /// 指定した言語の置換前の文面を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `lang` - 言語です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// `{名前}`を含む文面です。
+    /// 
+    pub fn template(&self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::Ja => self.ja,
+            Lang::En => self.en,
+        }
+    }
+
+    /// 現在の言語で引数を置換した文面を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `args` - 名前と値の組です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 置換後の文面です。
+    /// 
+    pub fn format(&self, args: &[(&str, &dyn Display)]) -> String {
+        self.format_in(lang(), args)
+    }
+
+    /// 指定した言語で引数を置換した文面を取得します。
+    /// 
+    /// `{名前}`を引数で置換し、`{{`と`}}`は括弧そのものとして扱います。
+    /// 
+    /// # 引数
+    /// 
+    /// * `lang` - 言語です。
+    /// * `args` - 名前と値の組です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 置換後の文面です。
+    /// 
+    pub fn format_in(&self, lang: Lang, args: &[(&str, &dyn Display)]) -> String {
+        let template = self.template(lang);
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            out.push_str(&rest[..i]);
+            let tail = &rest[i..];
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                out.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+            match (tail.starts_with('{'), tail.find('}')) {
+                (true, Some(end)) => {
+                    let name = &tail[1..end];
+                    match args.iter().find(|(n, _)| *n == name) {
+                        Some((_, value)) => { let _ = write!(out, "{}", value); },
+                        None => out.push_str(&tail[..=end]),
+                    }
+                    rest = &tail[end + 1..];
+                },
+                _ => {
+                    out.push_str(&tail[..1]);
+                    rest = &tail[1..];
+                },
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+impl Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&[]))
+    }
+}
+
+/// メッセージの一覧を検証します。
+/// 
+/// 各クレートのテストから呼び出し、IDの重複と言語間の引数の不一致を検出します。
+/// 
+/// # 引数
+/// 
+/// * `messages` - メッセージの一覧です。
+/// 
+/// # 異常終了
+/// 
+/// 問題が見つかった場合異常終了します。
+/// 
+pub fn check_catalog(messages: &[&Message]) {
+    let params = |s: &str| {
+        let mut names = s.split('{')
+            .skip(1)
+            .filter_map(|p| p.split_once('}').map(|(n, _)| n.to_string()))
+            .filter(|n| !n.is_empty())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    };
+    for (i, m) in messages.iter().enumerate() {
+        assert!(
+            messages[..i].iter().all(|o| o.id != m.id),
+            "メッセージID'{}'が重複しています。", m.id
+        );
+        assert_eq!(
+            params(m.ja), params(m.en),
+            "メッセージ'{}'の引数が言語間で一致しません。", m.id
+        );
+    }
+}
+
+/// メッセージの一覧が定義をすべて含むかを検証します。
+/// 
+/// `check_catalog`の検証に加え、ソースで`Message::new`に渡したIDと一覧のIDが一致するかを確認します。
+/// 一覧に登録し忘れたメッセージは`check_catalog`では検出できないため、各クレートのテストでは
+/// `include_str!`でメッセージを定義したソースを渡します。
+/// 
+/// # 引数
+/// 
+/// * `messages` - メッセージの一覧です。
+/// * `source` - メッセージを定義したソースです。
+/// 
+/// # 異常終了
+/// 
+/// 問題が見つかった場合異常終了します。
+/// 
+pub fn check_catalog_source(messages: &[&Message], source: &str) {
+    check_catalog(messages);
+    let defined = source.split("Message::new(")
+        .skip(1)
+        .filter_map(|rest| rest.trim_start().strip_prefix('"')?.split_once('"').map(|(id, _)| id))
+        .collect::<Vec<_>>();
+    for id in &defined {
+        assert!(
+            messages.iter().any(|m| m.id == *id),
+            "メッセージ'{}'が一覧に登録されていません。", id
+        );
+    }
+    for m in messages {
+        assert!(
+            defined.contains(&m.id),
+            "メッセージ'{}'の定義がソースにありません。", m.id
+        );
+    }
+}
+
+/// 現在の言語で引数を置換したメッセージを作成します。
+/// 
+/// ```
+/// # use cwago_utility::msg::Message;
+/// const OPEN_FAILED: Message = Message::new("doc.open_failed", "'{path}'を開けません。", "Cannot open '{path}'.");
+/// let text = cwago_utility::msg!(OPEN_FAILED, path = "a.toml");
+/// ```
+#[macro_export]
+macro_rules! msg {
+    ($message:expr) => {
+        $message.format(&[])
+    };
+    ($message:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $message.format(&[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+])
+    };
+}
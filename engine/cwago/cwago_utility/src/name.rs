@@ -0,0 +1,276 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/name.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 同じ文字列を1つに集約した名前を提供します。
+//! 
+//! 文字列はプロセス全体で共有する表に登録され、解放されません。
+//! 同じ文字列の`Name`は同じ領域を指すため、比較とハッシュはポインタだけで済みます。
+//! 
+//! ```
+//! use cwago_utility::name::Name;
+//! 
+//! let a = Name::new("transform");
+//! let b = Name::new(&String::from("transform"));
+//! assert_eq!(a, b);
+//! assert_eq!(a.as_str(), "transform");
+//! ```
+// =========================
+
+use std::{
+    cmp::Ordering,
+    fmt::{
+        self,
+        Debug,
+        Display
+    },
+    hash::{
+        Hash,
+        Hasher
+    },
+    ops::Deref,
+    ptr,
+    sync::{
+        OnceLock,
+        RwLock
+    }
+};
+
+use serde::{
+    de::{
+        self,
+        Visitor
+    },
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+
+use crate::hash::FxHashSet;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::hash::FxHashMap;
+
+    #[test]
+    fn test_intern() {
+        let a = Name::new("test.name.a");
+        let b = Name::new(&format!("test.name.{}", "a"));
+        assert_eq!(a, b);
+        assert!(ptr::eq(a.as_str(), b.as_str()), "同じ領域を指す必要があります。");
+        assert_ne!(a, Name::new("test.name.b"));
+        assert_eq!(&*a, "test.name.a");
+        assert_eq!(a.to_string(), "test.name.a");
+        assert!(Name::new("test.name.a") < Name::new("test.name.b"));
+
+        let mut map = FxHashMap::default();
+        map.insert(a, 1);
+        assert_eq!(map.get(&b), Some(&1));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(Name::get("test.lookup.missing"), None);
+        Name::register_static(&["test.lookup.static"]);
+        let name = Name::get("test.lookup.static").expect("事前登録された名前が見つかりません。");
+        assert!(ptr::eq(name.as_str(), "test.lookup.static"), "静的な文字列をそのまま使う必要があります。");
+        assert_eq!(Name::from_static("test.lookup.static"), name);
+    }
+
+    #[test]
+    fn test_threads() {
+        let names: Vec<Name> = (0..8)
+            .map(|_| thread::spawn(|| Name::new("test.threads")))
+            .map(|h| h.join().unwrap())
+            .collect();
+        assert!(names.iter().all(|n| *n == names[0]));
+    }
+
+    #[test]
+    fn test_serde() {
+        let name = Name::new("test.serde");
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(json, "\"test.serde\"");
+        let de: Name = serde_json::from_str(&json).unwrap();
+        assert_eq!(de, name);
+    }
+}
+
+/// 集約された名前です。
+/// 
+/// 比較とハッシュは文字列の位置で行います。
+/// そのため`&str`をキーにした検索はできず、ハッシュ値は実行毎に変わります。
+/// 保存する場合は`as_str`を`StableHasher`に渡します。
+/// 
+#[derive(Clone, Copy)]
+pub struct Name(&'static str);
+impl Name {
+    /// 名前を取得します。未登録の場合は登録します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - 文字列です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 名前です。
+    /// 
+    pub fn new(name: &str) -> Name {
+        Self::intern(name, || Box::leak(name.into()))
+    }
+
+    /// 静的な文字列から名前を取得します。
+    /// 
+    /// 未登録の場合は、文字列を複製せずにそのまま登録します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - 文字列です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 名前です。
+    /// 
+    pub fn from_static(name: &'static str) -> Name {
+        Self::intern(name, || name)
+    }
+
+    /// 静的な文字列をまとめて登録します。
+    /// 
+    /// 起動時に既知の名前を登録しておくと、以降の取得で文字列を複製しません。
+    /// 
+    /// # 引数
+    /// 
+    /// * `names` - 登録する文字列です。
+    /// 
+    pub fn register_static(names: &[&'static str]) {
+        let mut table = table().write().unwrap_or_else(|e| e.into_inner());
+        table.extend(names.iter().copied());
+    }
+
+    /// 登録済みの名前を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - 文字列です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 名前、未登録の場合はNoneです。
+    /// 
+    pub fn get(name: &str) -> Option<Name> {
+        let table = table().read().unwrap_or_else(|e| e.into_inner());
+        table.get(name).map(|s| Name(s))
+    }
+
+    /// 文字列を取得します。
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+
+    // 登録済みならそれを、未登録ならleakが返す文字列を登録して返します。
+    fn intern(name: &str, leak: impl FnOnce() -> &'static str) -> Name {
+        if let Some(name) = Self::get(name) {
+            return name;
+        }
+        let mut table = table().write().unwrap_or_else(|e| e.into_inner());
+        // 読み込みから書き込みの間に、他のスレッドが登録している場合があります。
+        if let Some(s) = table.get(name) {
+            return Name(s);
+        }
+        let s = leak();
+        table.insert(s);
+        Name(s)
+    }
+}
+impl PartialEq for Name {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+impl Eq for Name {}
+impl Hash for Name {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ptr::hash(self.0.as_ptr(), state)
+    }
+}
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Name {
+    /// 文字列の辞書順で比較します。
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+impl Deref for Name {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+impl AsRef<str> for Name {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.0, f)
+    }
+}
+impl Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.0, f)
+    }
+}
+impl Serialize for Name {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor)
+    }
+}
+
+// 一時的な文字列を確保せずに名前を取得します。
+struct NameVisitor;
+impl Visitor<'_> for NameVisitor {
+    type Value = Name;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Name, E> {
+        Ok(Name::new(v))
+    }
+}
+
+// 登録済みの文字列の表です。
+fn table() -> &'static RwLock<FxHashSet<&'static str>> {
+    static TABLE: OnceLock<RwLock<FxHashSet<&'static str>>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
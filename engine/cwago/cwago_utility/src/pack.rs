@@ -0,0 +1,879 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/pack.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 配布用にファイルをまとめるパック形式を提供します。
+//! 
+//! 数値はすべてリトルエンディアンです。
+//! 
+//! ```text
+//! ヘッダ(40バイト)
+//!   0  b"CWPK"
+//!   4  u16 版
+//!   6  u16 予約
+//!   8  u32 データの境界
+//!  12  u32 項目数
+//!  16  u64 目次の位置
+//!  24  u64 目次のバイト数
+//!  32  u32 目次のCRC-32
+//!  36  u32 予約
+//! データ(各項目は境界に揃えて配置)
+//! 目次
+//!   項目(48バイト)をパスのハッシュ値の順に並べ、続けてパスの文字列を置きます。
+//!   0  u64 パスの`stable_hash_str`によるハッシュ値
+//!   8  u64 データの位置
+//!  16  u64 格納したバイト数
+//!  24  u64 元のバイト数
+//!  32  u32 元のデータのCRC-32
+//!  36  u8  圧縮形式
+//!  37  u8x3 予約
+//!  40  u32 パスの文字列の位置(文字列の先頭から)
+//!  44  u32 パスのバイト数
+//! ```
+//! 
+//! 非圧縮の項目は境界に揃うため、ファイル全体をメモリマップしてそのまま参照できます。
+//! 
+//! ```
+//! use std::io::Cursor;
+//! use cwago_utility::pack::{Compression, PackReader, PackWriter};
+//! 
+//! let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+//! writer.add("textures/grass.png", &[7; 1000], Compression::Lz4).unwrap();
+//! let file = writer.finish().unwrap();
+//! 
+//! let reader = PackReader::new(file).unwrap();
+//! assert_eq!(reader.read("textures/grass.png").unwrap(), [7; 1000]);
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Debug
+    },
+    fs::File,
+    io::{
+        self,
+        BufReader,
+        Cursor,
+        Read,
+        Seek,
+        SeekFrom,
+        Write
+    },
+    path::Path,
+    sync::{
+        Mutex,
+        MutexGuard
+    }
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result,
+        ResultExt
+    },
+    hash::{
+        crc32,
+        stable_hash_str,
+        Crc32,
+        FxHashMap
+    },
+    messages::{
+        PACK_ALIGNMENT,
+        PACK_CHECKSUM,
+        PACK_CORRUPT,
+        PACK_DUPLICATE,
+        PACK_FILE,
+        PACK_HASH_COLLISION,
+        PACK_NOT_FOUND,
+        PACK_TOC_CHECKSUM
+    },
+    msg,
+    vfs::{
+        self,
+        Backend,
+        DirEntry,
+        EntryKind,
+        Metadata
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> PackReader<Cursor<Vec<u8>>> {
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.set_alignment(64).unwrap();
+        writer.add("a.txt", b"hello", Compression::None).unwrap();
+        writer.add("/dir/b.bin", &[1u8; 4096], Compression::Lz4).unwrap();
+        writer.add("dir/sub/c.txt", b"c", Compression::Lz4).unwrap();
+        writer.add("empty", b"", Compression::None).unwrap();
+        PackReader::new(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        crate::logging::init();
+        let reader = sample();
+        assert_eq!(reader.len(), 4);
+        assert_eq!(reader.read("a.txt").unwrap(), b"hello");
+        assert_eq!(reader.read("dir/b.bin").unwrap(), [1u8; 4096]);
+        assert_eq!(reader.read("./dir/sub/c.txt").unwrap(), b"c");
+        assert_eq!(reader.read("empty").unwrap(), b"");
+        assert_eq!(reader.read("missing").unwrap_err().kind(), ErrorKind::NotFound);
+
+        let b = reader.find("dir/b.bin").unwrap();
+        assert_eq!(b.compression(), Compression::Lz4);
+        assert!(b.stored_size() < b.size(), "圧縮される必要があります。");
+        // 縮まない場合は非圧縮で格納します。
+        assert_eq!(reader.find("dir/sub/c.txt").unwrap().compression(), Compression::None);
+        for entry in reader.entries() {
+            assert_eq!(entry.offset() % 64, 0, "データは境界に揃う必要があります。");
+            assert_eq!(entry.hash(), stable_hash_str(entry.path()));
+        }
+        let mut paths: Vec<_> = reader.entries().map(|e| e.path().to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, ["a.txt", "dir/b.bin", "dir/sub/c.txt", "empty"]);
+    }
+
+    #[test]
+    fn test_stream() {
+        crate::logging::init();
+        let reader = sample();
+        let mut data = String::new();
+        reader.stream("a.txt").unwrap().read_to_string(&mut data).unwrap();
+        assert_eq!(data, "hello");
+
+        let mut data = Vec::new();
+        let mut stream = reader.stream("dir/b.bin").unwrap();
+        let mut buf = [0u8; 100];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(data, [1u8; 4096]);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        crate::logging::init();
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add("a", b"payload", Compression::None).unwrap();
+        let mut bytes = writer.finish().unwrap().into_inner();
+
+        // データを壊すとチェックサムで検出します。
+        let offset = PackReader::new(Cursor::new(bytes.clone())).unwrap().find("a").unwrap().offset() as usize;
+        bytes[offset] ^= 0xff;
+        let reader = PackReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(reader.read("a").unwrap_err().kind(), ErrorKind::Parse);
+        let mut sink = Vec::new();
+        assert!(reader.stream("a").unwrap().read_to_end(&mut sink).is_err());
+
+        // 目次を壊すと開けません。
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert_eq!(PackReader::new(Cursor::new(bytes.clone())).unwrap_err().kind(), ErrorKind::Parse);
+        assert_eq!(PackReader::new(Cursor::new(b"CWPX".to_vec())).unwrap_err().kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn test_header() {
+        crate::logging::init();
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add("a", b"payload", Compression::None).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        // 目次のバイト数がストリームを超える場合は確保せずに拒否します。
+        let mut huge = bytes.clone();
+        huge[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(PackReader::new(Cursor::new(huge)).unwrap_err().kind(), ErrorKind::Parse);
+        let mut past = bytes.clone();
+        past[24..32].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        assert_eq!(PackReader::new(Cursor::new(past)).unwrap_err().kind(), ErrorKind::Parse);
+
+        // 境界が2の累乗でない場合は拒否します。
+        for alignment in [0u32, 3] {
+            let mut bad = bytes.clone();
+            bad[8..12].copy_from_slice(&alignment.to_le_bytes());
+            assert_eq!(PackReader::new(Cursor::new(bad)).unwrap_err().kind(), ErrorKind::Parse);
+        }
+
+        // 目次のチェックサムのエラーはファイルのパスを含みます。
+        let mut broken = bytes.clone();
+        let last = broken.len() - 1;
+        broken[last] ^= 0xff;
+        let path = std::env::temp_dir().join(format!("cwago_pack_header_{}.cwpk", std::process::id()));
+        std::fs::write(&path, broken).unwrap();
+        let e = PackReader::open(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        assert!(e.message().contains(&path.display().to_string()), "パスが含まれていません: {}", e);
+    }
+
+    #[test]
+    fn test_entry_size() {
+        crate::logging::init();
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add("raw", b"payload", Compression::None).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add("lz4", &[7u8; 4096], Compression::Lz4).unwrap();
+        let lz4 = writer.finish().unwrap().into_inner();
+
+        // 目次の元のバイト数を書き換え、チェックサムを計算し直します。
+        let with_size = |bytes: &[u8], size: u64| {
+            let mut bytes = bytes.to_vec();
+            let toc_offset = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+            let toc_size = u64::from_le_bytes(bytes[24..32].try_into().unwrap()) as usize;
+            bytes[toc_offset + 24..toc_offset + 32].copy_from_slice(&size.to_le_bytes());
+            let crc = crc32(&bytes[toc_offset..toc_offset + toc_size]);
+            bytes[32..36].copy_from_slice(&crc.to_le_bytes());
+            bytes
+        };
+
+        // 格納したバイト数から取り得ない大きさは、確保する前に拒否します。
+        assert!(PackReader::new(Cursor::new(with_size(&bytes, 7))).is_ok());
+        for size in [6, 8, 1 << 62] {
+            assert_eq!(PackReader::new(Cursor::new(with_size(&bytes, size))).unwrap_err().kind(), ErrorKind::Parse);
+        }
+        assert!(PackReader::new(Cursor::new(with_size(&lz4, 4096))).is_ok());
+        for size in [1 << 62, u64::MAX] {
+            assert_eq!(PackReader::new(Cursor::new(with_size(&lz4, size))).unwrap_err().kind(), ErrorKind::Parse);
+        }
+    }
+
+    #[test]
+    fn test_writer_errors() {
+        crate::logging::init();
+        let mut writer = PackWriter::new(Cursor::new(Vec::new())).unwrap();
+        assert_eq!(writer.set_alignment(3).unwrap_err().kind(), ErrorKind::InvalidArgument);
+        writer.add("a", b"1", Compression::None).unwrap();
+        assert_eq!(writer.add("./a", b"2", Compression::None).unwrap_err().kind(), ErrorKind::InvalidArgument);
+        assert_eq!(writer.add("../a", b"2", Compression::None).unwrap_err().kind(), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_vfs() {
+        crate::logging::init();
+        let fs = vfs::Vfs::new();
+        fs.mount("assets", 0, sample()).unwrap();
+        assert_eq!(fs.read("assets/dir/sub/c.txt").unwrap(), b"c");
+        let names: Vec<_> = fs.read_dir("assets/dir").unwrap().into_iter().map(|e| (e.name, e.kind)).collect();
+        assert_eq!(names, [("b.bin".to_string(), EntryKind::File), ("sub".to_string(), EntryKind::Dir)]);
+        assert_eq!(fs.metadata("assets/dir/b.bin"), Some(Metadata { kind: EntryKind::File, len: 4096 }));
+        assert!(fs.write("assets/new", b"").is_err());
+    }
+}
+
+// ファイルの識別子です。
+const MAGIC: [u8; 4] = *b"CWPK";
+
+/// 形式の版です。
+/// 
+/// 版2から目次のハッシュ値はパスの終端を含めて計算します。
+pub const VERSION: u16 = 2;
+
+/// 既定のデータの境界です。
+pub const DEFAULT_ALIGNMENT: u32 = 16;
+
+// ヘッダのバイト数です。
+const HEADER_SIZE: u64 = 40;
+
+// 目次の項目のバイト数です。
+const ENTRY_SIZE: usize = 48;
+
+// LZ4のブロックが格納したバイト数あたりに展開できる最大の倍率です。
+const LZ4_MAX_RATIO: u64 = 255;
+
+// LZ4のブロックの展開で倍率に加えて増え得るバイト数です。
+const LZ4_MAX_EXTRA: u64 = 16;
+
+/// 項目の圧縮形式です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    /// 圧縮しません。
+    #[default]
+    None,
+    /// LZ4のブロック形式で圧縮します。
+    Lz4,
+}
+impl Compression {
+    // 形式の値を取得します。
+    fn code(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+        }
+    }
+
+    // 形式の値から変換します。
+    fn from_code(code: u8) -> Option<Compression> {
+        match code {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+}
+
+// 目次の項目です。
+#[derive(Debug, Clone)]
+struct TocEntry {
+    hash: u64,                // パスのハッシュ値です。
+    offset: u64,              // データの位置です。
+    stored_size: u64,         // 格納したバイト数です。
+    size: u64,                // 元のバイト数です。
+    crc: u32,                 // 元のデータのCRC-32です。
+    compression: Compression, // 圧縮形式です。
+    path: String,             // 正規化したパスです。
+}
+
+/// パックを書き込みます。
+pub struct PackWriter<W: Write + Seek> {
+    out: W,                        // 出力先です。
+    alignment: u32,                // データの境界です。
+    position: u64,                 // 次に書き込む位置です。
+    entries: Vec<TocEntry>,        // 書き込んだ項目です。
+    hashes: FxHashMap<u64, usize>, // パスのハッシュ値から項目の位置への表です。
+}
+impl<W: Write + Seek> PackWriter<W> {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `out` - 出力先です。先頭から書き込みます。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、書き込めない場合はエラーです。
+    /// 
+    pub fn new(mut out: W) -> Result<PackWriter<W>> {
+        // ヘッダは最後に書き直します。
+        out.seek(SeekFrom::Start(0))?;
+        out.write_all(&[0; HEADER_SIZE as usize])?;
+        Ok(PackWriter {
+            out,
+            alignment: DEFAULT_ALIGNMENT,
+            position: HEADER_SIZE,
+            entries: Vec::new(),
+            hashes: FxHashMap::default(),
+        })
+    }
+
+    /// 以降に追加するデータの境界を設定します。
+    /// 
+    /// メモリマップする場合はページサイズを指定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `alignment` - 2の累乗のバイト数です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 2の累乗でない場合はエラーです。
+    /// 
+    pub fn set_alignment(&mut self, alignment: u32) -> Result<()> {
+        if !alignment.is_power_of_two() {
+            return Err(CwagoError::new(ErrorKind::InvalidArgument, msg!(PACK_ALIGNMENT, alignment = alignment)));
+        }
+        self.alignment = alignment;
+        Ok(())
+    }
+
+    /// 項目を追加します。
+    /// 
+    /// 圧縮しても縮まない場合は非圧縮で格納します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。`vfs::normalize`で正規化して格納します。
+    /// * `data` - 内容です。
+    /// * `compression` - 圧縮形式です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// パスが不正、重複、または、書き込めない場合はエラーです。
+    /// 
+    pub fn add(&mut self, path: &str, data: &[u8], compression: Compression) -> Result<()> {
+        crate::profile_scope!("pack.add");
+        let path = vfs::normalize(path)?;
+        let hash = stable_hash_str(&path);
+        if let Some(other) = self.hashes.get(&hash).map(|&i| &self.entries[i]) {
+            let message = if other.path == path {
+                msg!(PACK_DUPLICATE, path = path)
+            } else {
+                msg!(PACK_HASH_COLLISION, path = path, other = other.path)
+            };
+            return Err(CwagoError::new(ErrorKind::InvalidArgument, message));
+        }
+
+        let compressed = match compression {
+            Compression::None => None,
+            Compression::Lz4 => Some(lz4_flex::block::compress(data)).filter(|c| c.len() < data.len()),
+        };
+        let (stored, compression) = match &compressed {
+            Some(c) => (c.as_slice(), compression),
+            None => (data, Compression::None),
+        };
+
+        let offset = self.pad(self.alignment as u64)?;
+        self.out.write_all(stored)?;
+        self.position += stored.len() as u64;
+        self.hashes.insert(hash, self.entries.len());
+        self.entries.push(TocEntry {
+            hash,
+            offset,
+            stored_size: stored.len() as u64,
+            size: data.len() as u64,
+            crc: crc32(data),
+            compression,
+            path,
+        });
+        Ok(())
+    }
+
+    // 境界まで0で埋め、揃えた位置を返します。
+    fn pad(&mut self, alignment: u64) -> Result<u64> {
+        let aligned = self.position.next_multiple_of(alignment);
+        io::copy(&mut io::repeat(0).take(aligned - self.position), &mut self.out)?;
+        self.position = aligned;
+        Ok(aligned)
+    }
+
+    /// 目次とヘッダを書き込んで完了します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 出力先、書き込めない場合はエラーです。
+    /// 
+    pub fn finish(mut self) -> Result<W> {
+        self.entries.sort_by_key(|e| e.hash);
+
+        let mut toc = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        let mut paths = Vec::new();
+        for entry in &self.entries {
+            toc.extend_from_slice(&entry.hash.to_le_bytes());
+            toc.extend_from_slice(&entry.offset.to_le_bytes());
+            toc.extend_from_slice(&entry.stored_size.to_le_bytes());
+            toc.extend_from_slice(&entry.size.to_le_bytes());
+            toc.extend_from_slice(&entry.crc.to_le_bytes());
+            toc.extend_from_slice(&[entry.compression.code(), 0, 0, 0]);
+            toc.extend_from_slice(&(paths.len() as u32).to_le_bytes());
+            toc.extend_from_slice(&(entry.path.len() as u32).to_le_bytes());
+            paths.extend_from_slice(entry.path.as_bytes());
+        }
+        toc.extend_from_slice(&paths);
+
+        let toc_offset = self.pad(8)?;
+        self.out.write_all(&toc)?;
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&self.alignment.to_le_bytes());
+        header.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        header.extend_from_slice(&toc_offset.to_le_bytes());
+        header.extend_from_slice(&(toc.len() as u64).to_le_bytes());
+        header.extend_from_slice(&crc32(&toc).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+impl<W: Write + Seek> Debug for PackWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackWriter")
+            .field("alignment", &self.alignment)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+/// 項目の情報です。
+#[derive(Clone, Copy)]
+pub struct EntryInfo<'a> {
+    entry: &'a TocEntry, // 目次の項目です。
+}
+impl<'a> EntryInfo<'a> {
+    /// パスを取得します。
+    pub fn path(&self) -> &'a str {
+        &self.entry.path
+    }
+
+    /// パスのハッシュ値を取得します。
+    pub fn hash(&self) -> u64 {
+        self.entry.hash
+    }
+
+    /// データの位置を取得します。
+    pub fn offset(&self) -> u64 {
+        self.entry.offset
+    }
+
+    /// 元のバイト数を取得します。
+    pub fn size(&self) -> u64 {
+        self.entry.size
+    }
+
+    /// 格納したバイト数を取得します。
+    pub fn stored_size(&self) -> u64 {
+        self.entry.stored_size
+    }
+
+    /// 圧縮形式を取得します。
+    pub fn compression(&self) -> Compression {
+        self.entry.compression
+    }
+
+    /// 元のデータのCRC-32を取得します。
+    pub fn crc(&self) -> u32 {
+        self.entry.crc
+    }
+}
+impl Debug for EntryInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.entry, f)
+    }
+}
+
+/// パックを読み込みます。
+/// 
+/// 読み込み専用の`vfs::Backend`としてマウントできます。
+/// 
+pub struct PackReader<R: Read + Seek> {
+    source: Mutex<R>,        // 入力元です。
+    entries: Vec<TocEntry>,  // ハッシュ値の順の項目です。
+}
+impl PackReader<BufReader<File>> {
+    /// ファイルを開きます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パックのファイルです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、開けない、または、形式が不正な場合はエラーです。
+    /// 
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        File::open(path)
+            .map_err(CwagoError::from)
+            .and_then(|file| PackReader::load(BufReader::new(file), Some(path)))
+            .with_context(|| msg!(PACK_FILE, path = path.display()))
+    }
+}
+impl<R: Read + Seek> PackReader<R> {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `source` - 入力元です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、形式が不正な場合はエラーです。
+    /// 
+    pub fn new(source: R) -> Result<Self> {
+        Self::load(source, None)
+    }
+
+    // 読み込みます。パスはチェックサムのエラーに使います。
+    fn load(mut source: R, path: Option<&Path>) -> Result<Self> {
+        let end = source.seek(SeekFrom::End(0))?;
+        let mut header = [0u8; HEADER_SIZE as usize];
+        source.seek(SeekFrom::Start(0))?;
+        source.read_exact(&mut header).map_err(|e| CwagoError::from_source(ErrorKind::Parse, e))?;
+        if header[0..4] != MAGIC || u16_at(&header, 4) != VERSION {
+            return Err(corrupt());
+        }
+        let alignment = u32_at(&header, 8);
+        if !alignment.is_power_of_two() {
+            return Err(CwagoError::new(ErrorKind::Parse, msg!(PACK_ALIGNMENT, alignment = alignment)));
+        }
+        let count = u32_at(&header, 12) as usize;
+        let toc_offset = u64_at(&header, 16);
+        let toc_size = u64_at(&header, 24);
+        let toc_crc = u32_at(&header, 32);
+
+        // 目次は確保する前に、ストリームに収まるかを確認します。
+        if toc_offset < HEADER_SIZE || toc_offset.checked_add(toc_size).is_none_or(|e| e > end) {
+            return Err(corrupt());
+        }
+        let table_size = count.checked_mul(ENTRY_SIZE).filter(|s| (*s as u64) <= toc_size).ok_or_else(corrupt)?;
+        let mut toc = vec![0u8; toc_size as usize];
+        source.seek(SeekFrom::Start(toc_offset))?;
+        source.read_exact(&mut toc).map_err(|e| CwagoError::from_source(ErrorKind::Parse, e))?;
+        if crc32(&toc) != toc_crc {
+            let message = match path {
+                Some(path) => msg!(PACK_CHECKSUM, path = path.display()),
+                None => msg!(PACK_TOC_CHECKSUM),
+            };
+            return Err(CwagoError::new(ErrorKind::Parse, message));
+        }
+
+        let (table, paths) = toc.split_at(table_size);
+        let mut entries = Vec::with_capacity(count);
+        for raw in table.chunks_exact(ENTRY_SIZE) {
+            let start = u32_at(raw, 40) as usize;
+            let len = u32_at(raw, 44) as usize;
+            let path = paths.get(start..start + len)
+                .and_then(|p| std::str::from_utf8(p).ok())
+                .ok_or_else(corrupt)?;
+            // データはヘッダと目次の間に収まる必要があります。
+            let offset = u64_at(raw, 8);
+            let stored_size = u64_at(raw, 16);
+            if offset < HEADER_SIZE || offset.checked_add(stored_size).is_none_or(|e| e > toc_offset) {
+                return Err(corrupt());
+            }
+            // 展開後のバイト数は読み込み時に確保するため、格納したバイト数から取り得る範囲に限ります。
+            let size = u64_at(raw, 24);
+            let compression = Compression::from_code(raw[36]).ok_or_else(corrupt)?;
+            let valid = match compression {
+                Compression::None => size == stored_size,
+                Compression::Lz4 => stored_size.checked_mul(LZ4_MAX_RATIO)
+                    .and_then(|max| max.checked_add(LZ4_MAX_EXTRA))
+                    .is_some_and(|max| size <= max),
+            };
+            if !valid {
+                return Err(corrupt());
+            }
+            entries.push(TocEntry {
+                hash: u64_at(raw, 0),
+                offset,
+                stored_size,
+                size,
+                crc: u32_at(raw, 32),
+                compression,
+                path: path.to_string(),
+            });
+        }
+        if !entries.is_sorted_by_key(|e| e.hash) {
+            return Err(corrupt());
+        }
+        Ok(PackReader { source: Mutex::new(source), entries })
+    }
+
+    /// 項目数を取得します。
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 空かを取得します。
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 項目をハッシュ値の順に走査します。
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = EntryInfo<'_>> + '_ {
+        self.entries.iter().map(|entry| EntryInfo { entry })
+    }
+
+    /// 項目を探します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 項目の情報、無い場合はNoneです。
+    /// 
+    pub fn find(&self, path: &str) -> Option<EntryInfo<'_>> {
+        let path = vfs::normalize(path).ok()?;
+        self.find_normalized(&path).map(|entry| EntryInfo { entry })
+    }
+
+    // 正規化済みのパスで項目を探します。
+    fn find_normalized(&self, path: &str) -> Option<&TocEntry> {
+        let hash = stable_hash_str(path);
+        let i = self.entries.binary_search_by_key(&hash, |e| e.hash).ok()?;
+        Some(&self.entries[i]).filter(|e| e.path == path)
+    }
+
+    // 項目を取得します。無い場合はエラーです。
+    fn get(&self, path: &str) -> Result<&TocEntry> {
+        let path = vfs::normalize(path)?;
+        self.find_normalized(&path)
+            .ok_or_else(|| CwagoError::new(ErrorKind::NotFound, msg!(PACK_NOT_FOUND, path = path)))
+    }
+
+    // 入力元をロックします。
+    fn source(&self) -> MutexGuard<'_, R> {
+        self.source.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // 格納したデータを読み込みます。
+    fn read_stored(&self, entry: &TocEntry) -> Result<Vec<u8>> {
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        let mut source = self.source();
+        source.seek(SeekFrom::Start(entry.offset))?;
+        source.read_exact(&mut stored)?;
+        Ok(stored)
+    }
+
+    /// 項目を読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 展開した内容、無い、または、壊れている場合はエラーです。
+    /// 
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        crate::profile_scope!("pack.read");
+        let entry = self.get(path)?;
+        let stored = self.read_stored(entry)?;
+        let data = match entry.compression {
+            Compression::None => stored,
+            Compression::Lz4 => lz4_flex::block::decompress(&stored, entry.size as usize)
+                .map_err(|e| CwagoError::from_source(ErrorKind::Parse, e).context(msg!(PACK_CORRUPT)))?,
+        };
+        if data.len() as u64 != entry.size || crc32(&data) != entry.crc {
+            return Err(CwagoError::new(ErrorKind::Parse, msg!(PACK_CHECKSUM, path = entry.path)));
+        }
+        Ok(data)
+    }
+
+    /// 項目を順に読み込むストリームを作成します。
+    /// 
+    /// 非圧縮の項目は全体を読み込まずに少しずつ読み込み、終端でチェックサムを確認します。
+    /// 圧縮された項目は展開してから読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// ストリーム、無い場合はエラーです。
+    /// 
+    pub fn stream(&self, path: &str) -> Result<EntryStream<'_, R>> {
+        let entry = self.get(path)?;
+        let inner = match entry.compression {
+            Compression::None => StreamInner::Stored { position: 0, crc: Crc32::new() },
+            _ => StreamInner::Buffered(Cursor::new(self.read(path)?)),
+        };
+        Ok(EntryStream { reader: self, entry, inner })
+    }
+}
+impl<R: Read + Seek> Debug for PackReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackReader")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+impl<R: Read + Seek + Send> Backend for PackReader<R> {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        PackReader::read(self, path)
+    }
+
+    fn metadata(&self, path: &str) -> Option<Metadata> {
+        if let Some(entry) = self.find_normalized(path) {
+            return Some(Metadata { kind: EntryKind::File, len: entry.size });
+        }
+        let prefix = format!("{}/", path);
+        self.entries.iter()
+            .any(|e| path.is_empty() || e.path.starts_with(&prefix))
+            .then_some(Metadata { kind: EntryKind::Dir, len: 0 })
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        let mut entries: Vec<DirEntry> = self.entries.iter()
+            .filter_map(|e| e.path.strip_prefix(prefix.as_str()))
+            .map(|rest| match rest.split_once('/') {
+                Some((dir, _)) => DirEntry { name: dir.to_string(), kind: EntryKind::Dir },
+                None => DirEntry { name: rest.to_string(), kind: EntryKind::File },
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+        if entries.is_empty() && !path.is_empty() {
+            return Err(CwagoError::new(ErrorKind::NotFound, msg!(PACK_NOT_FOUND, path = path)));
+        }
+        Ok(entries)
+    }
+}
+
+/// 項目を順に読み込むストリームです。
+pub struct EntryStream<'a, R: Read + Seek> {
+    reader: &'a PackReader<R>, // パックです。
+    entry: &'a TocEntry,       // 項目です。
+    inner: StreamInner,        // 読み込みの状態です。
+}
+
+// ストリームの状態です。
+enum StreamInner {
+    Stored { position: u64, crc: Crc32 }, // 非圧縮の項目を読み込んだ位置と途中のCRC-32です。
+    Buffered(Cursor<Vec<u8>>),            // 展開済みの内容です。
+}
+
+impl<R: Read + Seek> Read for EntryStream<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            StreamInner::Buffered(cursor) => cursor.read(buf),
+            StreamInner::Stored { position, crc } => {
+                let remaining = self.entry.size - *position;
+                if remaining == 0 || buf.is_empty() {
+                    return Ok(0);
+                }
+                let len = (buf.len() as u64).min(remaining) as usize;
+                {
+                    let mut source = self.reader.source();
+                    source.seek(SeekFrom::Start(self.entry.offset + *position))?;
+                    source.read_exact(&mut buf[..len])?;
+                }
+                crc.update(&buf[..len]);
+                *position += len as u64;
+                if *position == self.entry.size && crc.finish() != self.entry.crc {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        msg!(PACK_CHECKSUM, path = self.entry.path)
+                    ));
+                }
+                Ok(len)
+            },
+        }
+    }
+}
+impl<R: Read + Seek> Debug for EntryStream<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryStream")
+            .field("path", &self.entry.path)
+            .finish()
+    }
+}
+
+// 形式が不正なエラーを作成します。
+fn corrupt() -> CwagoError {
+    CwagoError::new(ErrorKind::Parse, msg!(PACK_CORRUPT))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
@@ -0,0 +1,622 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/plugin.rs
+// (C) 2023 CwagoCommunity.
+//
+//! プラグインのマニフェストと依存関係解決を提供します。
+//!
+//! プラグインはライブラリファイルと同じディレクトリに
+//! `<名前>.plugin.toml`のマニフェストを配置します。
+//!
+//! ```toml
+//! [plugin]
+//! name = "physics"
+//! version = "0.2.0"
+//! engine = ">=0.1, <0.2"
+//!
+//! [dependencies]
+//! transform = "^0.1"
+//! ```
+// =========================
+
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet
+    },
+    fmt::{
+        self,
+        Display
+    },
+    fs,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use libloading::Library;
+use semver::{
+    Version,
+    VersionReq
+};
+use serde::Deserialize;
+
+use crate::{
+    messages::*,
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, version: &str, deps: &[(&str, &str)]) -> Manifest {
+        let mut src = format!(
+            "[plugin]\nname = \"{}\"\nversion = \"{}\"\nengine = \"^0.1\"\n[dependencies]\n",
+            name,
+            version
+        );
+        for (dep, req) in deps {
+            src += &format!("{} = \"{}\"\n", dep, req);
+        }
+        Manifest::parse(&src).expect("マニフェストの解析に失敗しました。")
+    }
+
+    fn names(manifests: &[Manifest]) -> Vec<&str> {
+        manifests.iter().map(|m| m.name()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let m = manifest("physics", "0.2.0", &[("transform", "^0.1")]);
+        assert_eq!(m.name(), "physics");
+        assert_eq!(m.version(), &Version::new(0, 2, 0));
+        assert_eq!(m.library(), "physics");
+        assert!(m.dependencies().contains_key("transform"));
+
+        let err = Manifest::parse("[plugin]\nname = \"a\"\n").unwrap_err();
+        assert!(matches!(err, PluginError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let engine = Version::new(0, 1, 0);
+        let sorted = resolve(vec![
+            manifest("ai", "0.1.0", &[("pathfinding", "^0.1"), ("transform", "^0.1")]),
+            manifest("physics", "0.1.0", &[("transform", "^0.1")]),
+            manifest("pathfinding", "0.1.3", &[("transform", "^0.1")]),
+            manifest("transform", "0.1.0", &[]),
+        ], &engine).expect("依存関係の解決に失敗しました。");
+        assert_eq!(names(&sorted), vec!["transform", "pathfinding", "ai", "physics"]);
+    }
+
+    #[test]
+    fn test_resolve_cycle() {
+        let engine = Version::new(0, 1, 0);
+        let diag = resolve(vec![
+            manifest("a", "0.1.0", &[("b", "*")]),
+            manifest("b", "0.1.0", &[("c", "*")]),
+            manifest("c", "0.1.0", &[("a", "*")]),
+            manifest("d", "0.1.0", &[]),
+        ], &engine).unwrap_err();
+        assert_eq!(diag.errors().len(), 1);
+        match &diag.errors()[0] {
+            PluginError::Cycle { path } => assert_eq!(path, &vec!["a", "b", "c", "a"]),
+            e => panic!("循環依存以外のエラーが報告されました。{}", e),
+        }
+    }
+
+    #[test]
+    fn test_resolve_missing_and_incompatible() {
+        let engine = Version::new(0, 1, 0);
+        let diag = resolve(vec![
+            manifest("ai", "0.1.0", &[("pathfinding", "^0.1")]),
+            manifest("physics", "0.1.0", &[("transform", "^0.2")]),
+            manifest("transform", "0.1.0", &[]),
+        ], &engine).unwrap_err();
+        let errors = diag.errors();
+        assert_eq!(errors.len(), 2, "{}", diag);
+        assert!(errors.iter().any(|e| matches!(e, PluginError::Missing { plugin, dependency, .. } if plugin == "ai" && dependency == "pathfinding")));
+        assert!(errors.iter().any(|e| matches!(e, PluginError::Incompatible { plugin, dependency, .. } if plugin == "physics" && dependency == "transform")));
+    }
+
+    #[test]
+    fn test_resolve_engine_and_duplicate() {
+        let engine = Version::new(0, 2, 0);
+        let diag = resolve(vec![
+            manifest("a", "0.1.0", &[]),
+            manifest("a", "0.1.1", &[]),
+        ], &engine).unwrap_err();
+        let errors = diag.errors();
+        assert!(errors.iter().any(|e| matches!(e, PluginError::Duplicate { name } if name == "a")));
+        assert!(errors.iter().any(|e| matches!(e, PluginError::Engine { plugin, .. } if plugin == "a")));
+    }
+
+    fn plugin_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cwago_plugin_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, src) in files {
+            fs::write(dir.join(file), src).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_scan_valid() {
+        let engine = Version::new(0, 1, 0);
+        let dir = plugin_dir("valid", &[
+            ("physics.plugin.toml", "[plugin]\nname = \"physics\"\nversion = \"0.1.0\"\nengine = \"^0.1\"\n"),
+            ("notes.toml", "[plugin]\n"),
+        ]);
+
+        let manifests = scan(&dir).expect("マニフェストの読み込みに失敗しました。");
+        assert_eq!(names(&manifests), vec!["physics"]);
+        assert_eq!(manifests[0].path(), Some(dir.join("physics.plugin.toml").as_path()));
+
+        // マニフェストは正しいため、ライブラリが無いことだけが報告されます。
+        let diag = Plugins::load_dir(&dir, &engine).err().expect("ライブラリが無いのに読み込めました。");
+        assert_eq!(diag.errors().len(), 1, "{}", diag);
+        assert!(matches!(&diag.errors()[0], PluginError::Library { plugin, .. } if plugin == "physics"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_malformed() {
+        let engine = Version::new(0, 1, 0);
+        let dir = plugin_dir("malformed", &[
+            ("a.plugin.toml", "[plugin]\nname = \"a\"\n"),
+            ("b.plugin.toml", "[plugin]\nname = \"b\"\nversion = \"0.1.0\"\nengine = \"^0.1\"\n"),
+            ("c.plugin.toml", "[plugin\n"),
+        ]);
+        let malformed = [dir.join("a.plugin.toml"), dir.join("c.plugin.toml")];
+
+        let diag = scan(&dir).unwrap_err();
+        assert_eq!(diag.errors().len(), 2, "{}", diag);
+        for (e, expected) in diag.errors().iter().zip(&malformed) {
+            assert!(matches!(e, PluginError::Parse { path: Some(path), .. } if path == expected), "{}", e);
+        }
+
+        // マニフェストの問題はライブラリを読み込む前に報告されます。
+        let diag = Plugins::load_dir(&dir, &engine).err().expect("壊れたマニフェストを読み込めました。");
+        assert!(diag.errors().iter().all(|e| matches!(e, PluginError::Parse { .. })), "{}", diag);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_empty() {
+        let engine = Version::new(0, 1, 0);
+        let dir = plugin_dir("empty", &[("readme.txt", "plugins")]);
+
+        assert!(scan(&dir).expect("空のディレクトリの読み込みに失敗しました。").is_empty());
+        let plugins = Plugins::load_dir(&dir, &engine)
+            .unwrap_or_else(|e| panic!("空のディレクトリの読み込みに失敗しました。{}", e));
+        assert_eq!(plugins.iter().count(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+        let diag = scan(&dir).unwrap_err();
+        assert!(matches!(&diag.errors()[0], PluginError::Io { path, .. } if path == &dir));
+    }
+}
+
+/// マニフェストファイルの拡張子です。
+pub const MANIFEST_EXTENSION: &str = ".plugin.toml";
+
+/// エンジンのバージョンです。
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// プラグインのマニフェストです。
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    name: String,                                // プラグイン名です。
+    version: Version,                            // プラグインのバージョンです。
+    engine: VersionReq,                          // 対応するエンジンのバージョン範囲です。
+    library: String,                             // ライブラリ名です。
+    dependencies: BTreeMap<String, VersionReq>,  // 依存するプラグインとバージョン範囲です。
+    path: Option<PathBuf>,                       // マニフェストファイルのパスです。
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFile {
+    plugin: PluginSection,
+    #[serde(default)]
+    dependencies: BTreeMap<String, VersionReq>,
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PluginSection {
+    name: String,
+    version: Version,
+    engine: VersionReq,
+    library: Option<String>,
+}
+impl Manifest {
+    /// TOML文字列から作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `src` - マニフェストのTOML文字列です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// マニフェスト、または、解析エラーです。
+    /// 
+    pub fn parse(src: &str) -> Result<Manifest, PluginError> {
+        let file: ManifestFile = toml::from_str(src).map_err(|e| PluginError::Parse {
+            path: None,
+            message: e.message().to_string(),
+        })?;
+        let library = file.plugin.library.unwrap_or_else(|| file.plugin.name.clone());
+        Ok(Manifest {
+            name: file.plugin.name,
+            version: file.plugin.version,
+            engine: file.plugin.engine,
+            library,
+            dependencies: file.dependencies,
+            path: None,
+        })
+    }
+
+    /// マニフェストファイルを読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - マニフェストファイルのパスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// マニフェスト、または、読み込みエラーです。
+    /// 
+    pub fn load(path: &Path) -> Result<Manifest, PluginError> {
+        let src = fs::read_to_string(path).map_err(|e| PluginError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let mut manifest = Self::parse(&src).map_err(|e| match e {
+            PluginError::Parse { message, .. } => PluginError::Parse {
+                path: Some(path.to_path_buf()),
+                message
+            },
+            e => e,
+        })?;
+        manifest.path = Some(path.to_path_buf());
+        Ok(manifest)
+    }
+
+    /// プラグイン名を取得します。
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// プラグインのバージョンを取得します。
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// 対応するエンジンのバージョン範囲を取得します。
+    pub fn engine(&self) -> &VersionReq {
+        &self.engine
+    }
+
+    /// ライブラリ名を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// プラットフォーム固有の接頭辞、拡張子を除いたライブラリ名です。
+    /// 
+    pub fn library(&self) -> &str {
+        &self.library
+    }
+
+    /// 依存するプラグインとバージョン範囲を取得します。
+    pub fn dependencies(&self) -> &BTreeMap<String, VersionReq> {
+        &self.dependencies
+    }
+
+    /// マニフェストファイルのパスを取得します。
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// プラグインのエラーです。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginError {
+    /// ファイルの読み込みに失敗しました。
+    Io { path: PathBuf, message: String },
+    /// マニフェストの解析に失敗しました。
+    Parse { path: Option<PathBuf>, message: String },
+    /// 同じ名前のプラグインが複数存在します。
+    Duplicate { name: String },
+    /// エンジンのバージョンが対応範囲外です。
+    Engine { plugin: String, required: VersionReq, engine: Version },
+    /// 依存するプラグインが存在しません。
+    Missing { plugin: String, dependency: String, required: VersionReq },
+    /// 依存するプラグインのバージョンが対応範囲外です。
+    Incompatible { plugin: String, dependency: String, required: VersionReq, found: Version },
+    /// 依存関係が循環しています。
+    Cycle { path: Vec<String> },
+    /// ライブラリの読み込みに失敗しました。
+    Library { plugin: String, path: PathBuf, message: String },
+}
+impl Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            PluginError::Io { path, message } =>
+                msg!(PLUGIN_IO, path = path.display(), reason = message),
+            PluginError::Parse { path: Some(path), message } =>
+                msg!(PLUGIN_PARSE, path = path.display(), reason = message),
+            PluginError::Parse { path: None, message } =>
+                msg!(PLUGIN_PARSE_SOURCE, reason = message),
+            PluginError::Duplicate { name } =>
+                msg!(PLUGIN_DUPLICATE, plugin = name),
+            PluginError::Engine { plugin, required, engine } =>
+                msg!(PLUGIN_ENGINE, plugin = plugin, required = required, engine = engine),
+            PluginError::Missing { plugin, dependency, required } =>
+                msg!(PLUGIN_MISSING, plugin = plugin, dependency = dependency, required = required),
+            PluginError::Incompatible { plugin, dependency, required, found } =>
+                msg!(PLUGIN_INCOMPATIBLE, plugin = plugin, dependency = dependency, required = required, found = found),
+            PluginError::Cycle { path } =>
+                msg!(PLUGIN_CYCLE, path = path.join(" -> ")),
+            PluginError::Library { plugin, path, message } =>
+                msg!(PLUGIN_LIBRARY, plugin = plugin, path = path.display(), reason = message),
+        };
+        f.write_str(&text)
+    }
+}
+impl std::error::Error for PluginError {}
+
+/// 依存関係解決で見つかったエラーの一覧です。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    errors: Vec<PluginError>,
+}
+impl Diagnostics {
+    /// エラーの一覧を取得します。
+    pub fn errors(&self) -> &[PluginError] {
+        &self.errors
+    }
+}
+impl From<PluginError> for Diagnostics {
+    fn from(e: PluginError) -> Self {
+        Diagnostics { errors: vec![e] }
+    }
+}
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&msg!(PLUGIN_DIAGNOSTICS, count = self.errors.len()))?;
+        for e in &self.errors {
+            write!(f, "\n  * {}", e)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for Diagnostics {}
+
+/// 依存関係を解決します。
+/// 
+/// すべての問題を収集してから返すため、読み込み前に原因をまとめて確認できます。
+/// 
+/// # 引数
+/// 
+/// * `manifests` - マニフェストの一覧です。
+/// * `engine` - エンジンのバージョンです。
+/// 
+/// # 戻り値
+/// 
+/// 依存されるものが先に並ぶ読み込み順のマニフェスト、または、エラーの一覧です。
+/// 
+pub fn resolve(manifests: Vec<Manifest>, engine: &Version) -> Result<Vec<Manifest>, Diagnostics> {
+    let mut errors = Vec::new();
+
+    // 名前で索引を作成します。
+    let mut by_name = BTreeMap::new();
+    for m in manifests {
+        if by_name.contains_key(&m.name) {
+            let e = PluginError::Duplicate { name: m.name.clone() };
+            if !errors.contains(&e) {
+                errors.push(e);
+            }
+            continue;
+        }
+        by_name.insert(m.name.clone(), m);
+    }
+
+    // エンジンと依存先のバージョンを検証します。
+    for m in by_name.values() {
+        if !m.engine.matches(engine) {
+            errors.push(PluginError::Engine {
+                plugin: m.name.clone(),
+                required: m.engine.clone(),
+                engine: engine.clone()
+            });
+        }
+        for (dep, req) in &m.dependencies {
+            match by_name.get(dep) {
+                None => errors.push(PluginError::Missing {
+                    plugin: m.name.clone(),
+                    dependency: dep.clone(),
+                    required: req.clone()
+                }),
+                Some(d) if !req.matches(&d.version) => errors.push(PluginError::Incompatible {
+                    plugin: m.name.clone(),
+                    dependency: dep.clone(),
+                    required: req.clone(),
+                    found: d.version.clone()
+                }),
+                Some(_) => {},
+            }
+        }
+    }
+
+    // 深さ優先探索でトポロジカルソートします。
+    let mut order = Vec::with_capacity(by_name.len());
+    let mut done = BTreeSet::new();
+    for name in by_name.keys() {
+        let mut stack = Vec::new();
+        if let Some(path) = visit(name, &by_name, &mut stack, &mut done, &mut order) {
+            errors.push(PluginError::Cycle { path });
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors });
+    }
+    Ok(order.into_iter().filter_map(|n| by_name.remove(&n)).collect())
+}
+
+// 依存先から順に訪問して、循環を見つけた場合はその経路を返します。
+fn visit(
+    name: &str,
+    by_name: &BTreeMap<String, Manifest>,
+    stack: &mut Vec<String>,
+    done: &mut BTreeSet<String>,
+    order: &mut Vec<String>
+) -> Option<Vec<String>> {
+    if done.contains(name) {
+        return None;
+    }
+    if let Some(i) = stack.iter().position(|s| s == name) {
+        let mut path = stack[i..].to_vec();
+        path.push(name.to_string());
+        // 同じ循環を再度報告しないよう、経路上のプラグインを処理済みにします。
+        done.extend(stack[i..].iter().cloned());
+        return Some(path);
+    }
+    let m = by_name.get(name)?;
+    stack.push(name.to_string());
+    for dep in m.dependencies.keys() {
+        if let Some(path) = visit(dep, by_name, stack, done, order) {
+            stack.pop();
+            return Some(path);
+        }
+    }
+    stack.pop();
+    if done.insert(name.to_string()) {
+        order.push(name.to_string());
+    }
+    None
+}
+
+/// ディレクトリ内のマニフェストを読み込みます。
+/// 
+/// # 引数
+/// 
+/// * `dir` - プラグインのディレクトリです。
+/// 
+/// # 戻り値
+/// 
+/// ファイル名順のマニフェスト、または、エラーの一覧です。
+/// 
+pub fn scan(dir: &Path) -> Result<Vec<Manifest>, Diagnostics> {
+    let entries = fs::read_dir(dir).map_err(|e| PluginError::Io {
+        path: dir.to_path_buf(),
+        message: e.to_string()
+    })?;
+    let mut paths = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(MANIFEST_EXTENSION)))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut manifests = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match Manifest::load(&path) {
+            Ok(m) => manifests.push(m),
+            Err(e) => errors.push(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors });
+    }
+    Ok(manifests)
+}
+
+/// 読み込み済みのプラグインです。
+pub struct Plugin {
+    manifest: Manifest,
+    library: Library,
+}
+impl Plugin {
+    /// マニフェストを取得します。
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// ライブラリを取得します。
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+}
+
+/// 依存関係の順に読み込まれたプラグインの集まりです。
+/// 
+/// 解放は読み込みと逆の順で行います。
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+}
+impl Plugins {
+    /// ディレクトリのプラグインを依存関係の順に読み込みます。
+    /// 
+    /// マニフェストの問題はライブラリを1つも読み込む前に報告します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `dir` - プラグインのディレクトリです。
+    /// * `engine` - エンジンのバージョンです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 読み込んだプラグイン、または、エラーの一覧です。
+    /// 
+    pub fn load_dir(dir: &Path, engine: &Version) -> Result<Plugins, Diagnostics> {
+        let manifests = resolve(scan(dir)?, engine)?;
+
+        let mut plugins = Plugins { plugins: Vec::with_capacity(manifests.len()) };
+        for manifest in manifests {
+            let path = dir.join(libloading::library_filename(&manifest.library));
+            // プラグインの初期化処理を信頼して読み込みます。
+            let library = unsafe { Library::new(&path) }.map_err(|e| PluginError::Library {
+                plugin: manifest.name.clone(),
+                path: path.clone(),
+                message: e.to_string()
+            })?;
+            plugins.plugins.push(Plugin { manifest, library });
+        }
+        Ok(plugins)
+    }
+
+    /// プラグインを取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - プラグイン名です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// プラグイン、または、Noneです。
+    /// 
+    pub fn get(&self, name: &str) -> Option<&Plugin> {
+        self.plugins.iter().find(|p| p.manifest.name == name)
+    }
+
+    /// 読み込み順にプラグインを走査します。
+    pub fn iter(&self) -> impl Iterator<Item = &Plugin> {
+        self.plugins.iter()
+    }
+}
+impl Drop for Plugins {
+    fn drop(&mut self) {
+        // 依存する側から先に解放します。
+        while let Some(plugin) = self.plugins.pop() {
+            drop(plugin);
+        }
+    }
+}
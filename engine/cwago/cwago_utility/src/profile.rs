@@ -0,0 +1,427 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/profile.rs
+// (C) 2023 CwagoCommunity.
+//
+//! CPUの計測機能を提供します。
+//! 
+//! `profile`フィーチャーが有効な場合のみ記録し、無効な場合はマクロが何も生成しません。
+//! 記録はスレッド毎に蓄積され、Chromeのトレース形式(Perfettoでも表示可能)で書き出せます。
+//! 
+//! ```
+//! fn update() {
+//!     cwago_utility::profile_scope!("update");
+//!     cwago_utility::profile_counter!("entities", 128);
+//! }
+//! 
+//! update();
+//! let mut json = Vec::new();
+//! cwago_utility::profile::collect().write_chrome(&mut json).unwrap();
+//! ```
+// =========================
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write
+    },
+    path::Path
+};
+
+#[cfg(feature = "profile")]
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering
+        },
+        Arc,
+        Mutex,
+        OnceLock
+    },
+    thread,
+    time::Instant
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_chrome() {
+        let trace = Trace {
+            threads: vec![(1, "main \"0\"".to_string())],
+            events: vec![
+                Event { name: "update", tid: 1, timestamp: 10.0, kind: EventKind::Span { duration: 2.5 } },
+                Event { name: "memory.bytes", tid: 1, timestamp: 12.0, kind: EventKind::Counter { value: 64.0 } },
+                Event { name: "frame", tid: 1, timestamp: 13.0, kind: EventKind::Instant },
+            ],
+        };
+        let mut out = Vec::new();
+        trace.write_chrome(&mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(json, concat!(
+            "{\"traceEvents\":[\n",
+            "{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":1,\"tid\":1,\"args\":{\"name\":\"main \\\"0\\\"\"}},\n",
+            "{\"ph\":\"X\",\"name\":\"update\",\"pid\":1,\"tid\":1,\"ts\":10.000,\"dur\":2.500},\n",
+            "{\"ph\":\"C\",\"name\":\"memory.bytes\",\"pid\":1,\"tid\":1,\"ts\":12.000,\"args\":{\"value\":64}},\n",
+            "{\"ph\":\"i\",\"name\":\"frame\",\"pid\":1,\"tid\":1,\"ts\":13.000,\"s\":\"t\"}\n",
+            "],\"displayTimeUnit\":\"ms\"}\n"
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "profile")]
+    fn test_record() {
+        fn work() {
+            crate::profile_scope!("work");
+            crate::profile_counter!("test.counter", 3);
+        }
+        let handle = thread::Builder::new()
+            .name("profile_test".to_string())
+            .spawn(work)
+            .unwrap();
+        handle.join().unwrap();
+
+        let trace = collect();
+        let (tid, _) = trace.threads.iter()
+            .find(|(_, name)| name == "profile_test")
+            .expect("スレッド名が記録されていません。");
+        let events = trace.events.iter().filter(|e| e.tid == *tid).collect::<Vec<_>>();
+        assert!(events.iter().any(|e| e.name == "work" && matches!(e.kind, EventKind::Span { .. })));
+        assert!(events.iter().any(|e| e.name == "test.counter" && e.kind == EventKind::Counter { value: 3.0 }));
+
+        // 収集した記録は取り除かれます。
+        assert!(collect().events.iter().all(|e| e.tid != *tid));
+    }
+}
+
+/// 記録の種類です。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    /// 区間です。
+    Span {
+        /// 区間の長さ(マイクロ秒)です。
+        duration: f64,
+    },
+    /// カウンタの値です。
+    Counter {
+        /// 値です。
+        value: f64,
+    },
+    /// 時点です。
+    Instant,
+}
+
+/// 1件の記録です。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// 名前です。
+    pub name: &'static str,
+    /// 記録したスレッドのIDです。
+    pub tid: u64,
+    /// 計測開始からの時刻(マイクロ秒)です。
+    pub timestamp: f64,
+    /// 種類です。
+    pub kind: EventKind,
+}
+
+/// 収集した記録です。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    /// スレッドのIDと名前です。
+    pub threads: Vec<(u64, String)>,
+    /// 時刻順の記録です。
+    pub events: Vec<Event>,
+}
+impl Trace {
+    /// Chromeのトレース形式のJSONで書き出します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `out` - 書き出し先です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 書き出しに失敗した際、エラーを返します。
+    /// 
+    pub fn write_chrome(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut lines = Vec::with_capacity(self.threads.len() + self.events.len());
+        for (tid, name) in &self.threads {
+            lines.push(format!(
+                "{{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":{}}}}}",
+                tid, json_string(name)
+            ));
+        }
+        for e in &self.events {
+            let mut line = format!(
+                "{{\"ph\":\"{}\",\"name\":{},\"pid\":1,\"tid\":{},\"ts\":{:.3}",
+                match e.kind {
+                    EventKind::Span { .. } => "X",
+                    EventKind::Counter { .. } => "C",
+                    EventKind::Instant => "i",
+                },
+                json_string(e.name), e.tid, e.timestamp
+            );
+            let _ = match e.kind {
+                EventKind::Span { duration } => write!(line, ",\"dur\":{:.3}}}", duration),
+                EventKind::Counter { value } => write!(line, ",\"args\":{{\"value\":{}}}}}", value),
+                EventKind::Instant => write!(line, ",\"s\":\"t\"}}"),
+            };
+            lines.push(line);
+        }
+        writeln!(out, "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}", lines.join(",\n"))
+    }
+
+    /// Chromeのトレース形式のJSONファイルに保存します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - 保存先のパスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 保存に失敗した際、エラーを返します。
+    /// 
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_chrome(&mut out)?;
+        out.flush()
+    }
+}
+
+// JSONの文字列に変換します。
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 計測が有効かを取得します。
+pub const fn enabled() -> bool {
+    cfg!(feature = "profile")
+}
+
+/// スレッド毎の記録です。
+#[cfg(feature = "profile")]
+struct ThreadBuffer {
+    tid: u64,                  // スレッドのIDです。
+    name: String,              // スレッド名です。
+    events: Mutex<Vec<Event>>, // 未収集の記録です。
+}
+
+#[cfg(feature = "profile")]
+static THREADS: Mutex<Vec<Arc<ThreadBuffer>>> = Mutex::new(Vec::new());
+#[cfg(feature = "profile")]
+static NEXT_TID: AtomicU64 = AtomicU64::new(1);
+#[cfg(feature = "profile")]
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+#[cfg(feature = "profile")]
+thread_local! {
+    static BUFFER: Arc<ThreadBuffer> = {
+        let current = thread::current();
+        let tid = NEXT_TID.fetch_add(1, Ordering::Relaxed);
+        let buffer = Arc::new(ThreadBuffer {
+            tid,
+            name: current.name().map(str::to_string).unwrap_or_else(|| format!("thread {}", tid)),
+            events: Mutex::new(Vec::new()),
+        });
+        THREADS.lock().unwrap_or_else(|e| e.into_inner()).push(buffer.clone());
+        buffer
+    };
+}
+
+// 計測開始からの時刻(マイクロ秒)を取得します。
+#[cfg(feature = "profile")]
+fn timestamp(at: Instant) -> f64 {
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    at.saturating_duration_since(epoch).as_secs_f64() * 1_000_000.0
+}
+
+// 現在のスレッドの記録に追加します。
+#[cfg(feature = "profile")]
+fn push(name: &'static str, at: Instant, kind: EventKind) {
+    let timestamp = timestamp(at);
+    // スレッド終了中は記録できないため無視します。
+    let _ = BUFFER.try_with(|buffer| {
+        buffer.events.lock().unwrap_or_else(|e| e.into_inner()).push(Event {
+            name,
+            tid: buffer.tid,
+            timestamp,
+            kind,
+        });
+    });
+}
+
+/// 区間の計測です。
+/// 
+/// 破棄された時点で区間を記録します。通常は`profile_scope!`マクロで作成します。
+#[must_use]
+pub struct Span {
+    #[cfg(feature = "profile")]
+    name: &'static str, // 名前です。
+    #[cfg(feature = "profile")]
+    start: Instant,     // 開始時刻です。
+}
+impl Span {
+    /// 計測を開始します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `name` - 区間の名前です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 破棄されるまでを区間とするインスタンスです。
+    /// 
+    #[inline]
+    #[allow(unused_variables)]
+    pub fn begin(name: &'static str) -> Span {
+        Span {
+            #[cfg(feature = "profile")]
+            name,
+            #[cfg(feature = "profile")]
+            start: Instant::now(),
+        }
+    }
+}
+#[cfg(feature = "profile")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed().as_secs_f64() * 1_000_000.0;
+        push(self.name, self.start, EventKind::Span { duration });
+    }
+}
+
+/// カウンタの値を記録します。
+/// 
+/// # 引数
+/// 
+/// * `name` - カウンタの名前です。
+/// * `value` - 値です。
+/// 
+#[inline]
+#[allow(unused_variables)]
+pub fn counter(name: &'static str, value: f64) {
+    #[cfg(feature = "profile")]
+    push(name, Instant::now(), EventKind::Counter { value });
+}
+
+/// 時点を記録します。
+/// 
+/// # 引数
+/// 
+/// * `name` - 時点の名前です。
+/// 
+#[inline]
+#[allow(unused_variables)]
+pub fn instant(name: &'static str) {
+    #[cfg(feature = "profile")]
+    push(name, Instant::now(), EventKind::Instant);
+}
+
+/// すべてのスレッドの記録を収集します。
+/// 
+/// 収集した記録はバッファから取り除かれ、終了したスレッドのバッファは破棄されます。
+/// 
+/// # 戻り値
+/// 
+/// 時刻順の記録です。計測が無効な場合は空です。
+/// 
+pub fn collect() -> Trace {
+    #[allow(unused_mut)]
+    let mut trace = Trace::default();
+    #[cfg(feature = "profile")]
+    {
+        let mut threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+        for buffer in threads.iter() {
+            let mut events = buffer.events.lock().unwrap_or_else(|e| e.into_inner());
+            trace.threads.push((buffer.tid, buffer.name.clone()));
+            trace.events.append(&mut events);
+        }
+        // 他から参照されていないバッファは、スレッドが終了しています。
+        threads.retain(|buffer| Arc::strong_count(buffer) > 1);
+        trace.events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    }
+    trace
+}
+
+/// 区間を計測します。
+/// 
+/// スコープの終わりまでを区間として記録します。
+/// `profile`フィーチャーが無効な場合は何も生成しません。
+/// 
+/// ```
+/// fn physics() {
+///     cwago_utility::profile_scope!("physics");
+/// }
+/// ```
+#[cfg(feature = "profile")]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_span = $crate::profile::Span::begin($name);
+    };
+}
+
+/// 区間を計測します。
+/// 
+/// スコープの終わりまでを区間として記録します。
+/// `profile`フィーチャーが無効な場合は何も生成しません。
+/// 
+/// ```
+/// fn physics() {
+///     cwago_utility::profile_scope!("physics");
+/// }
+/// ```
+#[cfg(not(feature = "profile"))]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {};
+}
+
+/// カウンタの値を記録します。
+/// 
+/// `profile`フィーチャーが無効な場合は値の式も評価しません。
+/// 
+/// ```
+/// cwago_utility::profile_counter!("entities", 128);
+/// ```
+#[cfg(feature = "profile")]
+#[macro_export]
+macro_rules! profile_counter {
+    ($name:expr, $value:expr) => {
+        $crate::profile::counter($name, ($value) as f64)
+    };
+}
+
+/// カウンタの値を記録します。
+/// 
+/// `profile`フィーチャーが無効な場合は値の式も評価しません。
+/// 
+/// ```
+/// cwago_utility::profile_counter!("entities", 128);
+/// ```
+#[cfg(not(feature = "profile"))]
+#[macro_export]
+macro_rules! profile_counter {
+    ($name:expr, $value:expr) => {
+        ()
+    };
+}
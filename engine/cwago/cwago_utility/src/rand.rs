@@ -0,0 +1,572 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/rand.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 再現可能な乱数を提供します。
+//! 
+//! 生成器はxoshiro256**で、シードはSplitMix64で状態に展開します。
+//! 同じシードからは、環境を問わず同じ列が得られます。
+//! 状態は保存でき、リプレイの途中から再開できます。
+//! 
+//! システムやエンティティ毎に独立した列が必要な場合は、`from_stream`や`fork`で列を分けます。
+//! 
+//! ```
+//! use cwago_utility::{hash::stable_hash_str, rand::Rng};
+//! 
+//! let mut physics = Rng::from_stream(1234, stable_hash_str("physics"));
+//! let roll = physics.range(1..=6);
+//! assert!((1..=6).contains(&roll));
+//! 
+//! let mut deck: Vec<u32> = (0..52).collect();
+//! physics.shuffle(&mut deck);
+//! ```
+// =========================
+
+use std::{
+    f64::consts::TAU,
+    ops::{
+        Range,
+        RangeInclusive
+    }
+};
+
+use serde::{
+    de::Error as _,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+
+use crate::{
+    messages::{
+        RAND_EMPTY_RANGE,
+        RAND_ZERO_STATE
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_answers() {
+        // SplitMix64とxoshiro256**の参照実装から求めた値です。
+        assert_eq!(splitmix64(&mut 0), 0xe220a8397b1dcdaf);
+
+        let mut rng = Rng { s: [1, 2, 3, 4] };
+        let seq: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        assert_eq!(seq, [0x2d00, 0x0, 0x5a007080, 0x10e0000000009d80]);
+
+        let mut rng = Rng::from_seed(0);
+        assert_eq!(rng.s, [0xe220a8397b1dcdaf, 0x6e789e6aa1b965f4, 0x06c45d188009454f, 0xf88bb8a8724c81ec]);
+        let seq: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        assert_eq!(seq, [0x99ec5f36cb75f2b4, 0xbf6e1f784956452a, 0x1a5f849d4933e6e0, 0x6aa594f1262d2d2c]);
+
+        let mut rng = Rng::from_seed(42);
+        let seq: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        assert_eq!(seq, [0x15780b2e0c2ec716, 0x6104d9866d113a7e, 0xae17533239e499a1, 0xecb8ad4703b360a1]);
+
+        let mut rng = Rng::from_seed(42);
+        rng.jump();
+        assert_eq!(rng.next_u64(), 0x50086ef83cbf4f4a);
+        assert_eq!(rng.next_u64(), 0xba285ec21347d703);
+    }
+
+    #[test]
+    fn test_distribution_known_answers() {
+        // 同じアルゴリズムを別に実装して求めた値です。
+        let mut rng = Rng::from_seed(42);
+        assert_eq!(rng.next_u32(), 0x15780b2e);
+        assert_eq!(rng.next_f64(), 0.3789802506626686);
+        assert_eq!(rng.next_f32(), 11409235.0 / 16777216.0);
+        let rolls: Vec<u32> = (0..8).map(|_| rng.range(1..=6)).collect();
+        assert_eq!(rolls, [6, 6, 5, 5, 6, 5, 4, 5]);
+        assert_eq!(rng.range(-10i64..10), -5);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut rng = Rng::from_seed(7);
+        for _ in 0..1000 {
+            assert!((3..9).contains(&rng.range(3u8..9)));
+            assert!((-5..=5).contains(&rng.range(-5i32..=5)));
+            let f = rng.range(2.0f32..3.0);
+            assert!((2.0..3.0).contains(&f));
+            let f = rng.next_f64();
+            assert!((0.0..1.0).contains(&f));
+        }
+        // 全域も扱えます。
+        rng.range(u64::MIN..=u64::MAX);
+        rng.range(i8::MIN..=i8::MAX);
+        assert_eq!(rng.range(4u32..=4), 4);
+
+        // 偏りが無いかを大まかに確認します。
+        let mut counts = [0u32; 3];
+        for _ in 0..30000 {
+            counts[rng.range(0usize..3)] += 1;
+        }
+        assert!(counts.iter().all(|c| (9000..11000).contains(c)), "{:?}", counts);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_range() {
+        Rng::from_seed(0).range(3..3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_below_zero() {
+        Rng::from_seed(0).below(0);
+    }
+
+    #[test]
+    fn test_streams() {
+        let mut a = Rng::from_stream(1, 0);
+        let mut b = Rng::from_stream(1, 1);
+        assert_ne!(a.next_u64(), b.next_u64());
+        assert_eq!(Rng::from_stream(1, 1).next_u64(), Rng::from_stream(1, 1).next_u64());
+
+        // forkは親を進めません。
+        let parent = Rng::from_seed(5);
+        let mut c = parent.fork(10);
+        assert_eq!(parent, Rng::from_seed(5));
+        assert_eq!(c.next_u64(), parent.fork(10).next_u64());
+        assert_ne!(parent.fork(10), parent.fork(11));
+
+        // splitは親を跳躍させ、子は元の列を引き継ぎます。
+        let mut parent = Rng::from_seed(5);
+        let mut child = parent.split();
+        assert_eq!(child.next_u64(), Rng::from_seed(5).next_u64());
+        let mut jumped = Rng::from_seed(5);
+        jumped.jump();
+        assert_eq!(parent, jumped);
+    }
+
+    #[test]
+    fn test_choice() {
+        let mut rng = Rng::from_seed(3);
+        let mut values: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut values);
+        assert_ne!(values, (0..20).collect::<Vec<_>>());
+        values.sort();
+        assert_eq!(values, (0..20).collect::<Vec<_>>());
+
+        assert_eq!(rng.choose::<u32>(&[]), None);
+        assert!(values.contains(rng.choose(&values).unwrap()));
+
+        assert_eq!(rng.weighted_index(&[]), None);
+        assert_eq!(rng.weighted_index(&[0.0, 0.0]), None);
+        assert_eq!(rng.weighted_index(&[1.0, f32::NAN]), None);
+        for _ in 0..100 {
+            assert_eq!(rng.weighted_index(&[0.0, 2.0, 0.0]), Some(1));
+        }
+        let mut counts = [0u32; 2];
+        for _ in 0..10000 {
+            counts[rng.weighted_index(&[1.0, 3.0]).unwrap()] += 1;
+        }
+        assert!((2200..2800).contains(&counts[0]), "{:?}", counts);
+    }
+
+    #[test]
+    fn test_unit_vectors() {
+        let mut rng = Rng::from_seed(9);
+        for _ in 0..100 {
+            let [x, y] = rng.unit_vec2();
+            assert!(((x * x + y * y) - 1.0).abs() < 1e-5);
+            let [x, y, z] = rng.unit_vec3();
+            assert!(((x * x + y * y + z * z) - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let mut rng = Rng::from_seed(11);
+        rng.next_u64();
+        let json = serde_json::to_string(&rng).unwrap();
+        let mut de: Rng = serde_json::from_str(&json).unwrap();
+        assert_eq!(de.next_u64(), rng.next_u64());
+        assert!(serde_json::from_str::<Rng>("[0,0,0,0]").is_err());
+    }
+}
+
+/// xoshiro256**による乱数生成器です。
+/// 
+/// 暗号用途には使えません。
+/// 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rng {
+    s: [u64; 4], // 状態です。すべて0にはなりません。
+}
+impl Rng {
+    /// シードから作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `seed` - シードです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn from_seed(seed: u64) -> Rng {
+        let mut x = seed;
+        // SplitMix64の出力がすべて0になることはありません。
+        Rng { s: [splitmix64(&mut x), splitmix64(&mut x), splitmix64(&mut x), splitmix64(&mut x)] }
+    }
+
+    /// シードと列の番号から作成します。
+    /// 
+    /// 同じシードでも、列の番号が異なれば独立した列になります。
+    /// 名前で区別する場合は`stable_hash_str`で番号にします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `seed` - シードです。
+    /// * `stream` - 列の番号です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn from_stream(seed: u64, stream: u64) -> Rng {
+        Self::from_seed(mix64(seed) ^ stream)
+    }
+
+    /// 状態から作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `state` - 状態です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、状態がすべて0の場合はNoneです。
+    /// 
+    pub fn from_state(state: [u64; 4]) -> Option<Rng> {
+        (state != [0; 4]).then_some(Rng { s: state })
+    }
+
+    /// 状態を取得します。
+    #[inline]
+    pub fn state(&self) -> [u64; 4] {
+        self.s
+    }
+
+    /// 自身を進めずに、番号毎に独立した生成器を作成します。
+    /// 
+    /// エンティティ毎の生成器など、同じ親と番号から常に同じ列が必要な場合に使います。
+    /// 
+    /// # 引数
+    /// 
+    /// * `stream` - 列の番号です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 生成器です。
+    /// 
+    pub fn fork(&self, stream: u64) -> Rng {
+        let [a, b, c, d] = self.s;
+        Self::from_seed(mix64(a ^ mix64(b ^ mix64(c ^ mix64(d)))) ^ stream)
+    }
+
+    /// 現在の列を子に渡し、自身は重ならない先の列へ跳躍します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 自身の元の状態を持つ生成器です。
+    /// 
+    pub fn split(&mut self) -> Rng {
+        let child = self.clone();
+        self.jump();
+        child
+    }
+
+    /// 2^128回分進めます。
+    /// 
+    /// 跳躍前と後の列は、2^128回までは重なりません。
+    /// 
+    pub fn jump(&mut self) {
+        const JUMP: [u64; 4] = [0x180ec6d33cfd0aba, 0xd5a61266f0c9392c, 0xa9582618e03fc9aa, 0x39abdc4529b1661c];
+        let mut s = [0u64; 4];
+        for jump in JUMP {
+            for b in 0..64 {
+                if jump & (1 << b) != 0 {
+                    for (s, x) in s.iter_mut().zip(self.s) {
+                        *s ^= x;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = s;
+    }
+
+    /// 64bitの乱数を生成します。
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// 32bitの乱数を生成します。
+    /// 
+    /// 下位より質の良い上位32bitを使います。
+    /// 
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// `[0, 1)`の浮動小数点数を生成します。
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// `[0, 1)`の浮動小数点数を生成します。
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// 真偽値を生成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `p` - trueになる確率です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 真偽値です。
+    /// 
+    #[inline]
+    pub fn bool(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    /// `[0, n)`の整数を偏り無く生成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `n` - 上限です。0より大きい必要があります。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 整数です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `n`が0の場合に異常終了します。
+    /// 
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "{}", msg!(RAND_EMPTY_RANGE));
+        // Lemireの方法で、偏りの出る範囲だけを引き直します。
+        let mut m = self.next_u64() as u128 * n as u128;
+        if (m as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (m as u64) < threshold {
+                m = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (m >> 64) as u64
+    }
+
+    /// 範囲内の値を生成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `range` - `a..b`、または、`a..=b`の範囲です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 範囲内の値です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 範囲が空の場合に異常終了します。
+    /// 
+    #[inline]
+    pub fn range<T, R: SampleRange<T>>(&mut self, range: R) -> T {
+        range.sample(self)
+    }
+
+    /// 要素を1つ選びます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `items` - 選ぶ対象です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 選んだ要素、空の場合はNoneです。
+    /// 
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u64) as usize)
+    }
+
+    /// 重みに比例した確率で添え字を選びます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `weights` - 0以上の重みです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 選んだ添え字、重みの合計が0、または、有限でない値や負の値を含む場合はNoneです。
+    /// 
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return None;
+        }
+        let total: f64 = weights.iter().map(|w| *w as f64).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = self.next_f64() * total;
+        let mut last = None;
+        for (i, w) in weights.iter().enumerate() {
+            if *w <= 0.0 {
+                continue;
+            }
+            if target < *w as f64 {
+                return Some(i);
+            }
+            target -= *w as f64;
+            last = Some(i);
+        }
+        // 丸め誤差で超えた場合は、最後の重みのある添え字です。
+        last
+    }
+
+    /// 要素を並べ替えます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `items` - 並べ替える対象です。
+    /// 
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        // Fisher-Yatesの方法です。
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// 長さ1の2次元ベクトルを一様に生成します。
+    pub fn unit_vec2(&mut self) -> [f32; 2] {
+        let (s, c) = (self.next_f64() * TAU).sin_cos();
+        [c as f32, s as f32]
+    }
+
+    /// 長さ1の3次元ベクトルを一様に生成します。
+    pub fn unit_vec3(&mut self) -> [f32; 3] {
+        // 高さを一様に選ぶと、球面上で一様になります。
+        let z = self.next_f64() * 2.0 - 1.0;
+        let r = (1.0 - z * z).sqrt();
+        let (s, c) = (self.next_f64() * TAU).sin_cos();
+        [(r * c) as f32, (r * s) as f32, z as f32]
+    }
+}
+impl Serialize for Rng {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.s.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for Rng {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = <[u64; 4]>::deserialize(deserializer)?;
+        Rng::from_state(state).ok_or_else(|| D::Error::custom(msg!(RAND_ZERO_STATE)))
+    }
+}
+
+/// 乱数を生成できる範囲です。
+pub trait SampleRange<T> {
+    /// 範囲内の値を生成します。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 範囲が空の場合に異常終了します。
+    /// 
+    fn sample(self, rng: &mut Rng) -> T;
+}
+
+macro_rules! impl_int_range {
+    ($($t:ty => $u:ty),*) => {$(
+        impl SampleRange<$t> for Range<$t> {
+            fn sample(self, rng: &mut Rng) -> $t {
+                assert!(self.start < self.end, "{}", msg!(RAND_EMPTY_RANGE));
+                let span = (self.end as $u).wrapping_sub(self.start as $u) as u64;
+                self.start.wrapping_add(rng.below(span) as $t)
+            }
+        }
+        impl SampleRange<$t> for RangeInclusive<$t> {
+            fn sample(self, rng: &mut Rng) -> $t {
+                let (start, end) = self.into_inner();
+                assert!(start <= end, "{}", msg!(RAND_EMPTY_RANGE));
+                let span = (end as $u).wrapping_sub(start as $u) as u64;
+                match span.checked_add(1) {
+                    Some(n) => start.wrapping_add(rng.below(n) as $t),
+                    // 64bitの全域です。
+                    None => rng.next_u64() as $t,
+                }
+            }
+        }
+    )*};
+}
+impl_int_range!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, usize => usize,
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize
+);
+
+macro_rules! impl_float_range {
+    ($($t:ty => $next:ident),*) => {$(
+        impl SampleRange<$t> for Range<$t> {
+            fn sample(self, rng: &mut Rng) -> $t {
+                assert!(self.start < self.end, "{}", msg!(RAND_EMPTY_RANGE));
+                let v = self.start + (self.end - self.start) * rng.$next();
+                // 丸めで上限に達した場合は下限にします。
+                if v < self.end { v } else { self.start }
+            }
+        }
+        impl SampleRange<$t> for RangeInclusive<$t> {
+            fn sample(self, rng: &mut Rng) -> $t {
+                let (start, end) = self.into_inner();
+                assert!(start <= end, "{}", msg!(RAND_EMPTY_RANGE));
+                (start + (end - start) * rng.$next()).min(end)
+            }
+        }
+    )*};
+}
+impl_float_range!(f32 => next_f32, f64 => next_f64);
+
+// SplitMix64で状態を進めて値を生成します。
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// 値を撹拌します。
+fn mix64(x: u64) -> u64 {
+    splitmix64(&mut { x })
+}
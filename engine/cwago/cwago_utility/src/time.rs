@@ -0,0 +1,484 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/time.rs
+// (C) 2023 CwagoCommunity.
+//
+//! フレームの時間と固定時間刻みを提供します。
+//! 
+//! `Time`はフレーム毎に`tick`して経過時間を求めます。
+//! 時計は差し替えられるので、テストでは`ManualClock`で決定的に進められます。
+//! 
+//! ```
+//! use std::time::Duration;
+//! use cwago_utility::time::{FixedStep, ManualClock, Time};
+//! 
+//! let clock = ManualClock::new();
+//! let mut time = Time::new(clock.clone());
+//! let mut fixed = FixedStep::new(Duration::from_millis(10));
+//! 
+//! clock.advance(Duration::from_millis(25));
+//! time.tick();
+//! assert_eq!(fixed.accumulate(time.delta()), 2);
+//! assert_eq!(fixed.alpha(), 0.5);
+//! ```
+// =========================
+
+use std::{
+    fmt::{
+        self,
+        Debug
+    },
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering
+        },
+        Arc
+    },
+    time::{
+        Duration,
+        Instant
+    }
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result,
+        ResultExt
+    },
+    messages::{
+        TIME_INVALID_SCALE,
+        TIME_ZERO_STEP
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_time() {
+        let clock = ManualClock::new();
+        let mut time = Time::new(clock.clone());
+        assert_eq!(time.frame(), 0);
+
+        clock.advance(MS * 16);
+        time.tick();
+        assert_eq!(time.frame(), 1);
+        assert_eq!(time.delta(), MS * 16);
+        assert_eq!(time.elapsed(), MS * 16);
+
+        time.set_scale(0.5);
+        clock.advance(MS * 20);
+        time.tick();
+        assert_eq!(time.delta(), MS * 10);
+        assert_eq!(time.unscaled_delta(), MS * 20);
+        assert_eq!(time.elapsed(), MS * 26);
+        assert_eq!(time.unscaled_elapsed(), MS * 36);
+
+        time.pause();
+        clock.advance(MS * 20);
+        time.tick();
+        assert_eq!(time.delta(), Duration::ZERO);
+        assert_eq!(time.unscaled_delta(), MS * 20);
+        assert_eq!(time.elapsed(), MS * 26);
+        assert_eq!(time.frame(), 3, "停止中もフレーム番号は進む必要があります。");
+
+        // 長い停止は上限で切り詰めます。
+        time.resume();
+        time.set_scale(1.0);
+        for scale in [-1.0, f64::INFINITY, f64::NAN] {
+            assert_eq!(time.try_set_scale(scale).unwrap_err().kind(), ErrorKind::InvalidArgument);
+        }
+        assert_eq!(time.scale(), 1.0, "拒否した倍率は適用してはいけません。");
+        clock.advance(Duration::from_secs(10));
+        time.tick();
+        assert_eq!(time.delta(), Time::DEFAULT_MAX_DELTA);
+    }
+
+    #[test]
+    fn test_fixed_step() {
+        let mut fixed = FixedStep::new(MS * 10);
+        assert_eq!(fixed.accumulate(MS * 4), 0);
+        assert_eq!(fixed.alpha(), 0.4);
+        assert_eq!(fixed.accumulate(MS * 17), 2);
+        assert!((fixed.alpha() - 0.1).abs() < 1e-9);
+
+        // 追い付けない分は捨てます。
+        let mut fixed = FixedStep::new(MS * 10).with_max_steps(3);
+        assert_eq!(fixed.accumulate(MS * 105), 3);
+        assert_eq!(fixed.dropped_steps(), 7);
+        assert_eq!(fixed.alpha(), 0.5);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let mut time = Time::default();
+        std::thread::sleep(MS);
+        time.tick();
+        assert!(time.delta() >= MS);
+    }
+}
+
+/// 時計です。
+pub trait Clock: Send + Sync {
+    /// 現在時刻を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 時計の基準時刻からの経過時間です。
+    /// 
+    fn now(&self) -> Duration;
+}
+
+/// OSの単調増加時計です。
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant, // 基準時刻です。
+}
+impl SystemClock {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 作成時を基準時刻とするインスタンスです。
+    /// 
+    pub fn new() -> SystemClock {
+        SystemClock { origin: Instant::now() }
+    }
+}
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// 手動で進める時計です。
+/// 
+/// 複製は同じ時刻を共有するので、`Time`に渡した後も進められます。
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicU64>, // 基準時刻からのナノ秒です。
+}
+impl ManualClock {
+    /// 作成します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 時刻0のインスタンスです。
+    /// 
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    /// 時刻を進めます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `duration` - 進める時間です。
+    /// 
+    pub fn advance(&self, duration: Duration) {
+        self.nanos.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// 時刻を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `now` - 基準時刻からの経過時間です。
+    /// 
+    pub fn set(&self, now: Duration) {
+        self.nanos.store(now.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// フレームの時間です。
+/// 
+/// ECSではリソースとして共有し、システムはこれを読んで経過時間を得ます。
+pub struct Time {
+    clock: Box<dyn Clock>,      // 時計です。
+    last: Duration,             // 前回の時計の時刻です。
+    delta: Duration,            // 倍率を適用した前フレームからの経過時間です。
+    unscaled_delta: Duration,   // 倍率を適用しない前フレームからの経過時間です。
+    elapsed: Duration,          // 倍率を適用した合計の経過時間です。
+    unscaled_elapsed: Duration, // 倍率を適用しない合計の経過時間です。
+    frame: u64,                 // フレーム番号です。
+    scale: f64,                 // 時間の倍率です。
+    paused: bool,               // 停止中かの論理値です。
+    max_delta: Duration,        // 1フレームの経過時間の上限です。
+}
+impl Time {
+    /// 1フレームの経過時間の既定の上限です。
+    pub const DEFAULT_MAX_DELTA: Duration = Duration::from_millis(250);
+
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `clock` - 時刻を読む時計です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 時計の現在時刻を開始時刻とするインスタンスです。
+    /// 
+    pub fn new(clock: impl Clock + 'static) -> Time {
+        let last = clock.now();
+        Time {
+            clock: Box::new(clock),
+            last,
+            delta: Duration::ZERO,
+            unscaled_delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            unscaled_elapsed: Duration::ZERO,
+            frame: 0,
+            scale: 1.0,
+            paused: false,
+            max_delta: Self::DEFAULT_MAX_DELTA,
+        }
+    }
+
+    /// フレームを進めます。
+    /// 
+    /// 時計を読み、前回からの経過時間を上限で切り詰めてから倍率を適用します。
+    /// 
+    pub fn tick(&mut self) {
+        let now = self.clock.now();
+        self.unscaled_delta = now.saturating_sub(self.last).min(self.max_delta);
+        self.last = now;
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else {
+            self.unscaled_delta.mul_f64(self.scale)
+        };
+        self.elapsed += self.delta;
+        self.unscaled_elapsed += self.unscaled_delta;
+        self.frame += 1;
+    }
+
+    /// 倍率を適用した前フレームからの経過時間を取得します。
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// 倍率を適用した前フレームからの経過秒数を取得します。
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// 倍率と停止を適用しない前フレームからの経過時間を取得します。
+    pub fn unscaled_delta(&self) -> Duration {
+        self.unscaled_delta
+    }
+
+    /// 倍率を適用した合計の経過時間を取得します。
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// 倍率と停止を適用しない合計の経過時間を取得します。
+    pub fn unscaled_elapsed(&self) -> Duration {
+        self.unscaled_elapsed
+    }
+
+    /// `tick`した回数を取得します。
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// 時間の倍率を取得します。
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// 時間の倍率を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `scale` - 倍率です。0以上の有限の値である必要があります。
+    /// 
+    /// # 異常終了
+    /// 
+    /// 倍率が負、無限大、または、NaNの場合に異常終了します。
+    /// 
+    pub fn set_scale(&mut self, scale: f64) {
+        self.try_set_scale(scale).or_abort()
+    }
+
+    /// 時間の倍率を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `scale` - 倍率です。0以上の有限の値である必要があります。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 倍率が負、無限大、または、NaNの場合はエラーです。
+    /// 
+    pub fn try_set_scale(&mut self, scale: f64) -> Result<()> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(CwagoError::new(ErrorKind::InvalidArgument, msg!(TIME_INVALID_SCALE, scale = scale)));
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    /// 停止します。
+    /// 
+    /// 停止中は倍率を適用した経過時間が進みません。
+    /// 
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// 再開します。
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// 停止中かを取得します。
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 1フレームの経過時間の上限を設定します。
+    /// 
+    /// ブレークポイントなどで長く止まった後に、時間が飛ばないようにします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `max_delta` - 上限です。
+    /// 
+    pub fn set_max_delta(&mut self, max_delta: Duration) {
+        self.max_delta = max_delta;
+    }
+}
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(SystemClock::new())
+    }
+}
+impl Debug for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Time")
+            .field("delta", &self.delta)
+            .field("unscaled_delta", &self.unscaled_delta)
+            .field("elapsed", &self.elapsed)
+            .field("frame", &self.frame)
+            .field("scale", &self.scale)
+            .field("paused", &self.paused)
+            .finish()
+    }
+}
+
+/// 固定時間刻みの蓄積器です。
+/// 
+/// フレームの経過時間を蓄積し、固定の刻みで何回更新するかを求めます。
+#[derive(Debug, Clone)]
+pub struct FixedStep {
+    step: Duration,        // 1回の更新の時間です。
+    accumulator: Duration, // 未消化の時間です。
+    max_steps: u32,        // 1フレームで更新する回数の上限です。
+    dropped: u64,          // 上限により捨てた回数の合計です。
+}
+impl FixedStep {
+    /// 1フレームで更新する回数の既定の上限です。
+    pub const DEFAULT_MAX_STEPS: u32 = 8;
+
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `step` - 1回の更新の時間です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `step`が0の場合異常終了します。
+    /// 
+    pub fn new(step: Duration) -> FixedStep {
+        assert!(!step.is_zero(), "{}", msg!(TIME_ZERO_STEP));
+        FixedStep {
+            step,
+            accumulator: Duration::ZERO,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+            dropped: 0,
+        }
+    }
+
+    /// 1フレームで更新する回数の上限を設定します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `max_steps` - 上限です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 設定したインスタンスです。
+    /// 
+    pub fn with_max_steps(mut self, max_steps: u32) -> FixedStep {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// 経過時間を蓄積します。
+    /// 
+    /// 上限を超える回数分の時間は、処理落ちが続かないように捨てます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `delta` - フレームの経過時間です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// このフレームで更新する回数です。
+    /// 
+    pub fn accumulate(&mut self, delta: Duration) -> u32 {
+        self.accumulator += delta;
+        let (nanos, step) = (self.accumulator.as_nanos(), self.step.as_nanos());
+        let steps = (nanos / step) as u64;
+        let run = steps.min(self.max_steps as u64);
+        self.dropped += steps - run;
+        self.accumulator = Duration::from_nanos((nanos % step) as u64);
+        run as u32
+    }
+
+    /// 補間の係数を取得します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最後の更新から次の更新までの進み具合で、0以上1未満です。
+    /// 
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.as_nanos() as f64 / self.step.as_nanos() as f64
+    }
+
+    /// 1回の更新の時間を取得します。
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// 上限により捨てた回数の合計を取得します。
+    pub fn dropped_steps(&self) -> u64 {
+        self.dropped
+    }
+}
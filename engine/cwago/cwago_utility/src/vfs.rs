@@ -0,0 +1,1004 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/vfs.rs
+// (C) 2023 CwagoCommunity.
+//
+//! マウントしたバックエンドを1つのパス空間で扱う仮想ファイルシステムを提供します。
+//! 
+//! パスは`/`区切りで、先頭の`/`や`.`は取り除き、`..`は1つ上の階層として解決します。
+//! 同じパスを複数のマウントが持つ場合は、優先度の高いマウントが見えます。
+//! 優先度が同じ場合は、後からマウントしたものが優先されます。
+//! 
+//! ```
+//! use cwago_utility::vfs::{MemoryBackend, Vfs};
+//! 
+//! let base = MemoryBackend::new();
+//! base.insert("textures/grass.png", b"base".to_vec());
+//! let patch = MemoryBackend::new();
+//! patch.insert("textures/grass.png", b"patch".to_vec());
+//! 
+//! let vfs = Vfs::new();
+//! vfs.mount("assets", 0, base).unwrap();
+//! vfs.mount("assets", 10, patch).unwrap();
+//! assert_eq!(vfs.read("/assets/./textures/grass.png").unwrap(), b"patch");
+//! ```
+// =========================
+
+use std::{
+    collections::BTreeMap,
+    fmt::{
+        self,
+        Debug
+    },
+    fs,
+    future::Future,
+    io,
+    path::{
+        Component,
+        Path,
+        PathBuf
+    },
+    pin::Pin,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering
+        },
+        Arc,
+        Mutex,
+        RwLock
+    },
+    task::{
+        Context,
+        Poll,
+        Waker
+    }
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result,
+        ResultExt
+    },
+    job::{
+        Counter,
+        JobSystem
+    },
+    messages::{
+        VFS_INVALID_PATH,
+        VFS_IO,
+        VFS_NOT_FOUND,
+        VFS_NOT_UTF8,
+        VFS_READ_ONLY,
+        VFS_READ_PANICKED,
+        VFS_RESULT_TAKEN
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        process
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("").unwrap(), "");
+        assert_eq!(normalize("/").unwrap(), "");
+        assert_eq!(normalize("a/b/c").unwrap(), "a/b/c");
+        assert_eq!(normalize("/a//b/./c/").unwrap(), "a/b/c");
+        assert_eq!(normalize("a\\b\\..\\c").unwrap(), "a/c");
+        assert_eq!(normalize("a/..").unwrap(), "");
+        let e = normalize("a/../../b").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_overlay() {
+        crate::logging::init();
+        let base = MemoryBackend::new();
+        base.insert("a.txt", b"base a".to_vec());
+        base.insert("b.txt", b"base b".to_vec());
+        let patch = MemoryBackend::new();
+        patch.insert("a.txt", b"patch a".to_vec());
+
+        let vfs = Vfs::new();
+        vfs.mount("data", 0, base).unwrap();
+        let id = vfs.mount("data", 1, patch).unwrap();
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"patch a");
+        assert_eq!(vfs.read_to_string("data/b.txt").unwrap(), "base b");
+        assert!(vfs.exists("data/b.txt"));
+
+        let e = vfs.read("data/c.txt").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+        assert_eq!(vfs.read("other/a.txt").unwrap_err().kind(), ErrorKind::NotFound);
+
+        // 外すと下のマウントが見えます。
+        assert!(vfs.unmount(id));
+        assert!(!vfs.unmount(id));
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"base a");
+    }
+
+    #[test]
+    fn test_write() {
+        crate::logging::init();
+        let vfs = Vfs::new();
+        let pack = MemoryBackend::new();
+        pack.insert("config.toml", b"packed".to_vec());
+        vfs.mount("", 10, pack.into_read_only()).unwrap();
+        let save = MemoryBackend::new();
+        vfs.mount("", 0, save).unwrap();
+
+        // 読み込み専用のマウントは飛ばして書き込みます。
+        vfs.write("saves/slot1.sav", b"hp=10").unwrap();
+        assert_eq!(vfs.read("saves/slot1.sav").unwrap(), b"hp=10");
+        // 読み込みは優先度の高い読み込み専用のマウントが見えます。
+        vfs.write("config.toml", b"local").unwrap();
+        assert_eq!(vfs.read("config.toml").unwrap(), b"packed");
+
+        vfs.remove("saves/slot1.sav").unwrap();
+        assert!(!vfs.exists("saves/slot1.sav"));
+
+        let ro = Vfs::new();
+        ro.mount("", 0, MemoryBackend::new().into_read_only()).unwrap();
+        assert_eq!(ro.write("a", b"").unwrap_err().kind(), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_read_dir() {
+        crate::logging::init();
+        let base = MemoryBackend::new();
+        base.insert("textures/a.png", vec![1]);
+        base.insert("textures/ui/button.png", vec![2]);
+        base.insert("readme.txt", vec![3]);
+        let patch = MemoryBackend::new();
+        patch.insert("b.png", vec![4]);
+
+        let vfs = Vfs::new();
+        vfs.mount("assets", 0, base).unwrap();
+        vfs.mount("assets/textures", 0, patch).unwrap();
+        vfs.mount("mods/extra", 0, MemoryBackend::new()).unwrap();
+
+        let names = |path: &str| -> Vec<(String, EntryKind)> {
+            vfs.read_dir(path).unwrap().into_iter().map(|e| (e.name, e.kind)).collect()
+        };
+        assert_eq!(names(""), [("assets".into(), EntryKind::Dir), ("mods".into(), EntryKind::Dir)]);
+        assert_eq!(names("assets"), [("readme.txt".into(), EntryKind::File), ("textures".into(), EntryKind::Dir)]);
+        assert_eq!(names("assets/textures"), [
+            ("a.png".into(), EntryKind::File),
+            ("b.png".into(), EntryKind::File),
+            ("ui".into(), EntryKind::Dir),
+        ]);
+        assert_eq!(names("mods"), [("extra".into(), EntryKind::Dir)]);
+        assert_eq!(vfs.read_dir("missing").unwrap_err().kind(), ErrorKind::NotFound);
+
+        assert_eq!(vfs.metadata("assets/readme.txt"), Some(Metadata { kind: EntryKind::File, len: 1 }));
+        assert_eq!(vfs.metadata("assets/textures").map(|m| m.kind), Some(EntryKind::Dir));
+        assert_eq!(vfs.metadata("mods").map(|m| m.kind), Some(EntryKind::Dir));
+        assert_eq!(vfs.metadata("nothing"), None);
+    }
+
+    #[test]
+    fn test_host() {
+        crate::logging::init();
+        let root = env::temp_dir().join(format!("cwago_vfs_{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("user", 0, HostBackend::new(&root)).unwrap();
+        vfs.write("user/saves/1.sav", b"data").unwrap();
+        assert_eq!(fs::read(root.join("saves/1.sav")).unwrap(), b"data");
+        assert_eq!(vfs.read("user/saves/1.sav").unwrap(), b"data");
+        assert_eq!(vfs.read_dir("user/saves").unwrap(), [DirEntry { name: "1.sav".into(), kind: EntryKind::File }]);
+        assert_eq!(vfs.metadata("user/saves/1.sav").map(|m| m.len), Some(4));
+        assert_eq!(vfs.read("user/saves/2.sav").unwrap_err().kind(), ErrorKind::NotFound);
+        vfs.remove("user/saves/1.sav").unwrap();
+        assert!(!root.join("saves/1.sav").exists());
+
+        let ro = Vfs::new();
+        ro.mount("", 0, HostBackend::new(&root).into_read_only()).unwrap();
+        assert!(ro.write("x", b"").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_host_path() {
+        crate::logging::init();
+        let root = env::temp_dir().join("cwago_vfs_root");
+        let host = HostBackend::new(&root);
+        assert_eq!(host.host_path("a/b.txt").unwrap(), root.join("a").join("b.txt"));
+        assert_eq!(host.host_path("").unwrap(), root);
+
+        // ルートを置き換える、または、外に出る要素は拒否します。
+        for path in ["/etc/passwd", "../x", "a/../../x"] {
+            assert_eq!(host.host_path(path).unwrap_err().kind(), ErrorKind::InvalidArgument, "{}", path);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_host_path_prefix() {
+        crate::logging::init();
+        let root = env::temp_dir().join("cwago_vfs_root");
+        let host = HostBackend::new(&root);
+        for path in ["C:", "C:/Windows/win.ini", r"\\server\share\x"] {
+            assert_eq!(host.host_path(path).unwrap_err().kind(), ErrorKind::InvalidArgument, "{}", path);
+        }
+
+        // 正規化してもドライブの要素は残るため、バックエンドで拒否します。
+        let vfs = Vfs::new();
+        vfs.mount("user", 0, host).unwrap();
+        assert_eq!(vfs.read("user/C:/Windows/win.ini").unwrap_err().kind(), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_read_async() {
+        crate::logging::init();
+        let files = MemoryBackend::new();
+        files.insert("a", b"async".to_vec());
+        let vfs = Vfs::new();
+        vfs.mount("", 0, files).unwrap();
+
+        let jobs = JobSystem::new(1);
+        let read = vfs.read_async(&jobs, "a");
+        assert_eq!(read.wait(&jobs).unwrap(), b"async");
+
+        let read = vfs.read_async(&jobs, "missing");
+        jobs.wait(read.counter()).unwrap();
+        assert!(read.is_ready());
+        assert_eq!(read.try_take().unwrap().unwrap_err().kind(), ErrorKind::NotFound);
+
+        // Futureとしても待てます。
+        let read = vfs.read_async(&jobs, "a");
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut read = Box::pin(read);
+        let data = loop {
+            if let Poll::Ready(data) = read.as_mut().poll(&mut cx) {
+                break data;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(data.unwrap(), b"async");
+    }
+
+    // 読み込みでパニックするバックエンドです。
+    struct PanicBackend;
+    impl Backend for PanicBackend {
+        fn read(&self, _: &str) -> Result<Vec<u8>> {
+            panic!("test")
+        }
+
+        fn metadata(&self, _: &str) -> Option<Metadata> {
+            None
+        }
+
+        fn read_dir(&self, _: &str) -> Result<Vec<DirEntry>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_read_async_panic() {
+        crate::logging::init();
+        let vfs = Vfs::new();
+        vfs.mount("", 0, PanicBackend).unwrap();
+        let jobs = JobSystem::new(1);
+
+        // Futureはパニックしたジョブでもエラーで完了します。
+        let mut read = Box::pin(vfs.read_async(&jobs, "a"));
+        let mut cx = Context::from_waker(Waker::noop());
+        let result = loop {
+            if let Poll::Ready(result) = read.as_mut().poll(&mut cx) {
+                break result;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Other);
+
+        let read = vfs.read_async(&jobs, "a");
+        assert!(read.wait(&jobs).is_err());
+    }
+}
+
+/// パスを正規化します。
+/// 
+/// 区切りは`/`と`\`を受け付け、空の要素と`.`を取り除き、`..`を解決します。
+/// 
+/// # 引数
+/// 
+/// * `path` - パスです。
+/// 
+/// # 戻り値
+/// 
+/// 先頭と末尾に`/`の無いパス、ルートより上を指す場合はエラーです。ルートは空文字列です。
+/// 
+pub fn normalize(path: &str) -> Result<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {},
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(invalid_path(path));
+                }
+            },
+            part => parts.push(part),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// 項目の種類です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EntryKind {
+    /// ファイルです。
+    File,
+    /// ディレクトリです。
+    Dir,
+}
+
+/// 項目の情報です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    /// 種類です。
+    pub kind: EntryKind,
+    /// ファイルのバイト数です。ディレクトリの場合は0です。
+    pub len: u64,
+}
+
+/// ディレクトリ内の項目です。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DirEntry {
+    /// 名前です。
+    pub name: String,
+    /// 種類です。
+    pub kind: EntryKind,
+}
+
+/// マウントできるバックエンドです。
+/// 
+/// 受け取るパスは正規化済みで、マウント位置からの相対パスです。
+/// 見つからない場合は`ErrorKind::NotFound`のエラーを返します。
+/// 書き込みを実装しないバックエンドは読み込み専用になります。
+/// 
+pub trait Backend: Send + Sync {
+    /// ファイルを読み込みます。
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// 項目の情報を取得します。存在しない場合はNoneです。
+    fn metadata(&self, path: &str) -> Option<Metadata>;
+
+    /// ディレクトリ内の項目を取得します。順序は問いません。
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>>;
+
+    /// 読み込み専用かを取得します。
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// ファイルを書き込みます。親のディレクトリは必要に応じて作成します。
+    fn write(&self, path: &str, _data: &[u8]) -> Result<()> {
+        Err(read_only(path))
+    }
+
+    /// ファイルを削除します。
+    fn remove(&self, path: &str) -> Result<()> {
+        Err(read_only(path))
+    }
+}
+
+/// マウントの識別子です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MountId(u64);
+
+// マウントです。
+struct Mount {
+    id: MountId,                  // 識別子です。
+    point: String,                // 正規化したマウント位置です。
+    priority: i32,                // 優先度です。
+    backend: Arc<dyn Backend>,    // バックエンドです。
+}
+impl Mount {
+    // パスがこのマウント内なら、マウント位置からの相対パスを取得します。
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.point.is_empty() {
+            return Some(path);
+        }
+        match path.strip_prefix(self.point.as_str()) {
+            Some("") => Some(""),
+            Some(rest) => rest.strip_prefix('/'),
+            None => None,
+        }
+    }
+}
+
+// マウント位置からの相対パスとバックエンドです。
+type Resolved = (String, Arc<dyn Backend>);
+
+/// 仮想ファイルシステムです。
+/// 
+/// 複製したインスタンスは同じマウントを共有します。
+/// 
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Arc<RwLock<Vec<Mount>>>, // 優先度の高い順のマウントです。
+}
+impl Vfs {
+    /// 作成します。
+    pub fn new() -> Vfs {
+        Vfs::default()
+    }
+
+    /// バックエンドをマウントします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `point` - マウント位置です。空文字列はルートです。
+    /// * `priority` - 優先度です。大きい方が優先されます。
+    /// * `backend` - バックエンドです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// マウントの識別子、マウント位置が不正な場合はエラーです。
+    /// 
+    pub fn mount(&self, point: &str, priority: i32, backend: impl Backend + 'static) -> Result<MountId> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let point = normalize(point)?;
+        let id = MountId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let mut mounts = self.mounts.write().unwrap_or_else(|e| e.into_inner());
+        // 同じ優先度では後からのマウントを先に置きます。
+        let at = mounts.iter().position(|m| m.priority <= priority).unwrap_or(mounts.len());
+        mounts.insert(at, Mount { id, point, priority, backend: Arc::new(backend) });
+        Ok(id)
+    }
+
+    /// マウントを外します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `id` - マウントの識別子です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 外した場合はtrueです。
+    /// 
+    pub fn unmount(&self, id: MountId) -> bool {
+        let mut mounts = self.mounts.write().unwrap_or_else(|e| e.into_inner());
+        let len = mounts.len();
+        mounts.retain(|m| m.id != id);
+        mounts.len() != len
+    }
+
+    // パスを含むマウントを優先度の高い順に、相対パスと共に取得します。
+    fn resolve(&self, path: &str) -> Result<(String, Vec<Resolved>)> {
+        let path = normalize(path)?;
+        let mounts = self.mounts.read().unwrap_or_else(|e| e.into_inner());
+        let found = mounts.iter()
+            .filter_map(|m| m.relative(&path).map(|r| (r.to_string(), m.backend.clone())))
+            .collect();
+        Ok((path, found))
+    }
+
+    /// ファイルを読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最も優先度の高いマウントにあるファイルの内容です。
+    /// 
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        crate::profile_scope!("vfs.read");
+        let (path, mounts) = self.resolve(path)?;
+        for (relative, backend) in mounts {
+            match backend.read(&relative) {
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+        Err(not_found(&path))
+    }
+
+    /// UTF-8のファイルを読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最も優先度の高いマウントにあるファイルの内容です。
+    /// 
+    pub fn read_to_string(&self, path: &str) -> Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| CwagoError::from_source(ErrorKind::Parse, e).context(msg!(VFS_NOT_UTF8, path = path)))
+    }
+
+    /// ファイルを別のスレッドで読み込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `jobs` - 読み込みを実行するジョブシステムです。
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 読み込みの完了を待つハンドルです。
+    /// 
+    pub fn read_async(&self, jobs: &JobSystem, path: &str) -> ReadHandle {
+        let shared = Arc::new(Mutex::new(ReadState { result: None, waker: None }));
+        let (vfs, path, state) = (self.clone(), path.to_string(), shared.clone());
+        let counter = jobs.spawn(move || {
+            // 読み込みがパニックしても、ハンドルはエラーで完了します。
+            let mut completion = ReadCompletion { state: Some(state), path };
+            let result = vfs.read(&completion.path);
+            completion.complete(result);
+        });
+        ReadHandle { state: shared, counter }
+    }
+
+    /// ファイルを書き込みます。
+    /// 
+    /// パスを含む書き込み可能なマウントのうち、最も優先度の高いものに書き込みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// * `data` - 内容です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 書き込めるマウントが無い場合はエラーです。
+    /// 
+    pub fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let (path, mounts) = self.resolve(path)?;
+        match mounts.into_iter().find(|(_, b)| !b.is_read_only()) {
+            Some((relative, backend)) => backend.write(&relative, data),
+            None => Err(read_only(&path)),
+        }
+    }
+
+    /// ファイルを削除します。
+    /// 
+    /// パスを含む書き込み可能なマウントのうち、最も優先度の高いものから削除します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 書き込めるマウントが無い場合はエラーです。
+    /// 
+    pub fn remove(&self, path: &str) -> Result<()> {
+        let (path, mounts) = self.resolve(path)?;
+        match mounts.into_iter().find(|(_, b)| !b.is_read_only()) {
+            Some((relative, backend)) => backend.remove(&relative),
+            None => Err(read_only(&path)),
+        }
+    }
+
+    /// 項目の情報を取得します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 最も優先度の高いマウントにある項目の情報、存在しない場合はNoneです。
+    /// マウント位置の途中のディレクトリも存在します。
+    /// 
+    pub fn metadata(&self, path: &str) -> Option<Metadata> {
+        let (path, found) = self.resolve(path).ok()?;
+        if let Some(metadata) = found.iter().find_map(|(r, b)| b.metadata(r)) {
+            return Some(metadata);
+        }
+        self.mount_dirs(&path).is_some().then_some(Metadata { kind: EntryKind::Dir, len: 0 })
+    }
+
+    /// 項目が存在するかを取得します。
+    #[inline]
+    pub fn exists(&self, path: &str) -> bool {
+        self.metadata(path).is_some()
+    }
+
+    /// ディレクトリ内の項目を取得します。
+    /// 
+    /// すべてのマウントの項目を合わせ、同じ名前は優先度の高いマウントのものにします。
+    /// マウント位置の途中のディレクトリも含みます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 名前順の項目、ディレクトリがどのマウントにも無い場合はエラーです。
+    /// 
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let (path, found) = self.resolve(path)?;
+        let mut entries: BTreeMap<String, EntryKind> = BTreeMap::new();
+        let mut exists = false;
+        for (relative, backend) in found {
+            match backend.read_dir(&relative) {
+                Ok(list) => {
+                    exists = true;
+                    for entry in list {
+                        entries.entry(entry.name).or_insert(entry.kind);
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::NotFound => {},
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(dirs) = self.mount_dirs(&path) {
+            exists = true;
+            for name in dirs {
+                entries.entry(name).or_insert(EntryKind::Dir);
+            }
+        }
+        if !exists {
+            return Err(not_found(&path));
+        }
+        Ok(entries.into_iter().map(|(name, kind)| DirEntry { name, kind }).collect())
+    }
+
+    // パスの下にマウント位置がある場合、その次の階層の名前を取得します。
+    fn mount_dirs(&self, path: &str) -> Option<Vec<String>> {
+        let mounts = self.mounts.read().unwrap_or_else(|e| e.into_inner());
+        let names: Vec<String> = mounts.iter()
+            .filter_map(|m| {
+                let rest = if path.is_empty() {
+                    m.point.as_str()
+                } else {
+                    m.point.strip_prefix(path)?.strip_prefix('/')?
+                };
+                rest.split('/').next().filter(|s| !s.is_empty()).map(str::to_string)
+            })
+            .collect();
+        (!names.is_empty()).then_some(names)
+    }
+}
+impl Debug for Vfs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mounts = self.mounts.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_list()
+            .entries(mounts.iter().map(|m| (&m.point, m.priority, m.backend.is_read_only())))
+            .finish()
+    }
+}
+
+// 非同期読み込みの状態です。
+struct ReadState {
+    result: Option<Result<Vec<u8>>>, // 読み込み結果です。
+    waker: Option<Waker>,            // 完了を通知するタスクです。
+}
+
+// 非同期読み込みの結果を格納し、待っているタスクに通知します。
+// 格納せずにドロップした場合は、ジョブがパニックしたとしてエラーを格納します。
+struct ReadCompletion {
+    state: Option<Arc<Mutex<ReadState>>>, // 未完了の場合の状態です。
+    path: String,                         // 読み込むパスです。
+}
+impl ReadCompletion {
+    fn complete(&mut self, result: Result<Vec<u8>>) {
+        if let Some(state) = self.state.take() {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+impl Drop for ReadCompletion {
+    fn drop(&mut self) {
+        if self.state.is_some() {
+            let e = CwagoError::new(ErrorKind::Other, msg!(VFS_READ_PANICKED, path = self.path));
+            self.complete(Err(e));
+        }
+    }
+}
+
+/// 非同期読み込みのハンドルです。
+/// 
+/// `Future`として待つか、ジョブシステムで待つか、毎フレーム`try_take`で確認します。
+/// 
+pub struct ReadHandle {
+    state: Arc<Mutex<ReadState>>, // 状態です。
+    counter: Counter,             // 読み込みジョブのカウンタです。
+}
+impl ReadHandle {
+    /// 完了したかを取得します。
+    pub fn is_ready(&self) -> bool {
+        self.counter.is_done()
+    }
+
+    /// 読み込みジョブのカウンタを取得します。
+    /// 
+    /// 後続のジョブの依存先に使えます。
+    /// 
+    pub fn counter(&self) -> &Counter {
+        &self.counter
+    }
+
+    /// 完了していれば結果を取り出します。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 結果、未完了、または、取り出し済みの場合はNoneです。
+    /// 
+    pub fn try_take(&self) -> Option<Result<Vec<u8>>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).result.take()
+    }
+
+    /// 完了を待って結果を取り出します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `jobs` - 読み込みを投入したジョブシステムです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 結果です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// `try_take`で取り出し済みの場合に異常終了します。
+    /// 
+    pub fn wait(self, jobs: &JobSystem) -> Result<Vec<u8>> {
+        jobs.wait(&self.counter)?;
+        self.try_take().unwrap_or_else(|| panic!("{}", msg!(VFS_RESULT_TAKEN)))
+    }
+}
+impl Future for ReadHandle {
+    type Output = Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+impl Debug for ReadHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadHandle")
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
+
+/// メモリ上のバックエンドです。
+/// 
+/// ディレクトリはファイルのパスから暗黙に存在します。
+/// 
+#[derive(Default)]
+pub struct MemoryBackend {
+    files: RwLock<BTreeMap<String, Arc<[u8]>>>, // パス毎の内容です。
+    read_only: bool,                            // 読み込み専用かです。
+}
+impl MemoryBackend {
+    /// 作成します。
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    /// 読み込み専用にします。
+    /// 
+    /// 読み込み専用にした後も`insert`で追加できます。
+    /// 
+    pub fn into_read_only(mut self) -> MemoryBackend {
+        self.read_only = true;
+        self
+    }
+
+    /// ファイルを追加します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - パスです。
+    /// * `data` - 内容です。
+    /// 
+    /// # 異常終了
+    /// 
+    /// パスがルートより上を指す場合に異常終了します。
+    /// 
+    pub fn insert(&self, path: &str, data: Vec<u8>) {
+        let path = normalize(path).or_abort();
+        self.files.write().unwrap_or_else(|e| e.into_inner()).insert(path, data.into());
+    }
+}
+impl Backend for MemoryBackend {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files.get(path).map(|d| d.to_vec()).ok_or_else(|| not_found(path))
+    }
+
+    fn metadata(&self, path: &str) -> Option<Metadata> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(data) = files.get(path) {
+            return Some(Metadata { kind: EntryKind::File, len: data.len() as u64 });
+        }
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        files.range(prefix.clone()..)
+            .next()
+            .filter(|(k, _)| k.starts_with(&prefix))
+            .map(|_| Metadata { kind: EntryKind::Dir, len: 0 })
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        let mut entries: Vec<DirEntry> = Vec::new();
+        for key in files.range(prefix.clone()..).map(|(k, _)| k).take_while(|k| k.starts_with(&prefix)) {
+            let entry = match key[prefix.len()..].split_once('/') {
+                Some((dir, _)) => DirEntry { name: dir.to_string(), kind: EntryKind::Dir },
+                None => DirEntry { name: key[prefix.len()..].to_string(), kind: EntryKind::File },
+            };
+            // 名前順に並ぶため、同じディレクトリは連続します。
+            if entries.last() != Some(&entry) {
+                entries.push(entry);
+            }
+        }
+        if entries.is_empty() && !path.is_empty() {
+            return Err(not_found(path));
+        }
+        Ok(entries)
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        if self.read_only {
+            return Err(read_only(path));
+        }
+        self.files.write().unwrap_or_else(|e| e.into_inner()).insert(path.to_string(), data.into());
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        if self.read_only {
+            return Err(read_only(path));
+        }
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        files.remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+    }
+}
+
+/// ホストのディレクトリのバックエンドです。
+#[derive(Debug, Clone)]
+pub struct HostBackend {
+    root: PathBuf,   // ルートのディレクトリです。
+    read_only: bool, // 読み込み専用かです。
+}
+impl HostBackend {
+    /// 作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `root` - マウント位置に対応するディレクトリです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンスです。
+    /// 
+    pub fn new(root: impl Into<PathBuf>) -> HostBackend {
+        HostBackend { root: root.into(), read_only: false }
+    }
+
+    /// 読み込み専用にします。
+    pub fn into_read_only(mut self) -> HostBackend {
+        self.read_only = true;
+        self
+    }
+
+    // ホストのパスを取得します。
+    // ドライブやルートなど、結合するとルートを置き換える要素はエラーにし、ルートの外を指さないようにします。
+    fn host_path(&self, path: &str) -> Result<PathBuf> {
+        let mut host = self.root.clone();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(name) => host.push(name),
+                _ => return Err(invalid_path(path)),
+            }
+        }
+        Ok(host)
+    }
+}
+impl Backend for HostBackend {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        fs::read(self.host_path(path)?).map_err(|e| io_error(e, path))
+    }
+
+    fn metadata(&self, path: &str) -> Option<Metadata> {
+        let metadata = fs::metadata(self.host_path(path).ok()?).ok()?;
+        Some(if metadata.is_dir() {
+            Metadata { kind: EntryKind::Dir, len: 0 }
+        } else {
+            Metadata { kind: EntryKind::File, len: metadata.len() }
+        })
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(self.host_path(path)?).map_err(|e| io_error(e, path))? {
+            let entry = entry.map_err(|e| io_error(e, path))?;
+            // 仮想パスはUTF-8のため、表せない名前は除きます。
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let kind = match entry.file_type() {
+                Ok(t) if t.is_dir() => EntryKind::Dir,
+                _ => EntryKind::File,
+            };
+            entries.push(DirEntry { name, kind });
+        }
+        Ok(entries)
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        if self.read_only {
+            return Err(read_only(path));
+        }
+        let host = self.host_path(path)?;
+        if let Some(parent) = host.parent() {
+            fs::create_dir_all(parent).map_err(|e| io_error(e, path))?;
+        }
+        fs::write(host, data).map_err(|e| io_error(e, path))
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        if self.read_only {
+            return Err(read_only(path));
+        }
+        fs::remove_file(self.host_path(path)?).map_err(|e| io_error(e, path))
+    }
+}
+
+// 不正なパスのエラーを作成します。
+fn invalid_path(path: &str) -> CwagoError {
+    CwagoError::new(ErrorKind::InvalidArgument, msg!(VFS_INVALID_PATH, path = path))
+}
+
+// 見つからないエラーを作成します。
+fn not_found(path: &str) -> CwagoError {
+    CwagoError::new(ErrorKind::NotFound, msg!(VFS_NOT_FOUND, path = path))
+}
+
+// 読み込み専用のエラーを作成します。
+fn read_only(path: &str) -> CwagoError {
+    CwagoError::new(ErrorKind::InvalidArgument, msg!(VFS_READ_ONLY, path = path))
+}
+
+// 入出力のエラーを変換します。見つからない場合は他のバックエンドと同じ種類にします。
+fn io_error(e: io::Error, path: &str) -> CwagoError {
+    let kind = match e.kind() {
+        io::ErrorKind::NotFound => ErrorKind::NotFound,
+        _ => ErrorKind::Io,
+    };
+    CwagoError::from_source(kind, e).context(msg!(VFS_IO, path = path))
+}
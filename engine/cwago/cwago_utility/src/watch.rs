@@ -0,0 +1,790 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/src/watch.rs
+// (C) 2023 CwagoCommunity.
+//
+//! ホットリロードのためのファイル監視を提供します。
+//! 
+//! Linuxではinotifyを使い、使えない環境では一定間隔で走査します。
+//! エディタの保存で連続する変更は、静まるまで待ってから1つのイベントにまとめます。
+//! 
+//! ```no_run
+//! use std::time::Duration;
+//! use cwago_utility::watch::Watcher;
+//! 
+//! let watcher = Watcher::new(Duration::from_millis(100)).unwrap();
+//! watcher.watch("assets").unwrap();
+//! // 毎フレーム溜まった変更を処理します。
+//! for event in watcher.try_iter() {
+//!     println!("{:?} {}", event.kind, event.path.display());
+//! }
+//! ```
+// =========================
+
+use std::{
+    collections::{
+        hash_map::Entry,
+        HashMap
+    },
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    },
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering
+        },
+        mpsc::{
+            self,
+            Receiver,
+            Sender,
+            TryIter
+        },
+        Arc,
+        Mutex,
+        MutexGuard
+    },
+    thread::{
+        self,
+        JoinHandle
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime
+    }
+};
+
+use crate::{
+    error::{
+        CwagoError,
+        ErrorKind,
+        Result
+    },
+    messages::{
+        WATCH_FAILED,
+        WATCH_FALLBACK,
+        WATCH_OVERFLOW,
+        WATCH_SPAWN_FAILED
+    },
+    msg
+};
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        process
+    };
+
+    use super::*;
+
+    // 条件を満たすイベントが届くまで待ちます。
+    fn expect(watcher: &Watcher, path: &Path, kind: ChangeKind) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match watcher.recv_timeout(remaining) {
+                Some(event) if event.path == path && event.kind == kind => return,
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        panic!("{:?}が{:?}になりませんでした。", path, kind);
+    }
+
+    fn check(watcher: Watcher, name: &str) {
+        let root = env::temp_dir().join(format!("cwago_watch_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("old")).unwrap();
+        watcher.watch(&root).unwrap();
+
+        let file = root.join("a.txt");
+        fs::write(&file, "1").unwrap();
+        expect(&watcher, &file, ChangeKind::Created);
+        fs::write(&file, "22").unwrap();
+        expect(&watcher, &file, ChangeKind::Modified);
+        fs::remove_file(&file).unwrap();
+        expect(&watcher, &file, ChangeKind::Removed);
+
+        // 監視後に作成したディレクトリも監視します。
+        fs::create_dir(root.join("new")).unwrap();
+        expect(&watcher, &root.join("new"), ChangeKind::Created);
+        let nested = root.join("new/b.txt");
+        fs::write(&nested, "b").unwrap();
+        expect(&watcher, &nested, ChangeKind::Created);
+
+        // 名前を変えたディレクトリは新しいパスで監視します。
+        fs::rename(root.join("new"), root.join("renamed")).unwrap();
+        expect(&watcher, &root.join("renamed"), ChangeKind::Created);
+        let moved = root.join("renamed/d.txt");
+        fs::write(&moved, "d").unwrap();
+        expect(&watcher, &moved, ChangeKind::Created);
+
+        // 監視の外へ移したディレクトリは監視しません。
+        let outside = env::temp_dir().join(format!("cwago_watch_{}_out_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&outside);
+        fs::rename(root.join("renamed"), &outside).unwrap();
+        expect(&watcher, &root.join("renamed"), ChangeKind::Removed);
+        fs::write(outside.join("e.txt"), "e").unwrap();
+        while let Some(event) = watcher.recv_timeout(Duration::from_millis(200)) {
+            assert_eq!(event.kind, ChangeKind::Removed, "監視の外の変更が届きました: {:?}", event);
+        }
+        fs::remove_dir_all(&outside).unwrap();
+
+        watcher.unwatch(&root);
+        fs::write(root.join("old/c.txt"), "c").unwrap();
+        assert_eq!(watcher.recv_timeout(Duration::from_millis(200)), None, "監視を解除する必要があります。");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_native() {
+        crate::logging::init();
+        let watcher = Watcher::new(Duration::from_millis(20)).unwrap();
+        check(watcher, "native");
+    }
+
+    #[test]
+    fn test_polling() {
+        crate::logging::init();
+        let watcher = Watcher::polling(Duration::from_millis(10), Duration::from_millis(20)).unwrap();
+        assert!(!watcher.is_native());
+        check(watcher, "polling");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_overflow_resync() {
+        let root = env::temp_dir().join(format!("cwago_watch_overflow_{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("keep")).unwrap();
+        fs::write(root.join("keep/a.txt"), "1").unwrap();
+        fs::write(root.join("gone.txt"), "1").unwrap();
+
+        let native = Inotify::new().unwrap();
+        let mut state = State::default();
+        state.add_tree(&native, &root, None).unwrap();
+        scan(&root, &mut state.snapshot);
+        state.roots.push(root.clone());
+
+        // 通知を読まずに変更し、溢れて失われた場合と同じ状態にします。
+        fs::write(root.join("keep/a.txt"), "22").unwrap();
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        fs::create_dir(root.join("new")).unwrap();
+        fs::write(root.join("new/b.txt"), "b").unwrap();
+
+        let mut changes = Vec::new();
+        state.resync(&native, &mut changes);
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(changes, [
+            (root.join("gone.txt"), ChangeKind::Removed),
+            (root.join("keep/a.txt"), ChangeKind::Modified),
+            (root.join("new"), ChangeKind::Created),
+            (root.join("new/b.txt"), ChangeKind::Created),
+        ]);
+        // 作成を取りこぼしたディレクトリも監視します。
+        assert!(state.wds.values().any(|p| p == &root.join("new")), "新しいディレクトリを監視していません。");
+
+        // 通知で検出した変更は前回の状態に反映され、走査し直しても重ねて届きません。
+        fs::write(root.join("new/c.txt"), "c").unwrap();
+        state.record(&[(root.join("new/c.txt"), ChangeKind::Created)]);
+        changes.clear();
+        state.resync(&native, &mut changes);
+        assert_eq!(changes, []);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(10));
+        let start = Instant::now();
+        let later = start + Duration::from_millis(10);
+        let a = PathBuf::from("a");
+        let b = PathBuf::from("b");
+        let c = PathBuf::from("c");
+        debouncer.push(a.clone(), ChangeKind::Created, start);
+        debouncer.push(a.clone(), ChangeKind::Modified, start);
+        debouncer.push(b.clone(), ChangeKind::Created, start);
+        debouncer.push(b.clone(), ChangeKind::Removed, start);
+        debouncer.push(c.clone(), ChangeKind::Removed, start);
+        debouncer.push(c.clone(), ChangeKind::Created, later);
+
+        assert_eq!(debouncer.ready(start), []);
+        assert_eq!(debouncer.ready(later), [Event { path: a, kind: ChangeKind::Created }]);
+        assert_eq!(debouncer.ready(later + Duration::from_millis(10)), [Event { path: c, kind: ChangeKind::Modified }]);
+    }
+}
+
+// 停止の確認と、待機の最大間隔です。
+const TICK: Duration = Duration::from_millis(20);
+
+/// ポーリングで監視する場合の既定の走査間隔です。
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 変更の種類です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// 作成されました。
+    Created,
+    /// 内容が変更されました。
+    Modified,
+    /// 削除されました。
+    Removed,
+}
+impl ChangeKind {
+    // 続けて起きた変更をまとめます。打ち消し合う場合はNoneです。
+    fn merge(self, next: ChangeKind) -> Option<ChangeKind> {
+        match (self, next) {
+            (ChangeKind::Created, ChangeKind::Removed) => None,
+            (ChangeKind::Created, _) => Some(ChangeKind::Created),
+            (_, ChangeKind::Removed) => Some(ChangeKind::Removed),
+            _ => Some(ChangeKind::Modified),
+        }
+    }
+}
+
+/// 変更のイベントです。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Event {
+    /// 変更されたパスです。監視を始めたパスに相対パスを連結したものです。
+    pub path: PathBuf,
+    /// 変更の種類です。
+    pub kind: ChangeKind,
+}
+
+/// ファイルの監視です。
+/// 
+/// 専用のスレッドで変更を検出し、チャンネルに送ります。
+/// 破棄するとスレッドを停止します。
+/// 
+pub struct Watcher {
+    shared: Arc<Shared>,            // スレッドと共有する状態です。
+    events: Receiver<Event>,        // イベントの受信側です。
+    thread: Option<JoinHandle<()>>, // 監視するスレッドです。
+}
+impl Watcher {
+    /// 作成します。
+    /// 
+    /// 利用できればOSの通知を使い、使えない場合はポーリングにします。
+    /// 
+    /// # 引数
+    /// 
+    /// * `debounce` - 最後の変更からイベントを送るまでの時間です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、スレッドを作成できない場合はエラーです。
+    /// 
+    pub fn new(debounce: Duration) -> Result<Watcher> {
+        match Inotify::new() {
+            Ok(native) => Self::start(Some(native), DEFAULT_POLL_INTERVAL, debounce),
+            Err(e) => {
+                log::warn!("{}", msg!(WATCH_FALLBACK, reason = e));
+                Self::polling(DEFAULT_POLL_INTERVAL, debounce)
+            },
+        }
+    }
+
+    /// ポーリングで監視するインスタンスを作成します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `interval` - 走査する間隔です。
+    /// * `debounce` - 最後の変更からイベントを送るまでの時間です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// インスタンス、スレッドを作成できない場合はエラーです。
+    /// 
+    pub fn polling(interval: Duration, debounce: Duration) -> Result<Watcher> {
+        Self::start(None, interval, debounce)
+    }
+
+    // スレッドを開始します。
+    fn start(native: Option<Inotify>, interval: Duration, debounce: Duration) -> Result<Watcher> {
+        let shared = Arc::new(Shared {
+            stop: AtomicBool::new(false),
+            native,
+            interval,
+            state: Mutex::new(State::default()),
+        });
+        let (sender, events) = mpsc::channel();
+        let thread = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("cwago-watch".to_string())
+                .spawn(move || run(&shared, sender, debounce))
+                .map_err(|e| CwagoError::new(ErrorKind::Other, msg!(WATCH_SPAWN_FAILED, reason = e)))?
+        };
+        Ok(Watcher { shared, events, thread: Some(thread) })
+    }
+
+    /// OSの通知で監視しているかを取得します。
+    pub fn is_native(&self) -> bool {
+        self.shared.native.is_some()
+    }
+
+    /// 監視を始めます。ディレクトリは配下もすべて監視します。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - ファイルかディレクトリのパスです。
+    /// 
+    /// # 戻り値
+    /// 
+    /// 存在しない、または、監視できない場合はエラーです。
+    /// 
+    pub fn watch(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(CwagoError::new(ErrorKind::NotFound, msg!(WATCH_FAILED, path = path.display())));
+        }
+        let mut state = self.shared.state();
+        if let Some(native) = &self.shared.native {
+            state.add_tree(native, path, None)
+                .map_err(|e| CwagoError::from(e).context(msg!(WATCH_FAILED, path = path.display())))?;
+        }
+        scan(path, &mut state.snapshot);
+        state.roots.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// 監視を止めます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `path` - `watch`に渡したパスです。
+    /// 
+    pub fn unwatch(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let mut state = self.shared.state();
+        state.roots.retain(|root| root != path);
+        // 他の監視に含まれるパスは残します。
+        let roots = state.roots.clone();
+        let removed = |p: &Path| p.starts_with(path) && !roots.iter().any(|root| p.starts_with(root));
+        if let Some(native) = &self.shared.native {
+            state.wds.retain(|wd, p| {
+                let remove = removed(p);
+                if remove {
+                    native.remove(*wd);
+                }
+                !remove
+            });
+        }
+        state.snapshot.retain(|p, _| !removed(p));
+    }
+
+    /// 届いているイベントを待たずに取得します。
+    pub fn try_recv(&self) -> Option<Event> {
+        self.events.try_recv().ok()
+    }
+
+    /// 届いているイベントを待たずに走査します。
+    pub fn try_iter(&self) -> TryIter<'_, Event> {
+        self.events.try_iter()
+    }
+
+    /// イベントが届くまで待ちます。
+    /// 
+    /// # 引数
+    /// 
+    /// * `timeout` - 待つ最大の時間です。
+    /// 
+    /// # 戻り値
+    /// 
+    /// イベント、時間内に届かない場合はNoneです。
+    /// 
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// イベントの受信側を取得します。
+    pub fn receiver(&self) -> &Receiver<Event> {
+        &self.events
+    }
+}
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// スレッドと共有する状態です。
+struct Shared {
+    stop: AtomicBool,        // 停止の要求です。
+    native: Option<Inotify>, // OSの通知です。Noneはポーリングです。
+    interval: Duration,      // ポーリングの間隔です。
+    state: Mutex<State>,     // 監視の対象です。
+}
+impl Shared {
+    // 状態をロックします。
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// 監視の対象です。
+#[derive(Default)]
+struct State {
+    roots: Vec<PathBuf>,                // 監視を始めたパスです。
+    wds: HashMap<i32, PathBuf>,         // inotifyの監視とパスの対応です。
+    snapshot: HashMap<PathBuf, Stamp>,  // 前回走査した状態です。OSの通知では溢れた際の比較に使います。
+}
+impl State {
+    // パスと配下を監視します。changesがある場合は配下の項目を作成として記録します。
+    fn add_tree(&mut self, native: &Inotify, path: &Path, mut changes: Option<&mut Vec<(PathBuf, ChangeKind)>>) -> io::Result<()> {
+        let wd = native.add(path)?;
+        self.wds.insert(wd, path.to_path_buf());
+        if !path.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let child = entry.path();
+            if let Some(changes) = changes.as_deref_mut() {
+                changes.push((child.clone(), ChangeKind::Created));
+            }
+            if entry.file_type()?.is_dir() {
+                self.add_tree(native, &child, changes.as_deref_mut())?;
+            }
+        }
+        Ok(())
+    }
+
+    // パスと配下の監視を解除します。
+    fn remove_tree(&mut self, native: &Inotify, path: &Path) {
+        self.wds.retain(|wd, p| {
+            let remove = p.starts_with(path);
+            if remove {
+                native.remove(*wd);
+            }
+            !remove
+        });
+    }
+
+    // OSの通知で検出した変更を前回の状態に反映します。
+    fn record(&mut self, changes: &[(PathBuf, ChangeKind)]) {
+        for (path, kind) in changes {
+            match (kind, Stamp::read(path)) {
+                (ChangeKind::Removed, _) | (_, None) => self.snapshot.retain(|p, _| !p.starts_with(path)),
+                (_, Some(stamp)) => {
+                    self.snapshot.insert(path.clone(), stamp);
+                },
+            }
+        }
+    }
+
+    // 通知が溢れた後に監視を張り直し、走査した差分を記録します。
+    fn resync(&mut self, native: &Inotify, changes: &mut Vec<(PathBuf, ChangeKind)>) {
+        // 取りこぼした作成のディレクトリを監視し、無くなったパスの監視を解除します。
+        self.wds.retain(|wd, p| {
+            let exists = p.exists();
+            if !exists {
+                native.remove(*wd);
+            }
+            exists
+        });
+        for root in self.roots.clone() {
+            if let Err(e) = self.add_tree(native, &root, None) {
+                log::warn!("{}", CwagoError::from(e).context(msg!(WATCH_FAILED, path = root.display())));
+            }
+        }
+        self.rescan(changes);
+    }
+
+    // 走査し、前回との差分を記録します。
+    fn rescan(&mut self, changes: &mut Vec<(PathBuf, ChangeKind)>) {
+        let mut current = HashMap::with_capacity(self.snapshot.len());
+        for root in &self.roots {
+            scan(root, &mut current);
+        }
+        for (path, stamp) in &current {
+            match self.snapshot.get(path) {
+                None => changes.push((path.clone(), ChangeKind::Created)),
+                Some(old) if !stamp.dir && old != stamp => changes.push((path.clone(), ChangeKind::Modified)),
+                Some(_) => {},
+            }
+        }
+        for path in self.snapshot.keys() {
+            if !current.contains_key(path) {
+                changes.push((path.clone(), ChangeKind::Removed));
+            }
+        }
+        self.snapshot = current;
+    }
+}
+
+// ポーリングで比較する項目の状態です。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>, // 更新日時です。
+    len: u64,                     // バイト数です。
+    dir: bool,                    // ディレクトリかです。
+}
+
+impl Stamp {
+    // パスの状態を取得します。存在しない場合はNoneです。
+    fn read(path: &Path) -> Option<Stamp> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Stamp { modified: metadata.modified().ok(), len: metadata.len(), dir: metadata.is_dir() })
+    }
+}
+
+// パスと配下の状態を記録します。
+fn scan(path: &Path, snapshot: &mut HashMap<PathBuf, Stamp>) {
+    let Some(stamp) = Stamp::read(path) else {
+        return;
+    };
+    snapshot.insert(path.to_path_buf(), stamp);
+    if let Some(entries) = stamp.dir.then(|| fs::read_dir(path).ok()).flatten() {
+        for entry in entries.flatten() {
+            scan(&entry.path(), snapshot);
+        }
+    }
+}
+
+// 連続する変更をまとめます。
+struct Debouncer {
+    delay: Duration,                                 // 静まるまでの時間です。
+    pending: HashMap<PathBuf, (ChangeKind, Instant)>, // まとめている変更と最後の変更の時刻です。
+}
+impl Debouncer {
+    fn new(delay: Duration) -> Debouncer {
+        Debouncer { delay, pending: HashMap::new() }
+    }
+
+    // 変更を記録します。
+    fn push(&mut self, path: PathBuf, kind: ChangeKind, now: Instant) {
+        match self.pending.entry(path) {
+            Entry::Vacant(entry) => {
+                entry.insert((kind, now));
+            },
+            Entry::Occupied(mut entry) => match entry.get().0.merge(kind) {
+                Some(kind) => *entry.get_mut() = (kind, now),
+                None => {
+                    entry.remove();
+                },
+            },
+        }
+    }
+
+    // 静まった変更をパスの順に取り出します。
+    fn ready(&mut self, now: Instant) -> Vec<Event> {
+        let mut events = Vec::new();
+        self.pending.retain(|path, (kind, time)| {
+            let ready = now.saturating_duration_since(*time) >= self.delay;
+            if ready {
+                events.push(Event { path: path.clone(), kind: *kind });
+            }
+            !ready
+        });
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+        events
+    }
+}
+
+// 監視するスレッドの処理です。
+fn run(shared: &Shared, sender: Sender<Event>, debounce: Duration) {
+    let mut debouncer = Debouncer::new(debounce);
+    let mut changes = Vec::new();
+    let mut last_scan = Instant::now();
+    let mut buffer = vec![0u8; 4096];
+    while !shared.stop.load(Ordering::Acquire) {
+        match &shared.native {
+            Some(native) => {
+                if native.wait(TICK) {
+                    let mut state = shared.state();
+                    let overflowed = native.read(&mut state, &mut buffer, &mut changes);
+                    state.record(&changes);
+                    if overflowed {
+                        // 溢れた分の通知は失われているため、走査した差分で補います。
+                        log::warn!("{}", msg!(WATCH_OVERFLOW));
+                        state.resync(native, &mut changes);
+                    }
+                }
+            },
+            None => {
+                thread::sleep(TICK.min(shared.interval));
+                if last_scan.elapsed() >= shared.interval {
+                    shared.state().rescan(&mut changes);
+                    last_scan = Instant::now();
+                }
+            },
+        }
+
+        let now = Instant::now();
+        for (path, kind) in changes.drain(..) {
+            debouncer.push(path, kind, now);
+        }
+        for event in debouncer.ready(now) {
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// inotifyのファイル記述子です。
+#[cfg(target_os = "linux")]
+struct Inotify {
+    fd: i32, // ファイル記述子です。
+}
+#[cfg(target_os = "linux")]
+impl Inotify {
+    // 監視する変更です。
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MODIFY
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF;
+
+    // inotify_event構造体のnameより前のバイト数です。
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+    fn new() -> io::Result<Inotify> {
+        // Safety: 引数はフラグだけです。
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Inotify { fd })
+    }
+
+    // パスを監視し、監視の識別子を返します。
+    fn add(&self, path: &Path) -> io::Result<i32> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // Safety: pathはNUL終端された文字列です。
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), Self::MASK) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(wd)
+    }
+
+    // 監視を解除します。
+    fn remove(&self, wd: i32) {
+        // Safety: 無効な識別子はエラーを返すだけです。
+        unsafe { libc::inotify_rm_watch(self.fd, wd) };
+    }
+
+    // イベントが届くまで待ちます。届いた場合はtrueです。
+    fn wait(&self, timeout: Duration) -> bool {
+        let mut fds = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+        // Safety: fdsは1つの有効なpollfdです。
+        let ready = unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) };
+        ready > 0
+    }
+
+    // 届いているイベントを読み込み、変更を記録します。通知が溢れていた場合はtrueです。
+    fn read(&self, state: &mut State, buffer: &mut [u8], changes: &mut Vec<(PathBuf, ChangeKind)>) -> bool {
+        use std::{
+            ffi::OsStr,
+            os::unix::ffi::OsStrExt
+        };
+
+        let mut overflowed = false;
+        loop {
+            // Safety: bufferは書き込み可能な領域です。
+            let len = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if len <= 0 {
+                return overflowed;
+            }
+            let mut at = 0;
+            while at + Self::HEADER <= len as usize {
+                // Safety: カーネルは完全なイベントだけを書き込みます。境界は揃っていない場合があります。
+                let event: libc::inotify_event = unsafe { buffer.as_ptr().add(at).cast::<libc::inotify_event>().read_unaligned() };
+                let name = &buffer[at + Self::HEADER..at + Self::HEADER + event.len as usize];
+                at += Self::HEADER + event.len as usize;
+
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    overflowed = true;
+                    continue;
+                }
+                if event.mask & libc::IN_IGNORED != 0 {
+                    state.wds.remove(&event.wd);
+                    continue;
+                }
+                let Some(dir) = state.wds.get(&event.wd) else {
+                    continue;
+                };
+                let name = name.split(|b| *b == 0).next().unwrap_or_default();
+                let path = if name.is_empty() { dir.clone() } else { dir.join(OsStr::from_bytes(name)) };
+
+                // 移動したディレクトリの監視は元のパスを指したままになるため解除します。
+                // 監視の中へ移動した場合は、移動先の作成として監視し直します。
+                if event.mask & libc::IN_MOVED_FROM != 0 && event.mask & libc::IN_ISDIR != 0 {
+                    state.remove_tree(self, &path);
+                }
+
+                let kind = if event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                    ChangeKind::Created
+                } else if event.mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+                    ChangeKind::Removed
+                } else if event.mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0 {
+                    // 配下の削除と移動は親ディレクトリの監視で通知されます。
+                    if !state.roots.contains(&path) {
+                        continue;
+                    }
+                    if event.mask & libc::IN_MOVE_SELF != 0 {
+                        state.remove_tree(self, &path);
+                    }
+                    ChangeKind::Removed
+                } else {
+                    ChangeKind::Modified
+                };
+                if kind == ChangeKind::Created && event.mask & libc::IN_ISDIR != 0 {
+                    // 監視を始める前に作成された項目を取りこぼさないよう、配下も作成として扱います。
+                    let _ = state.add_tree(self, &path, Some(changes));
+                }
+                changes.push((path, kind));
+            }
+        }
+    }
+}
+#[cfg(target_os = "linux")]
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        // Safety: fdはこのインスタンスだけが所有しています。
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+// inotifyを使えない環境では常にポーリングで監視します。
+#[cfg(not(target_os = "linux"))]
+enum Inotify {}
+#[cfg(not(target_os = "linux"))]
+impl Inotify {
+    fn new() -> io::Result<Inotify> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "inotify"))
+    }
+
+    fn add(&self, _path: &Path) -> io::Result<i32> {
+        match *self {}
+    }
+
+    fn remove(&self, _wd: i32) {
+        match *self {}
+    }
+
+    fn wait(&self, _timeout: Duration) -> bool {
+        match *self {}
+    }
+
+    fn read(&self, _state: &mut State, _buffer: &mut [u8], _changes: &mut Vec<(PathBuf, ChangeKind)>) -> bool {
+        match *self {}
+    }
+}
@@ -0,0 +1,74 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/tests/cwago_pack.rs
+// (C) 2023 CwagoCommunity.
+//
+//! パック作成コマンドで作成したパックを読み戻せることを確認します。
+// =========================
+
+use std::{
+    env,
+    fs,
+    process::{
+        self,
+        Command
+    }
+};
+
+use cwago_utility::pack::{
+    Compression,
+    PackReader
+};
+
+#[test]
+fn test_cwago_pack() {
+    let root = env::temp_dir().join(format!("cwago_pack_cli_{}", process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let input = root.join("assets");
+    fs::create_dir_all(input.join("textures/ui")).unwrap();
+    let text = "cwago ".repeat(200);
+    let binary = (0..=255u8).collect::<Vec<_>>();
+    fs::write(input.join("readme.txt"), &text).unwrap();
+    fs::write(input.join("textures/ui/button.bin"), &binary).unwrap();
+    fs::write(input.join("empty"), b"").unwrap();
+
+    let output = root.join("assets.cwpk");
+    let status = Command::new(env!("CARGO_BIN_EXE_cwago_pack"))
+        .arg(&input)
+        .arg(&output)
+        .args(["--align", "16"])
+        .status()
+        .expect("コマンドを実行できませんでした。");
+    assert!(status.success(), "パックの作成に失敗しました。");
+
+    let reader = PackReader::open(&output).expect("作成したパックを開けませんでした。");
+    let mut paths = reader.entries().map(|e| e.path()).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec!["empty", "readme.txt", "textures/ui/button.bin"]);
+    assert_eq!(reader.read("readme.txt").unwrap(), text.as_bytes());
+    assert_eq!(reader.read("textures/ui/button.bin").unwrap(), binary);
+    assert!(reader.read("empty").unwrap().is_empty());
+    let readme = reader.find("readme.txt").unwrap();
+    assert_eq!(readme.compression(), Compression::Lz4);
+    assert!(readme.stored_size() < readme.size(), "圧縮されていません。");
+    assert!(reader.entries().all(|e| e.offset() % 16 == 0), "整列されていません。");
+
+    let list = Command::new(env!("CARGO_BIN_EXE_cwago_pack"))
+        .arg("--list")
+        .arg(&output)
+        .output()
+        .expect("コマンドを実行できませんでした。");
+    assert!(list.status.success(), "パックの一覧を表示できませんでした。");
+    let list = String::from_utf8(list.stdout).unwrap();
+    assert!(paths.iter().all(|p| list.lines().any(|l| l.ends_with(p))), "{}", list);
+
+    let usage = Command::new(env!("CARGO_BIN_EXE_cwago_pack"))
+        .arg(&input)
+        .output()
+        .expect("コマンドを実行できませんでした。");
+    assert!(!usage.status.success(), "引数の誤りが成功しました。");
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,44 @@
+// -------------------------
+//
+// Cwago.
+//
+// cwago/cwago_utility/tests/foreign_logger.rs
+// (C) 2023 CwagoCommunity.
+//
+//! 他のロガーが先に設定された場合に、その上限レベルを変更しないことを確認します。
+// =========================
+
+use cwago_utility::{
+    log::{
+        self,
+        LevelFilter,
+        Log,
+        Metadata,
+        Record
+    },
+    logging
+};
+
+// 何も出力しないロガーです。
+struct Foreign;
+impl Log for Foreign {
+    fn enabled(&self, _: &Metadata) -> bool {
+        false
+    }
+
+    fn log(&self, _: &Record) {}
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn test_foreign_logger() {
+    log::set_logger(&Foreign).unwrap();
+    log::set_max_level(LevelFilter::Error);
+
+    logging::init();
+    logging::logger().set_default_level(LevelFilter::Trace);
+    logging::logger().set_level("cwago", LevelFilter::Debug);
+    logging::logger().apply_spec("trace");
+    assert_eq!(log::max_level(), LevelFilter::Error, "他のロガーの上限レベルを変更してはいけません。");
+}